@@ -0,0 +1,52 @@
+use rgraphics::graphics::draw::{Color, Renderer, Transform2D};
+use rgraphics::Raymond;
+use rgraphics::EventHandler;
+use rgraphics::context::Context;
+
+pub struct MyWindow {
+    radius: f32,
+    speed: f32,
+    color: [f32; 3],
+    angle: f32
+}
+
+impl Default for MyWindow {
+    fn default() -> Self {
+        Self { radius: 0.2, speed: 1.0, color: [0.2, 0.6, 1.0], angle: 0.0 }
+    }
+}
+
+impl EventHandler for MyWindow {
+    fn on_update(&mut self, _ctx: &mut Context, delta: f32) {
+        self.angle += self.speed * delta;
+    }
+
+    // drawn over the scene, clicks and typing inside the egui window never reach on_input_event
+    fn on_egui(&mut self, ctx: &mut Context, egui: &egui::Context) {
+        egui::Window::new("Inspector").show(egui, |ui| {
+            if let Some(fps) = ctx.frame_stats().fps() {
+                ui.label(format!("{:.0} fps", fps));
+            }
+            ui.add(egui::Slider::new(&mut self.radius, 0.05..=0.5).text("radius"));
+            ui.add(egui::Slider::new(&mut self.speed, -5.0..=5.0).text("speed"));
+            ui.color_edit_button_rgb(&mut self.color);
+        });
+    }
+
+    fn on_draw(&mut self, renderer: &mut Renderer, _alpha: f32) {
+        renderer.set_background_color(Color::BLACK);
+        let position = glam::Vec2::from_angle(self.angle) * 0.5;
+        let [r, g, b] = self.color;
+        renderer.draw_circle(Transform2D::at(position.x, position.y), self.radius, 64, Color::new(r, g, b, 1.0));
+    }
+}
+
+fn main() {
+
+    let my_game = MyWindow::default();
+
+    Raymond::create_window(600, 800, "egui inspector", Box::new(my_game))
+        .set_target_fps(60)
+        .run();
+
+}
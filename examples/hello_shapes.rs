@@ -1,25 +1,74 @@
 use glam::Vec2;
-use rgraphics::graphics::draw::{Color, Renderer, Transform2D};
+use rgraphics::graphics::draw::{Color, ColorStop, Paint, Rect, Renderer, SpreadMode, Transform2D};
+use rgraphics::graphics::gpu::Vertex;
+use rgraphics::graphics::layer::LayerId;
+use rgraphics::graphics::mesh::{CornerRadii, LineCap, Mesh, MeshHandle, StrokeStyle};
 use rgraphics::Raymond;
 use rgraphics::EventHandler;
 
-pub struct MyWindow;
+#[derive(Default)]
+pub struct MyWindow {
+    star: Option<MeshHandle>,
+    rainbow: Option<MeshHandle>,
+    overlay: Option<LayerId>
+}
 
 impl EventHandler for MyWindow {
-    fn on_draw(&mut self, renderer: &mut Renderer) {
+    fn on_draw(&mut self, renderer: &mut Renderer, _alpha: f32) {
+        // punch a round hole through the square
+        renderer.begin_inverted_mask(Transform2D::at(0.10, 0.10), Mesh::new_circle(0.1, 48));
         renderer.draw_rectangle(Transform2D::at(0.10, 0.10), Vec2::new(0.5, 0.5), Color::GREEN);
-        renderer.draw_circle(Transform2D::at(0.25, 0.65), 0.25, 32, Color::RED);
+        renderer.end_mask();
+        // only the left half of the circle survives the clip
+        renderer.push_clip_rect(Rect::new(Vec2::new(-1.0, 0.0), Vec2::new(0.25, 1.0)));
+        renderer.draw_circle(Transform2D::at(0.25, 0.65), 0.25, 32, Paint::radial_gradient(Vec2::ZERO, 0.25, vec![
+            ColorStop::new(0.0, Color::WHITE),
+            ColorStop::new(1.0, Color::RED)
+        ]));
+        renderer.pop_clip_rect();
         renderer.draw_triangle(Transform2D::at(0.25, 0.25), Color::BLUE);
+
+        // per vertex colors are blended across the face
+        let rainbow = *self.rainbow.get_or_insert_with(|| renderer.create_mesh(Mesh::new(vec![
+            Vertex::new([0.0, 0.15, 0.0], [0.0, 0.0]).with_color(Color::RED),
+            Vertex::new([0.15, -0.15, 0.0], [0.0, 0.0]).with_color(Color::GREEN),
+            Vertex::new([-0.15, -0.15, 0.0], [0.0, 0.0]).with_color(Color::BLUE)
+        ], vec![0u16, 1, 2])));
+        renderer.draw_mesh(Transform2D::at(-0.6, 0.0), &rainbow, Color::WHITE, None);
+        let stripes = Paint::linear_gradient(Vec2::new(-0.2, 0.0), Vec2::new(-0.1, 0.0), vec![
+            ColorStop::new(0.0, Color::GREEN),
+            ColorStop::new(1.0, Color::BLUE)
+        ]).with_spread(SpreadMode::Reflect);
+        renderer.draw_rounded_rectangle(Transform2D::at(-0.6, 0.6), Vec2::new(0.4, 0.25), CornerRadii::new(0.05, 0.1, 0.0, 0.1), stripes);
+        renderer.draw_pie(Transform2D::at(0.6, -0.6), 0.2, 0.0, 1.5 * std::f32::consts::PI, Paint::conic_gradient(Vec2::ZERO, 0.0, vec![
+            ColorStop::new(0.0, Color::BLUE),
+            ColorStop::new(0.5, Color::RED),
+            ColorStop::new(1.0, Color::BLUE)
+        ]));
+        renderer.stroke_rectangle(Transform2D::at(0.10, 0.10), Vec2::new(0.55, 0.55), StrokeStyle::dashed(0.01, vec![0.04, 0.02]).with_line_cap(LineCap::Round), Color::WHITE);
+        // the overlay layer draws above everything else no matter when it is used
+        let overlay = *self.overlay.get_or_insert_with(|| renderer.add_layer("overlay", 10));
+        renderer.set_layer(overlay);
+        renderer.stroke_star(Transform2D::at(-0.1, -0.6), 5, 0.2, 0.08, 0.01, Color::RED);
+        renderer.set_layer(LayerId::DEFAULT);
+
+        // static geometry is uploaded once and reused every frame
+        let star = *self.star.get_or_insert_with(|| renderer.create_mesh(Mesh::new_polygon(vec![
+            (0.0, 0.2).into(), (0.05, 0.05).into(), (0.2, 0.0).into(), (0.05, -0.05).into(),
+            (0.0, -0.2).into(), (-0.05, -0.05).into(), (-0.2, 0.0).into(), (-0.05, 0.05).into()
+        ])));
+        renderer.draw_mesh(Transform2D::at(-0.5, -0.5), &star, Color::WHITE, None);
         
     }
 }
 
 fn main() {
     
-    let my_game = MyWindow {};
+    let my_game = MyWindow::default();
 
     Raymond::create_window(800, 800, "Hello Triangle", Box::new(my_game))
         .set_target_fps(60)
+        .set_msaa_samples(4)
         .run();
 
-}
\ No newline at end of file
+}
@@ -5,7 +5,7 @@ use rgraphics::EventHandler;
 pub struct MyWindow;
 
 impl EventHandler for MyWindow {
-    fn on_draw(&mut self, renderer: &mut Renderer) {
+    fn on_draw(&mut self, renderer: &mut Renderer, _alpha: f32) {
         renderer.draw_triangle(Transform2D::at(0.0, 0.0), Color::GREEN);
     }
 }
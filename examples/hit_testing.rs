@@ -0,0 +1,93 @@
+use glam::Vec2;
+use lyon::path::Path;
+use rgraphics::graphics::draw::{Color, CoordinateSpace, Renderer, Transform2D};
+use rgraphics::graphics::geometry::Shape;
+use rgraphics::graphics::mesh::Mesh;
+use rgraphics::Raymond;
+use rgraphics::{EventHandler, InputEvent};
+use rgraphics::context::Context;
+
+pub struct MyWindow {
+    // polygons keep the path they were flattened from to be drawn with
+    shapes: Vec<(Shape, Option<Path>, Transform2D)>,
+    cursor: Vec2,
+    time: f32
+}
+
+impl Default for MyWindow {
+    fn default() -> Self {
+        Self {
+            shapes: vec![
+                (Shape::Rectangle(Vec2::new(160.0, 90.0)), None, Transform2D::at(180.0, 200.0)),
+                (Shape::Circle(60.0), None, Transform2D::at(450.0, 200.0).with_scale(Vec2::new(1.6, 0.8))),
+                Self::polygon(Mesh::star_path(5, 90.0, 40.0), Transform2D::at(200.0, 480.0)),
+                Self::polygon(Mesh::regular_polygon_path(70.0, 3), Transform2D::at(480.0, 480.0).with_skew(Vec2::new(0.4, 0.0)))
+            ],
+            cursor: Vec2::ZERO,
+            time: 0.0
+        }
+    }
+}
+
+impl MyWindow {
+    fn polygon(path: Path, transform: Transform2D) -> (Shape, Option<Path>, Transform2D) {
+        (Shape::from_path(&path, 0.5), Some(path), transform)
+    }
+}
+
+impl EventHandler for MyWindow {
+    fn on_input_event(&mut self, _ctx: &mut Context, event: InputEvent) {
+        if let InputEvent::CursorMoved(_, position) = event {
+            self.cursor = position;
+        }
+    }
+
+    fn on_update(&mut self, _ctx: &mut Context, delta: f32) {
+        self.time += delta;
+        for (index, (_, _, transform)) in self.shapes.iter_mut().enumerate() {
+            transform.rotation = self.time * (0.2 + index as f32 * 0.15);
+        }
+    }
+
+    fn on_draw(&mut self, renderer: &mut Renderer, _alpha: f32) {
+        renderer.set_coordinate_space(CoordinateSpace::Logical);
+        renderer.set_background_color(Color::new(0.1, 0.1, 0.12, 1.0));
+
+        // shapes touching the circle around the cursor turn orange
+        let probe = Shape::Circle(30.0);
+        let probe_transform = Transform2D::at(self.cursor.x, self.cursor.y);
+
+        let mut first_commands = Vec::with_capacity(self.shapes.len());
+        for (shape, path, transform) in &self.shapes {
+            first_commands.push(renderer.commands.len());
+            let color = if shape.overlaps(transform, &probe, &probe_transform) { Color::new(1.0, 0.6, 0.2, 1.0) } else { Color::new(0.3, 0.5, 0.9, 1.0) };
+            match (shape, path) {
+                (Shape::Rectangle(size), _) => renderer.draw_rectangle(*transform, *size, color),
+                (Shape::Circle(radius), _) => renderer.draw_circle(*transform, *radius, 64, color),
+                (_, Some(path)) => renderer.fill_path(*transform, path.clone(), 90.0, color),
+                _ => renderer
+            };
+        }
+        renderer.stroke_circle(probe_transform, 30.0, 1.0, Color::WHITE);
+
+        // the renderer finds the topmost command under the cursor, which gets its bounding boxes drawn
+        let hovered = renderer.command_at(self.cursor)
+            .and_then(|command| first_commands.iter().position(|first| *first == command));
+        if let Some((shape, _, transform)) = hovered.map(|index| &self.shapes[index]) {
+            let bounds = shape.bounds(transform);
+            renderer.stroke_rectangle(Transform2D::at((bounds.min.x + bounds.max.x) * 0.5, (bounds.min.y + bounds.max.y) * 0.5), bounds.size(), 1.0, Color::new(0.5, 0.5, 0.5, 1.0));
+            let oriented = shape.oriented_bounds(transform);
+            renderer.stroke_rectangle(oriented.transform(), oriented.size(), 2.0, Color::WHITE);
+        }
+    }
+}
+
+fn main() {
+
+    let my_game = MyWindow::default();
+
+    Raymond::create_window(700, 700, "Hit testing", Box::new(my_game))
+        .set_target_fps(60)
+        .run();
+
+}
@@ -1,33 +1,39 @@
 use rgraphics::graphics::draw::{Image, Renderer, Transform2D};
+use rgraphics::tween::{Easing, Tween};
 use rgraphics::Raymond;
 use rgraphics::EventHandler;
+use rgraphics::context::Context;
 use std::sync::Arc;
 use glam::Vec2;
 
 pub struct MyWindow {
     tintin: Arc<Image>,
-    transform: Transform2D
+    transform: Transform2D,
+    tween: Tween<Transform2D>
 }
 
 impl Default for MyWindow {
     fn default() -> Self {
         Self {
             tintin: Arc::new(Image::from_file("C:/Workspace/rgraphics/examples/assets/tintin.jpg")),
-            transform: Transform2D::at(-1.0, 0.0)
+            // images are drawn at their size in logical pixels, scaled down to fit the window
+            transform: Transform2D::at(-1.0, 0.0).with_scale(Vec2::splat(0.4)),
+            // glide across and back, easing in and out at either edge
+            tween: Tween::new(Transform2D::at(-1.0, 0.0).with_scale(Vec2::splat(0.4)), Transform2D::at(1.0, 0.0).with_scale(Vec2::splat(0.4)), 10.0)
+                .with_easing(Easing::SineInOut)
+                .with_yoyo(true)
+                .repeat_forever()
         }
     }
 }
 
 impl EventHandler for MyWindow {
-    fn on_update(&mut self, delta: f32) {
-        self.transform.position.x += 0.1 * delta;
-        if self.transform.position.x > 1.0 {
-            self.transform.position.x = -1.0;
-        }
+    fn on_update(&mut self, _ctx: &mut Context, delta: f32) {
+        self.transform = self.tween.update(delta);
     }
 
-    fn on_draw(&mut self, renderer: &mut Renderer) {
-        renderer.draw_image(self.transform.clone(), self.tintin.clone());
+    fn on_draw(&mut self, renderer: &mut Renderer, _alpha: f32) {
+        renderer.draw_image(self.transform, self.tintin.clone());
     }
 
 }
@@ -0,0 +1,55 @@
+use rgraphics::graphics::draw::{Color, Renderer, Transform2D};
+use rgraphics::Raymond;
+use rgraphics::{EventHandler, InputEvent};
+use rgraphics::context::Context;
+use winit::dpi::LogicalSize;
+use winit::keyboard::KeyCode;
+use winit::window::Window;
+
+pub struct Palette {
+    color: Color
+}
+
+impl EventHandler for Palette {
+    fn on_draw(&mut self, renderer: &mut Renderer, _alpha: f32) {
+        renderer.draw_circle(Transform2D::at(0.0, 0.0), 0.5, 32, self.color);
+    }
+}
+
+pub struct Viewport {
+    palettes: u32
+}
+
+impl EventHandler for Viewport {
+    fn on_init(&mut self, ctx: &mut Context) {
+        ctx.set_title("Viewport - press P to open a palette");
+    }
+
+    fn on_input_event(&mut self, ctx: &mut Context, event: InputEvent) {
+        if let InputEvent::KeyboardInput(_, KeyCode::KeyP) = event {
+            self.palettes += 1;
+            let colors = [Color::RED, Color::GREEN, Color::BLUE];
+            let color = colors[self.palettes as usize % colors.len()];
+            ctx.open_window(
+                Window::default_attributes()
+                    .with_title(format!("Palette {}", self.palettes))
+                    .with_inner_size(LogicalSize::new(200, 200)),
+                Box::new(Palette { color })
+            );
+        }
+    }
+
+    fn on_draw(&mut self, renderer: &mut Renderer, _alpha: f32) {
+        renderer.draw_triangle(Transform2D::at(0.0, 0.0), Color::GREEN);
+    }
+}
+
+fn main() {
+
+    let viewport = Viewport { palettes: 0 };
+
+    Raymond::create_window(600, 800, "Viewport", Box::new(viewport))
+        .set_target_fps(60)
+        .run();
+
+}
@@ -0,0 +1,45 @@
+use std::sync::Arc;
+use glam::Vec2;
+use rgraphics::graphics::draw::{Color, CoordinateSpace, Image, Renderer, Transform2D};
+use rgraphics::graphics::mesh::{NineSlice, SliceFill};
+use rgraphics::Raymond;
+use rgraphics::EventHandler;
+use rgraphics::context::Context;
+
+pub struct MyWindow {
+    image: Arc<Image>,
+    time: f32
+}
+
+impl Default for MyWindow {
+    fn default() -> Self {
+        Self { image: Arc::new(Image::from_file("examples/assets/tintin.jpg")), time: 0.0 }
+    }
+}
+
+impl EventHandler for MyWindow {
+    fn on_update(&mut self, _ctx: &mut Context, delta: f32) {
+        self.time += delta;
+    }
+
+    fn on_draw(&mut self, renderer: &mut Renderer, _alpha: f32) {
+        renderer.set_coordinate_space(CoordinateSpace::Logical);
+        renderer.set_background_color(Color::BLACK);
+
+        // the panels grow and shrink, the 60 pixel borders stay the same size
+        let grow = self.time.sin() * 0.5 + 0.5;
+        let size = Vec2::new(200.0 + grow * 300.0, 150.0 + grow * 100.0);
+        renderer.draw_nine_slice(Transform2D::at(350.0, 180.0), self.image.clone(), 60.0, size);
+        renderer.draw_nine_slice(Transform2D::at(350.0, 520.0), self.image.clone(), NineSlice::uniform(60.0).with_fill(SliceFill::Tile), size);
+    }
+}
+
+fn main() {
+
+    let my_game = MyWindow::default();
+
+    Raymond::create_window(700, 700, "Nine slice", Box::new(my_game))
+        .set_target_fps(60)
+        .run();
+
+}
@@ -0,0 +1,54 @@
+use glam::Vec2;
+use rgraphics::graphics::draw::{Color, Renderer, Transform2D};
+use rgraphics::graphics::particles::{Burst, EmitterConfig, EmitterShape, Keyframe, ParticleEmitter};
+use rgraphics::Raymond;
+use rgraphics::EventHandler;
+use rgraphics::context::Context;
+
+pub struct MyWindow {
+    fountain: ParticleEmitter
+}
+
+impl Default for MyWindow {
+    fn default() -> Self {
+        // a fountain that sprays up, falls back down and fades from yellow to a transparent red
+        let config = EmitterConfig {
+            seed: 7,
+            rate: 300.0,
+            bursts: vec![Burst { time: 0.0, count: 100 }],
+            shape: EmitterShape::Circle { radius: 0.02 },
+            lifetime: (1.0, 2.0),
+            speed: (0.8, 1.2),
+            spread: 0.6,
+            gravity: Vec2::new(0.0, -1.5),
+            size: (0.02, 0.04),
+            size_over_life: vec![Keyframe::new(0.0, 1.0), Keyframe::new(1.0, 0.2)],
+            color_over_life: vec![Keyframe::new(0.0, Color::new(1.0, 0.9, 0.2, 1.0)), Keyframe::new(1.0, Color::new(1.0, 0.1, 0.0, 0.0))],
+            angular_velocity: (-4.0, 4.0),
+            ..Default::default()
+        };
+
+        Self { fountain: ParticleEmitter::new(config).with_position(Vec2::new(0.0, -0.8)) }
+    }
+}
+
+impl EventHandler for MyWindow {
+    fn on_update(&mut self, _ctx: &mut Context, delta: f32) {
+        self.fountain.update(delta);
+    }
+
+    fn on_draw(&mut self, renderer: &mut Renderer, _alpha: f32) {
+        renderer.set_background_color(Color::BLACK);
+        self.fountain.draw(renderer, Transform2D::default());
+    }
+}
+
+fn main() {
+
+    let my_game = MyWindow::default();
+
+    Raymond::create_window(600, 600, "Particles", Box::new(my_game))
+        .set_target_fps(60)
+        .run();
+
+}
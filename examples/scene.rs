@@ -0,0 +1,63 @@
+use glam::Vec2;
+use rgraphics::graphics::draw::{Color, Renderer, Transform2D};
+use rgraphics::scene::{Drawable, Node, NodeId, SceneGraph, Shape};
+use rgraphics::Raymond;
+use rgraphics::EventHandler;
+use rgraphics::context::Context;
+
+pub struct MyWindow {
+    scene: SceneGraph,
+    sun: NodeId
+}
+
+impl Default for MyWindow {
+    fn default() -> Self {
+        let mut scene = SceneGraph::new();
+
+        // the moon orbits the planet which orbits the sun, each only spins its own node
+        let sun = scene.add(Node::named("sun").with_drawable(Drawable::fill(Shape::Circle(0.2), Color::new(1.0, 0.8, 0.0, 1.0))));
+        let orbit = scene.add_child(sun, Node::named("orbit")).unwrap();
+        let planet = scene.add_child(orbit, Node::named("planet")
+            .with_transform(Transform2D::at(0.6, 0.0))
+            .with_drawable(Drawable::fill(Shape::Circle(0.08), Color::BLUE))).unwrap();
+        scene.add_child(planet, Node::named("moon")
+            .with_transform(Transform2D::at(0.15, 0.0))
+            .with_drawable(Drawable::fill(Shape::Circle(0.03), Color::WHITE)));
+        scene.add_child(sun, Node::named("ring")
+            .with_drawable(Drawable::stroke(Shape::Ellipse(Vec2::splat(0.6)), 0.005, Color::GREEN)));
+
+        Self { scene, sun }
+    }
+}
+
+impl EventHandler for MyWindow {
+    fn on_update(&mut self, _ctx: &mut Context, delta: f32) {
+        if let Some(orbit) = self.scene.find("orbit").and_then(|id| self.scene.get_mut(id)) {
+            orbit.transform.rotation += 0.5 * delta;
+        }
+        if let Some(planet) = self.scene.find("planet").and_then(|id| self.scene.get_mut(id)) {
+            planet.transform.rotation += 2.0 * delta;
+        }
+        // drift the whole system sideways so it leaves the screen and gets culled
+        if let Some(sun) = self.scene.get_mut(self.sun) {
+            sun.transform.position.x += 0.1 * delta;
+            if sun.transform.position.x > 2.0 {
+                sun.transform.position.x = -2.0;
+            }
+        }
+    }
+
+    fn on_draw(&mut self, renderer: &mut Renderer, _alpha: f32) {
+        self.scene.draw(renderer);
+    }
+}
+
+fn main() {
+
+    let my_game = MyWindow::default();
+
+    Raymond::create_window(600, 600, "Scene", Box::new(my_game))
+        .set_target_fps(60)
+        .run();
+
+}
@@ -0,0 +1,49 @@
+use std::sync::Arc;
+use glam::Vec2;
+use log::info;
+use rgraphics::graphics::draw::{Image, Renderer, Transform2D};
+use rgraphics::graphics::sprite::{AnimatedSprite, AnimationClip, AnimationEvent, PlayMode};
+use rgraphics::Raymond;
+use rgraphics::EventHandler;
+use rgraphics::context::Context;
+
+pub struct MyWindow {
+    sprite: AnimatedSprite
+}
+
+impl Default for MyWindow {
+    fn default() -> Self {
+        // a sheet exported from aseprite or texture packer loads with SpriteSheet::from_file, here the
+        // picture is cut into a 2 by 2 grid and the quarters played in turn
+        let image = Arc::new(Image::from_file("C:/Workspace/rgraphics/examples/assets/tintin.jpg"));
+        let frame_size = Vec2::new(image.image.width() as f32, image.image.height() as f32) * 0.5;
+        let clip = AnimationClip::from_grid("quarters", frame_size, 2, 4, 0.25, PlayMode::PingPong);
+
+        Self { sprite: AnimatedSprite::new(image, clip) }
+    }
+}
+
+impl EventHandler for MyWindow {
+    fn on_update(&mut self, _ctx: &mut Context, delta: f32) {
+        self.sprite.update(delta);
+        for event in self.sprite.drain_events() {
+            if let AnimationEvent::FrameChanged { frame, .. } = event {
+                info!("Showing quarter {}", frame);
+            }
+        }
+    }
+
+    fn on_draw(&mut self, renderer: &mut Renderer, _alpha: f32) {
+        self.sprite.draw(renderer, Transform2D::default());
+    }
+}
+
+fn main() {
+
+    let my_game = MyWindow::default();
+
+    Raymond::create_window(600, 800, "Sprite Animation", Box::new(my_game))
+        .set_target_fps(60)
+        .run();
+
+}
@@ -0,0 +1,64 @@
+use std::sync::Arc;
+use glam::Vec2;
+use rgraphics::graphics::draw::{Color, Image, Renderer, Transform2D};
+use rgraphics::graphics::tilemap::{MapLayer, Tile, Tilemap, Tileset};
+use rgraphics::Raymond;
+use rgraphics::EventHandler;
+use rgraphics::context::Context;
+
+pub struct MyWindow {
+    map: Tilemap,
+    time: f32,
+    column: u32
+}
+
+impl Default for MyWindow {
+    fn default() -> Self {
+        // maps made in Tiled load with rgraphics::graphics::tiled::load("level.tmx") instead
+        let image = Arc::new(Image::from_file("examples/assets/tintin.jpg"));
+        let tileset = Tileset::new(image, Vec2::new(32.0, 32.0));
+        let mut map = Tilemap::new(tileset, 20, 20);
+
+        let ground = map.add_tile_layer("ground");
+        if let MapLayer::Tiles(layer) = &mut map.layers[ground] {
+            for y in 0..20 {
+                for x in 0..20 {
+                    let tile = Tile::new((x + y * 7) % 40).with_flip(x % 2 == 1, y % 3 == 0);
+                    layer.set_tile(x, y, Some(tile));
+                }
+            }
+        }
+        map.add_tile_layer("cursor");
+
+        Self { map, time: 0.0, column: 0 }
+    }
+}
+
+impl EventHandler for MyWindow {
+    fn on_update(&mut self, _ctx: &mut Context, delta: f32) {
+        // moving one tile a step only rebuilds the chunks it leaves and enters
+        self.time += delta;
+        if self.time > 0.2 {
+            self.time = 0.0;
+            let layer = self.map.tile_layer_mut("cursor").unwrap();
+            layer.set_tile(self.column, 10, None);
+            self.column = (self.column + 1) % 20;
+            layer.set_tile(self.column, 10, Some(Tile::new(0).with_flip_diagonal(true)));
+        }
+    }
+
+    fn on_draw(&mut self, renderer: &mut Renderer, _alpha: f32) {
+        renderer.set_background_color(Color::BLACK);
+        self.map.draw(renderer, Transform2D::at(-1.0, 1.0));
+    }
+}
+
+fn main() {
+
+    let my_game = MyWindow::default();
+
+    Raymond::create_window(640, 640, "Tilemap", Box::new(my_game))
+        .set_target_fps(60)
+        .run();
+
+}
@@ -0,0 +1,68 @@
+use glam::Vec2;
+use rgraphics::graphics::draw::{Color, Rect, Renderer, Transform2D};
+use rgraphics::ui::{Theme, Ui};
+use rgraphics::Raymond;
+use rgraphics::{EventHandler, InputEvent};
+use rgraphics::context::Context;
+
+pub struct MyWindow {
+    ui: Ui,
+    dark: bool,
+    radius: f32,
+    name: String,
+    clicks: u32
+}
+
+impl Default for MyWindow {
+    fn default() -> Self {
+        Self { ui: Ui::default(), dark: true, radius: 80.0, name: String::new(), clicks: 0 }
+    }
+}
+
+impl EventHandler for MyWindow {
+    fn on_input_event(&mut self, _ctx: &mut Context, event: InputEvent) {
+        self.ui.handle_event(&event);
+    }
+
+    fn on_draw(&mut self, renderer: &mut Renderer, _alpha: f32) {
+        renderer.set_background_color(Color::BLACK);
+        renderer.draw_circle(Transform2D::at(0.4, 0.0), self.radius * renderer.logical_pixel().x, 64, Color::new(0.9, 0.4, 0.2, 1.0));
+
+        // the widgets are rebuilt every frame from the state they edit
+        let ui = &mut self.ui;
+        ui.begin(Rect::new(Vec2::ZERO, renderer.logical_size()));
+        ui.panel("Settings", Rect::new(Vec2::new(20.0, 20.0), Vec2::new(320.0, 420.0)), |ui| {
+            ui.label("A few widgets");
+            ui.row(|ui| {
+                if ui.button("Click me").clicked {
+                    self.clicks += 1;
+                }
+                ui.label(&format!("clicked {} times", self.clicks));
+            });
+            if ui.checkbox("Dark theme", &mut self.dark).changed {
+                ui.theme = if self.dark { Theme::dark() } else { Theme::light() };
+            }
+            ui.slider("Radius", &mut self.radius, 10.0..=200.0);
+            if ui.text_field("Your name", &mut self.name).submitted {
+                println!("Hello {}", self.name);
+            }
+            ui.scroll_area("list", 150.0, |ui| {
+                for i in 0..30 {
+                    ui.label(&format!("Item {}", i));
+                }
+            });
+        });
+        ui.end();
+        ui.draw(renderer);
+    }
+}
+
+fn main() {
+
+    let my_game = MyWindow::default();
+
+    Raymond::create_window(800, 600, "Ui", Box::new(my_game))
+        .set_target_fps(60)
+        .run();
+
+}
@@ -2,17 +2,20 @@ use log::{info, LevelFilter};
 use rgraphics::graphics::draw::{Color, Image, Renderer, Transform2D};
 use rgraphics::Raymond;
 use rgraphics::{EventHandler, InputEvent};
+use rgraphics::context::Context;
 use simplelog::{ColorChoice, Config, TermLogger, TerminalMode};
 use std::collections::HashMap;
 use std::sync::Arc;
 use glam::Vec2;
+use winit::keyboard::KeyCode;
 use winit::window::Window;
 
 pub struct MyWindow {
     images: HashMap<String, Arc<Image>>,
     position: Vec2,
     frame_count: u32,
-    fps_timer: f32
+    fps_timer: f32,
+    fullscreen: bool
 }
 
 impl Default for MyWindow {
@@ -21,30 +24,41 @@ impl Default for MyWindow {
             images: HashMap::with_capacity(4),
             position: Vec2::new(0.0, 0.0),
             frame_count: 0,
-            fps_timer: 0.0
+            fps_timer: 0.0,
+            fullscreen: false
         }
     }
 }
 
 impl EventHandler for MyWindow {
-    fn on_init(&mut self) {
+    fn on_init(&mut self, _ctx: &mut Context) {
         info!("Window initialized");
         self.images.insert("tintin".to_string(), Arc::new(Image::from_file("C:/Workspace/rgraphics/examples/assets/tintin.jpg")));
         self.images.insert("tintindog".to_string(), Arc::new(Image::from_file("C:/Workspace/rgraphics/examples/assets/tintindog.jpg")));
     }
 
-    fn on_input_event(&mut self, event: InputEvent) {
+    fn on_input_event(&mut self, ctx: &mut Context, event: InputEvent) {
         //println!("Game input: {:?}", event);
+
+        // toggle fullscreen with F11
+        if let InputEvent::KeyboardInput(_, KeyCode::F11) = event {
+            self.fullscreen = !self.fullscreen;
+            ctx.set_fullscreen(self.fullscreen);
+        }
     }
 
-    fn on_update(&mut self, delta: f32) {
+    fn on_update(&mut self, ctx: &mut Context, delta: f32) {
         
         self.frame_count += 1;
         self.fps_timer += delta;
         
         // calculate fps and print
         if self.fps_timer >= 1.0 {
-            info!("FPS: {}", self.frame_count);
+            let stats = ctx.frame_stats();
+            info!("FPS: {} (avg {:.2}ms, p99 {:.2}ms)", self.frame_count,
+                stats.average().unwrap_or(0.0) * 1000.0,
+                stats.percentile(0.99).unwrap_or(0.0) * 1000.0);
+            ctx.set_title(&format!("Hello Window - {} fps", self.frame_count));
             self.frame_count = 0;
             self.fps_timer = 0.0;
         }
@@ -57,11 +71,12 @@ impl EventHandler for MyWindow {
         
     }
 
-    fn on_draw(&mut self, renderer: &mut Renderer) {
+    fn on_draw(&mut self, renderer: &mut Renderer, _alpha: f32) {
 
         //renderer.draw_image(Vector2::new(0.4, 0.4), self.images.get("tintindog").unwrap().clone());
         //renderer.draw_image(Vector2::new(-0.2, -0.2), self.images.get("tintin").unwrap().clone());
-        renderer.draw_image(self.position, self.images.get("tintin").unwrap().clone());
+        // the 1252 pixel image is scaled down to fit the window
+        renderer.draw_image(Transform2D { scale: Vec2::splat(0.4), ..Transform2D::at(self.position.x, self.position.y) }, self.images.get("tintin").unwrap().clone());
 
         renderer.draw_triangle(Transform2D::at(0.3, -0.4), Color::RED);
         renderer.draw_triangle(Transform2D::at(-0.2, 0.4), Color::BLUE);
@@ -86,6 +101,7 @@ fn main() {
 
     Raymond::new(Box::new(my_game))
         .set_target_fps(60)
+        .set_vsync(false)
         .set_window_attributes(Window::default_attributes()
             .with_title("Hello Window")
             .with_resizable(false)
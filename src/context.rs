@@ -0,0 +1,204 @@
+use std::sync::Arc;
+use std::time::Duration;
+use glam::Vec2;
+use wgpu::PresentMode;
+use winit::dpi::{LogicalSize, PhysicalSize, Size};
+use winit::window::{CursorGrabMode, CursorIcon, WindowAttributes, WindowId};
+use crate::timing::{Clock, FrameStats, SystemClock};
+use crate::EventHandler;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum WindowCommand {
+    SetTitle(String),
+    SetFullscreen(bool),
+    SetSize(Size),
+    SetCursorVisible(bool),
+    SetCursorGrab(CursorGrabMode),
+    SetCursorIcon(CursorIcon),
+    SetPresentMode(PresentMode),
+    SetMsaaSamples(u32),
+    Redraw,
+    Close,
+    Quit
+}
+
+pub struct Context {
+    window_id: Option<WindowId>,
+    commands: Vec<WindowCommand>,
+    new_windows: Vec<(WindowAttributes, Box<dyn EventHandler>)>,
+    window_size: PhysicalSize<u32>,
+    scale_factor: f64,
+    // shared with Raymond so elapsed follows whatever clock it was given
+    clock: Arc<dyn Clock>,
+    start: Duration,
+    frame_stats: FrameStats,
+    #[cfg(feature = "egui")]
+    egui: egui::Context
+}
+
+impl Context {
+
+    pub fn new() -> Self {
+        Self::with_clock(Arc::new(SystemClock::new()))
+    }
+
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            window_id: None,
+            commands: Vec::with_capacity(4),
+            new_windows: Vec::new(),
+            window_size: PhysicalSize::new(0, 0),
+            scale_factor: 1.0,
+            start: clock.now(),
+            clock,
+            frame_stats: FrameStats::default(),
+            #[cfg(feature = "egui")]
+            egui: egui::Context::default()
+        }
+    }
+
+    pub(crate) fn for_window(window_id: WindowId, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            window_id: Some(window_id),
+            ..Self::with_clock(clock)
+        }
+    }
+
+    // elapsed starts again from zero on the new clock
+    pub(crate) fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.start = clock.now();
+        self.clock = clock;
+    }
+
+    pub fn window_id(&self) -> Option<WindowId> {
+        self.window_id
+    }
+
+    // opens another window with its own handler once the current callback returns
+    pub fn open_window(&mut self, attributes: WindowAttributes, handler: Box<dyn EventHandler>) -> &mut Self {
+        self.new_windows.push((attributes, handler));
+        self
+    }
+
+    // closes this window, closing the main window ends the app
+    pub fn close_window(&mut self) {
+        self.commands.push(WindowCommand::Close);
+    }
+
+    pub fn set_title(&mut self, title: &str) -> &mut Self {
+        self.commands.push(WindowCommand::SetTitle(title.to_string()));
+        self
+    }
+
+    pub fn set_fullscreen(&mut self, fullscreen: bool) -> &mut Self {
+        self.commands.push(WindowCommand::SetFullscreen(fullscreen));
+        self
+    }
+
+    pub fn set_window_size(&mut self, width: f64, height: f64) -> &mut Self {
+        self.commands.push(WindowCommand::SetSize(LogicalSize::new(width, height).into()));
+        self
+    }
+
+    pub fn set_physical_window_size(&mut self, width: u32, height: u32) -> &mut Self {
+        self.commands.push(WindowCommand::SetSize(PhysicalSize::new(width, height).into()));
+        self
+    }
+
+    pub fn set_cursor_visible(&mut self, visible: bool) -> &mut Self {
+        self.commands.push(WindowCommand::SetCursorVisible(visible));
+        self
+    }
+
+    pub fn set_cursor_grab(&mut self, mode: CursorGrabMode) -> &mut Self {
+        self.commands.push(WindowCommand::SetCursorGrab(mode));
+        self
+    }
+
+    pub fn set_cursor_icon(&mut self, icon: CursorIcon) -> &mut Self {
+        self.commands.push(WindowCommand::SetCursorIcon(icon));
+        self
+    }
+
+    pub fn set_present_mode(&mut self, mode: PresentMode) -> &mut Self {
+        self.commands.push(WindowCommand::SetPresentMode(mode));
+        self
+    }
+
+    pub fn set_vsync(&mut self, vsync: bool) -> &mut Self {
+        self.set_present_mode(if vsync { PresentMode::AutoVsync } else { PresentMode::AutoNoVsync })
+    }
+
+    pub fn set_msaa_samples(&mut self, samples: u32) -> &mut Self {
+        self.commands.push(WindowCommand::SetMsaaSamples(samples));
+        self
+    }
+
+    pub fn request_redraw(&mut self) {
+        if !self.commands.contains(&WindowCommand::Redraw) {
+            self.commands.push(WindowCommand::Redraw);
+        }
+    }
+
+    pub fn quit(&mut self) {
+        self.commands.push(WindowCommand::Quit);
+    }
+
+    pub fn window_size(&self) -> PhysicalSize<u32> {
+        self.window_size
+    }
+
+    pub fn logical_window_size(&self) -> LogicalSize<f64> {
+        self.window_size.to_logical(self.scale_factor)
+    }
+
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    pub fn to_logical(&self, physical: Vec2) -> Vec2 {
+        physical / self.scale_factor as f32
+    }
+
+    pub fn to_physical(&self, logical: Vec2) -> Vec2 {
+        logical * self.scale_factor as f32
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.clock.now().saturating_sub(self.start)
+    }
+
+    pub fn frame_stats(&self) -> &FrameStats {
+        &self.frame_stats
+    }
+
+    // the window's egui context, widgets can be made from on_update and on_egui
+    #[cfg(feature = "egui")]
+    pub fn egui(&self) -> &egui::Context {
+        &self.egui
+    }
+
+    pub(crate) fn frame_stats_mut(&mut self) -> &mut FrameStats {
+        &mut self.frame_stats
+    }
+
+    pub(crate) fn set_window_metrics(&mut self, size: PhysicalSize<u32>, scale_factor: f64) {
+        self.window_size = size;
+        self.scale_factor = scale_factor;
+    }
+
+    pub(crate) fn take_new_windows(&mut self) -> Vec<(WindowAttributes, Box<dyn EventHandler>)> {
+        std::mem::take(&mut self.new_windows)
+    }
+
+    pub(crate) fn take_commands(&mut self) -> Vec<WindowCommand> {
+        std::mem::take(&mut self.commands)
+    }
+
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        Self::new()
+    }
+}
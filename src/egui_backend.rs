@@ -0,0 +1,60 @@
+// runs egui inside a window, winit events go in and tessellated shapes come out for the display to draw
+// over everything the renderer drew
+use winit::event::WindowEvent;
+use winit::window::Window;
+
+// one frame of egui output, drawn and then dropped by Display::render
+pub struct EguiFrame {
+    pub(crate) primitives: Vec<egui::ClippedPrimitive>,
+    pub(crate) textures: egui::TexturesDelta,
+    pub(crate) pixels_per_point: f32
+}
+
+// one per window, sharing its egui::Context with the window's Context
+pub struct EguiBackend {
+    context: egui::Context,
+    state: egui_winit::State
+}
+
+impl EguiBackend {
+
+    pub fn new(context: egui::Context, window: &Window, max_texture_side: usize) -> Self {
+        let state = egui_winit::State::new(
+            context.clone(),
+            egui::ViewportId::ROOT,
+            window,
+            Some(window.scale_factor() as f32),
+            window.theme(),
+            Some(max_texture_side)
+        );
+        Self { context, state }
+    }
+
+    pub fn context(&self) -> &egui::Context {
+        &self.context
+    }
+
+    // returns (consumed, repaint), consumed when egui wants the event to itself such as a click on one
+    // of its windows or typing into one of its text fields
+    pub fn on_window_event(&mut self, window: &Window, event: &WindowEvent) -> (bool, bool) {
+        let response = self.state.on_window_event(window, event);
+        (response.consumed, response.repaint)
+    }
+
+    // egui widgets can be made from now until end_pass
+    pub fn begin_pass(&mut self, window: &Window) {
+        let input = self.state.take_egui_input(window);
+        self.context.begin_pass(input);
+    }
+
+    pub fn end_pass(&mut self, window: &Window) -> EguiFrame {
+        let output = self.context.end_pass();
+        self.state.handle_platform_output(window, output.platform_output);
+        EguiFrame {
+            primitives: self.context.tessellate(output.shapes, output.pixels_per_point),
+            textures: output.textures_delta,
+            pixels_per_point: output.pixels_per_point
+        }
+    }
+
+}
@@ -1,27 +1,89 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::sync::Arc;
 use glam::Vec2;
 use image::ImageReader;
 use image::RgbaImage;
-use crate::graphics::mesh::Mesh;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use winit::dpi::PhysicalSize;
+use lyon::path::Path;
+use lyon::tessellation::FillOptions;
+use crate::graphics::gpu::ParticleInstance;
+use crate::graphics::layer::{LayerId, RenderLayer};
+use crate::graphics::mesh::{CornerRadii, Mesh, MeshHandle, NineSlice, StrokeStyle, Tessellation};
+use crate::graphics::text::Font;
+pub use crate::graphics::transform::Transform2D;
 
-#[derive(Clone, Debug)]
-pub struct Transform2D {
-    pub position: Vec2,
-    pub scale: Vec2,
-    pub rotation: f32
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum CoordinateSpace {
+    // -1.0 to 1.0 on both axes with y pointing up
+    #[default]
+    Normalized,
+    // logical pixels from the top left corner with y pointing down
+    Logical,
+    // physical pixels from the top left corner with y pointing down
+    Physical
 }
 
-impl Transform2D {
-    pub fn at(x: f32, y: f32) -> Self {
-        Self {
-            position: Vec2::new(x, y),
-            scale: Vec2::new(1.0, 1.0),
-            rotation: 0.0
+
+// axis aligned rectangle between two corners
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Rect {
+    pub min: Vec2,
+    pub max: Vec2
+}
+
+impl Rect {
+
+    // the corners can be given in any order
+    pub fn new(a: Vec2, b: Vec2) -> Self {
+        Self { min: a.min(b), max: a.max(b) }
+    }
+
+    // the smallest rect around all the points, empty at the origin when there are none
+    pub fn bounding(points: &[Vec2]) -> Self {
+        match (points.iter().copied().reduce(Vec2::min), points.iter().copied().reduce(Vec2::max)) {
+            (Some(min), Some(max)) => Rect { min, max },
+            _ => Rect::default()
         }
     }
+
+    // the box around this rect after it has been transformed
+    pub fn transformed(&self, transform: &Transform2D) -> Rect {
+        let corners = [self.min, Vec2::new(self.max.x, self.min.y), self.max, Vec2::new(self.min.x, self.max.y)]
+            .map(|corner| transform.transform_point(corner));
+        Rect::bounding(&corners)
+    }
+
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.min.x <= other.max.x && self.max.x >= other.min.x && self.min.y <= other.max.y && self.max.y >= other.min.y
+    }
+
+    pub fn from_center(center: Vec2, size: Vec2) -> Self {
+        Self::new(center - size * 0.5, center + size * 0.5)
+    }
+
+    pub fn size(&self) -> Vec2 {
+        (self.max - self.min).max(Vec2::ZERO)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.max.x <= self.min.x || self.max.y <= self.min.y
+    }
+
+    pub fn contains(&self, point: Vec2) -> bool {
+        point.x >= self.min.x && point.x <= self.max.x && point.y >= self.min.y && point.y <= self.max.y
+    }
+
+    // may come back empty when the two do not overlap
+    pub fn intersect(&self, other: &Rect) -> Rect {
+        Rect { min: self.min.max(other.min), max: self.max.min(other.max) }
+    }
+
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Color {
     pub r: f32,
     pub g: f32,
@@ -49,15 +111,117 @@ impl Color {
 
 }
 
-impl Into<[f32; 4]> for Color {
-    fn into(self) -> [f32; 4] {
-        [self.r.clone(), self.g.clone(), self.b.clone(), self.a.clone()]
+impl From<Color> for [f32; 4] {
+    fn from(color: Color) -> [f32; 4] {
+        [color.r, color.g, color.b, color.a]
     }
 }
 
-impl Into<[u8; 4]> for Color {
-    fn into(self) -> [u8; 4] {
-        [((self.r * 255.0) as u8), ((self.g * 255.0) as u8), ((self.b * 255.0) as u8), ((self.a * 255.0) as u8)]
+impl From<Color> for [u8; 4] {
+    fn from(color: Color) -> [u8; 4] {
+        [((color.r * 255.0) as u8), ((color.g * 255.0) as u8), ((color.b * 255.0) as u8), ((color.a * 255.0) as u8)]
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum SpreadMode {
+    #[default]
+    Pad,
+    Repeat,
+    Reflect
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ColorStop {
+    pub offset: f32,
+    pub color: Color
+}
+
+impl ColorStop {
+    pub fn new(offset: f32, color: Color) -> Self {
+        Self { offset, color }
+    }
+}
+
+// gradient and pattern coordinates are in the mesh's own units, before the transform is applied
+#[derive(Clone, Debug)]
+pub enum Paint {
+    Solid(Color),
+    LinearGradient { start: Vec2, end: Vec2, stops: Vec<ColorStop>, spread: SpreadMode },
+    RadialGradient { center: Vec2, radius: f32, stops: Vec<ColorStop>, spread: SpreadMode },
+    // stops sweep one full turn starting at the angle, in radians
+    ConicGradient { center: Vec2, angle: f32, stops: Vec<ColorStop>, spread: SpreadMode },
+    ImagePattern { image: Arc<Image>, origin: Vec2, size: Vec2, spread: SpreadMode }
+}
+
+impl Paint {
+
+    // the shader only has room for this many stops
+    pub const MAX_STOPS: usize = 8;
+
+    pub fn linear_gradient(start: Vec2, end: Vec2, stops: Vec<ColorStop>) -> Self {
+        Paint::LinearGradient { start, end, stops, spread: SpreadMode::Pad }
+    }
+
+    pub fn radial_gradient(center: Vec2, radius: f32, stops: Vec<ColorStop>) -> Self {
+        Paint::RadialGradient { center, radius, stops, spread: SpreadMode::Pad }
+    }
+
+    pub fn conic_gradient(center: Vec2, angle: f32, stops: Vec<ColorStop>) -> Self {
+        Paint::ConicGradient { center, angle, stops, spread: SpreadMode::Repeat }
+    }
+
+    pub fn image_pattern(image: Arc<Image>, origin: Vec2, size: Vec2) -> Self {
+        Paint::ImagePattern { image, origin, size, spread: SpreadMode::Repeat }
+    }
+
+    pub fn with_spread(mut self, mode: SpreadMode) -> Self {
+        match self {
+            Paint::Solid(_) => {}
+            Paint::LinearGradient { ref mut spread, .. }
+            | Paint::RadialGradient { ref mut spread, .. }
+            | Paint::ConicGradient { ref mut spread, .. }
+            | Paint::ImagePattern { ref mut spread, .. } => *spread = mode
+        }
+        self
+    }
+
+    pub fn stops(&self) -> &[ColorStop] {
+        match self {
+            Paint::LinearGradient { stops, .. }
+            | Paint::RadialGradient { stops, .. }
+            | Paint::ConicGradient { stops, .. } => stops,
+            _ => &[]
+        }
+    }
+
+    // whether every pixel drawn with this paint is fully opaque
+    pub fn is_opaque(&self) -> bool {
+        match self {
+            Paint::Solid(color) => color.a >= 1.0,
+            Paint::ImagePattern { .. } => false,
+            _ => !self.stops().is_empty() && self.stops().iter().all(|stop| stop.color.a >= 1.0)
+        }
+    }
+
+    pub fn image(&self) -> Option<&Arc<Image>> {
+        match self {
+            Paint::ImagePattern { image, .. } => Some(image),
+            _ => None
+        }
+    }
+
+}
+
+impl Default for Paint {
+    fn default() -> Self {
+        Paint::Solid(Color::WHITE)
+    }
+}
+
+impl From<Color> for Paint {
+    fn from(color: Color) -> Self {
+        Paint::Solid(color)
     }
 }
 
@@ -67,7 +231,28 @@ pub enum DrawCommand {
         mesh: Mesh,
         transform: Transform2D,
         image: Option<Arc<Image>>,
-        color: Color
+        paint: Paint
+    },
+    RetainedMesh2D {
+        handle: MeshHandle,
+        transform: Transform2D,
+        image: Option<Arc<Image>>,
+        paint: Paint
+    },
+    // scissor rect in physical pixels, y down, None draws to the whole surface
+    Clip(Option<Rect>),
+    // later draws only land inside the mesh, or outside it when inverted, until the matching EndMask
+    BeginMask {
+        mesh: Mesh,
+        transform: Transform2D,
+        inverted: bool
+    },
+    EndMask,
+    // quads drawn together in one instanced call, positions are relative to transform
+    Particles {
+        instances: Vec<ParticleInstance>,
+        transform: Transform2D,
+        image: Option<Arc<Image>>
     }
 }
 
@@ -86,9 +271,23 @@ impl Image {
         }
     }
 
+    // like from_file but warns and returns None when the file is missing or can not be decoded
+    pub fn load(path: &str) -> Option<Self> {
+        let image = ImageReader::open(path)
+            .map_err(image::ImageError::IoError)
+            .and_then(|reader| reader.decode());
+        match image {
+            Ok(image) => Some(Self { path: path.to_string(), image }),
+            Err(error) => {
+                warn!("Failed to load image {}: {}", path, error);
+                None
+            }
+        }
+    }
+
     pub fn single_pixel(color: Color) -> Self {
         let mut img = RgbaImage::new(1, 1);
-        img.put_pixel(0, 0, image::Rgba(color.clone().into()));
+        img.put_pixel(0, 0, image::Rgba(color.into()));
         Self {
             path: format!("single_pixel_{:?}_{:?}_{:?}_{:?}", color.r, color.g, color.b, color.a),
             image: image::DynamicImage::ImageRgba8(img)
@@ -101,9 +300,56 @@ impl Image {
 
 }
 
+struct RetainedMesh {
+    // static meshes drop their cpu copy once uploaded
+    mesh: Option<Mesh>,
+    // kept for hit testing once the cpu copy is gone
+    bounds: Rect,
+    dynamic: bool,
+    dirty: bool,
+    opaque: bool
+}
+
+// where a command was recorded, state changes start a new segment that draws are never sorted across
+#[derive(Copy, Clone, Debug)]
+struct SortKey {
+    layer: LayerId,
+    segment: u32,
+    z: f32
+}
+
+// one entry of the sorted draw list, pointing back into Renderer::commands
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DrawCall {
+    pub command: usize,
+    pub layer: LayerId,
+    pub z: f32,
+    // depth buffer value, only meaningful on layers with depth testing
+    pub depth: f32,
+    pub opaque: bool
+}
+
 pub struct Renderer {
     pub commands: Vec<DrawCommand>,
-    pub background_color: Color
+    retained_meshes: HashMap<MeshHandle, RetainedMesh>,
+    released_meshes: Vec<MeshHandle>,
+    pub background_color: Color,
+    coordinate_space: CoordinateSpace,
+    tessellation: Tessellation,
+    edge_feather: Option<f32>,
+    clip_stack: Vec<Rect>,
+    mask_depth: u32,
+    transform_stack: Vec<Transform2D>,
+    layers: Vec<RenderLayer>,
+    // the clip each layer's command stream currently ends with
+    layer_clips: Vec<Option<Rect>>,
+    current_layer: LayerId,
+    z: f32,
+    segment: u32,
+    keys: Vec<SortKey>,
+    draw_calls: Vec<DrawCall>,
+    viewport: PhysicalSize<u32>,
+    scale_factor: f32
 }
 
 impl Renderer {
@@ -111,12 +357,507 @@ impl Renderer {
     pub fn new() -> Self {
         Self {
             commands: Vec::with_capacity(8),
-            background_color: Color::BLACK
+            retained_meshes: HashMap::new(),
+            released_meshes: Vec::new(),
+            background_color: Color::BLACK,
+            coordinate_space: CoordinateSpace::Normalized,
+            tessellation: Tessellation::default(),
+            edge_feather: None,
+            clip_stack: Vec::new(),
+            mask_depth: 0,
+            transform_stack: Vec::new(),
+            layers: vec![RenderLayer::new("default", 0)],
+            layer_clips: vec![None],
+            current_layer: LayerId::DEFAULT,
+            z: 0.0,
+            segment: 0,
+            keys: Vec::with_capacity(8),
+            draw_calls: Vec::new(),
+            viewport: PhysicalSize::new(0, 0),
+            scale_factor: 1.0
+        }
+    }
+
+    pub fn set_coordinate_space(&mut self, space: CoordinateSpace) -> &mut Self {
+        self.coordinate_space = space;
+        self
+    }
+
+    pub fn coordinate_space(&self) -> CoordinateSpace {
+        self.coordinate_space
+    }
+
+    pub fn set_tessellation(&mut self, tessellation: Tessellation) -> &mut Self {
+        self.tessellation = tessellation;
+        self
+    }
+
+    pub fn tessellation(&self) -> Tessellation {
+        self.tessellation
+    }
+
+    // analytic antialiasing, fades shape edges out over this many physical pixels
+    // cheaper than msaa but only applies to shapes the renderer tessellates itself
+    pub fn set_edge_feather(&mut self, pixels: Option<f32>) -> &mut Self {
+        self.edge_feather = pixels.filter(|pixels| *pixels > 0.0);
+        self
+    }
+
+    pub fn edge_feather(&self) -> Option<f32> {
+        self.edge_feather
+    }
+
+    // the feather width in a mesh's own units
+    fn feather(&self, transform: &Transform2D) -> Option<f32> {
+        self.edge_feather.map(|pixels| pixels / self.screen_scale(transform))
+    }
+
+    // physical pixels covered by one unit of a mesh drawn with this transform
+    pub fn screen_scale(&self, transform: &Transform2D) -> f32 {
+        let pixels_per_unit = match self.coordinate_space {
+            CoordinateSpace::Normalized => self.physical_size().max_element() * 0.5,
+            CoordinateSpace::Logical => self.scale_factor,
+            CoordinateSpace::Physical => 1.0
+        };
+        let zoom = self.layers[self.current_layer.0].camera.zoom.abs();
+        let scale = self.current_transform().compose(transform).scale;
+        scale.abs().max_element() * pixels_per_unit.max(1.0) * zoom
+    }
+
+    // curve flattening tolerance for a shape of the given radius, in the shape's own units
+    pub fn tolerance(&self, tessellation: Tessellation, radius: f32, transform: &Transform2D) -> f32 {
+        tessellation.tolerance(radius, self.screen_scale(transform))
+    }
+
+    pub fn scale_factor(&self) -> f32 {
+        self.scale_factor
+    }
+
+    pub fn physical_size(&self) -> Vec2 {
+        Vec2::new(self.viewport.width as f32, self.viewport.height as f32)
+    }
+
+    pub fn logical_size(&self) -> Vec2 {
+        self.physical_size() / self.scale_factor
+    }
+
+    // the part of the layer's world that its camera currently shows, in coordinate space units
+    pub fn visible_rect(&self, layer: LayerId) -> Rect {
+        let screen = match self.coordinate_space {
+            CoordinateSpace::Normalized => Rect::new(Vec2::splat(-1.0), Vec2::ONE),
+            CoordinateSpace::Logical => Rect::new(Vec2::ZERO, self.logical_size()),
+            CoordinateSpace::Physical => Rect::new(Vec2::ZERO, self.physical_size())
+        };
+        let camera = self.layer(layer).map(|layer| layer.camera).unwrap_or_default();
+        let inverse = camera.view_matrix().inverse();
+        let corners = [screen.min, Vec2::new(screen.max.x, screen.min.y), screen.max, Vec2::new(screen.min.x, screen.max.y)]
+            .map(|corner| inverse.transform_point3(corner.extend(0.0)).truncate());
+        Rect::bounding(&corners)
+    }
+
+    // size of one logical pixel in the current coordinate space
+    pub fn logical_pixel(&self) -> Vec2 {
+        match self.coordinate_space {
+            CoordinateSpace::Normalized => {
+                let size = self.logical_size();
+                if size.x > 0.0 && size.y > 0.0 { Vec2::new(2.0, 2.0) / size } else { Vec2::ONE }
+            }
+            CoordinateSpace::Logical => Vec2::ONE,
+            CoordinateSpace::Physical => Vec2::splat(self.scale_factor)
         }
     }
 
+    pub(crate) fn set_viewport(&mut self, size: PhysicalSize<u32>, scale_factor: f64) {
+        self.viewport = size;
+        self.scale_factor = scale_factor as f32;
+    }
+
     pub fn end_frame(&mut self) {
         self.commands.clear();
+        self.keys.clear();
+        self.draw_calls.clear();
+        self.clip_stack.clear();
+        self.transform_stack.clear();
+        self.layer_clips.fill(None);
+        self.mask_depth = 0;
+        self.segment = 0;
+        self.current_layer = LayerId::DEFAULT;
+        self.z = 0.0;
+    }
+
+    // everything drawn until the matching pop is placed inside this transform, pushes nest
+    pub fn push_transform(&mut self, transform: Transform2D) -> &mut Self {
+        let transform = self.current_transform().compose(&transform);
+        self.transform_stack.push(transform);
+        self
+    }
+
+    pub fn pop_transform(&mut self) -> &mut Self {
+        if self.transform_stack.pop().is_none() {
+            warn!("pop_transform called without a matching push_transform");
+        }
+        self
+    }
+
+    pub fn with_transform(&mut self, transform: Transform2D, draw: impl FnOnce(&mut Renderer)) -> &mut Self {
+        self.push_transform(transform);
+        draw(self);
+        self.pop_transform()
+    }
+
+    // the combined transform of every push so far
+    pub fn current_transform(&self) -> Transform2D {
+        self.transform_stack.last().copied().unwrap_or_default()
+    }
+
+    pub fn add_layer(&mut self, name: &str, order: i32) -> LayerId {
+        self.layers.push(RenderLayer::new(name, order));
+        self.layer_clips.push(None);
+        LayerId(self.layers.len() - 1)
+    }
+
+    pub fn find_layer(&self, name: &str) -> Option<LayerId> {
+        self.layers.iter().position(|layer| layer.name == name).map(LayerId)
+    }
+
+    pub fn layer(&self, id: LayerId) -> Option<&RenderLayer> {
+        self.layers.get(id.0)
+    }
+
+    pub fn layer_mut(&mut self, id: LayerId) -> Option<&mut RenderLayer> {
+        self.layers.get_mut(id.0)
+    }
+
+    // later draws go to this layer until the end of the frame, clips follow along but masks do not
+    pub fn set_layer(&mut self, id: LayerId) -> &mut Self {
+        if id.0 >= self.layers.len() {
+            warn!("Layer {:?} does not exist", id);
+            return self;
+        }
+        if id != self.current_layer && self.mask_depth > 0 {
+            warn!("Switching layers inside a mask, the mask only applies to the layer it began on");
+        }
+        self.current_layer = id;
+
+        let clip = self.clip_stack.last().copied();
+        if self.layer_clips[id.0] != clip {
+            self.push_command(DrawCommand::Clip(clip));
+        }
+        self
+    }
+
+    pub fn current_layer(&self) -> LayerId {
+        self.current_layer
+    }
+
+    // higher z draws on top within a layer, equal z keeps the order things were drawn in
+    pub fn set_z(&mut self, z: f32) -> &mut Self {
+        self.z = z;
+        self
+    }
+
+    pub fn z(&self) -> f32 {
+        self.z
+    }
+
+    fn push_command(&mut self, mut command: DrawCommand) {
+        if let Some(parent) = self.transform_stack.last() {
+            match command {
+                DrawCommand::Mesh2D { ref mut transform, .. }
+                | DrawCommand::RetainedMesh2D { ref mut transform, .. }
+                | DrawCommand::BeginMask { ref mut transform, .. }
+                | DrawCommand::Particles { ref mut transform, .. } => *transform = parent.compose(transform),
+                _ => {}
+            }
+        }
+
+        match command {
+            DrawCommand::Clip(clip) => {
+                self.layer_clips[self.current_layer.0] = clip;
+                self.segment += 1;
+            }
+            DrawCommand::BeginMask { .. } | DrawCommand::EndMask => self.segment += 1,
+            _ => {}
+        }
+        self.keys.push(SortKey { layer: self.current_layer, segment: self.segment, z: self.z });
+        self.commands.push(command);
+    }
+
+    // stable sort of the recorded commands into submission order, run once before rendering
+    pub fn sort_commands(&mut self) -> &[DrawCall] {
+        // commands pushed straight onto the list land in the default layer
+        let default_key = SortKey { layer: LayerId::DEFAULT, segment: self.segment, z: 0.0 };
+        self.keys.resize(self.commands.len(), default_key);
+
+        let mut calls: Vec<DrawCall> = self.keys.iter()
+            .enumerate()
+            .filter(|(_, key)| self.layers[key.layer.0].visible)
+            .map(|(index, key)| DrawCall {
+                command: index,
+                layer: key.layer,
+                z: key.z,
+                depth: 0.0,
+                opaque: self.layers[key.layer.0].depth_test && self.is_opaque(&self.commands[index])
+            })
+            .collect();
+
+        calls.sort_by(|a, b| self.compare_calls(a, b));
+
+        // later layers get nearer depth values so they are never hidden by earlier ones
+        let mut depth_zs: Vec<(LayerId, f32)> = calls.iter()
+            .filter(|call| self.layers[call.layer.0].depth_test && !self.is_state(call.command))
+            .map(|call| (call.layer, call.z))
+            .collect();
+        depth_zs.sort_by(|a, b| self.compare_layers(a.0, b.0).then(a.1.total_cmp(&b.1)));
+        depth_zs.dedup();
+
+        let total = depth_zs.len() as f32 + 1.0;
+        for call in calls.iter_mut().filter(|call| self.layers[call.layer.0].depth_test) {
+            if let Ok(rank) = depth_zs.binary_search_by(|probe| self.compare_layers(probe.0, call.layer).then(probe.1.total_cmp(&call.z))) {
+                call.depth = 1.0 - (rank as f32 + 1.0) / total;
+            }
+        }
+
+        self.draw_calls = calls;
+        &self.draw_calls
+    }
+
+    // the sorted draw list from the last sort_commands
+    pub fn draw_calls(&self) -> &[DrawCall] {
+        &self.draw_calls
+    }
+
+    fn compare_layers(&self, a: LayerId, b: LayerId) -> Ordering {
+        self.layers[a.0].order.cmp(&self.layers[b.0].order).then(a.0.cmp(&b.0))
+    }
+
+    fn compare_calls(&self, a: &DrawCall, b: &DrawCall) -> Ordering {
+        // state changes open their segment, then opaque draws front to back, then the rest back to front
+        let pass = |call: &DrawCall| if self.is_state(call.command) { 0 } else if call.opaque { 1 } else { 2 };
+
+        self.compare_layers(a.layer, b.layer)
+            .then(self.keys[a.command].segment.cmp(&self.keys[b.command].segment))
+            .then(pass(a).cmp(&pass(b)))
+            .then(match pass(a) {
+                1 => b.z.total_cmp(&a.z),
+                2 => a.z.total_cmp(&b.z),
+                _ => Ordering::Equal
+            })
+    }
+
+    fn is_state(&self, command: usize) -> bool {
+        matches!(self.commands[command], DrawCommand::Clip(_) | DrawCommand::BeginMask { .. } | DrawCommand::EndMask)
+    }
+
+    fn is_opaque(&self, command: &DrawCommand) -> bool {
+        match command {
+            DrawCommand::Mesh2D { mesh, image, paint, .. } => image.is_none() && paint.is_opaque() && mesh.is_opaque(),
+            DrawCommand::RetainedMesh2D { handle, image, paint, .. } => {
+                image.is_none() && paint.is_opaque() && self.retained_meshes.get(handle).is_some_and(|retained| retained.opaque)
+            }
+            _ => false
+        }
+    }
+
+    // index into commands of the topmost draw covering the point, given in the current coordinate space before
+    // any layer's camera, following layer order, z, clips and masks the way the recorded frame will draw
+    pub fn command_at(&self, point: Vec2) -> Option<usize> {
+        let physical = self.to_physical_pixels(point);
+        let worlds: Vec<Vec2> = self.layers.iter()
+            .map(|layer| layer.camera.view_matrix().inverse().transform_point3(point.extend(0.0)).truncate())
+            .collect();
+        let mut clips: Vec<Option<Rect>> = vec![None; self.layers.len()];
+        // whether the point is left open by each mask still active on a layer
+        let mut masks: Vec<Vec<bool>> = vec![Vec::new(); self.layers.len()];
+
+        let default_key = SortKey { layer: LayerId::DEFAULT, segment: self.segment, z: 0.0 };
+        let mut topmost: Option<(usize, SortKey)> = None;
+        for (index, command) in self.commands.iter().enumerate() {
+            let key = self.keys.get(index).copied().unwrap_or(default_key);
+            let layer = key.layer.0;
+            let world = worlds[layer];
+            let hit = match command {
+                DrawCommand::Clip(rect) => {
+                    clips[layer] = *rect;
+                    false
+                }
+                DrawCommand::BeginMask { mesh, transform, inverted } => {
+                    masks[layer].push(Self::mesh_covers(mesh, transform, world) != *inverted);
+                    false
+                }
+                DrawCommand::EndMask => {
+                    masks[layer].pop();
+                    false
+                }
+                DrawCommand::Mesh2D { mesh, transform, .. } => Self::mesh_covers(mesh, transform, world),
+                DrawCommand::RetainedMesh2D { handle, transform, .. } => match self.retained_meshes.get(handle) {
+                    Some(RetainedMesh { mesh: Some(mesh), .. }) => Self::mesh_covers(mesh, transform, world),
+                    // static meshes only keep their bounds once uploaded
+                    Some(retained) => transform.inverse().is_some_and(|inverse| retained.bounds.contains(inverse.transform_point(world))),
+                    None => false
+                },
+                // particles are effects rather than things to click
+                DrawCommand::Particles { .. } => false
+            };
+
+            if hit
+                && self.layers[layer].visible
+                && clips[layer].is_none_or(|clip| clip.contains(physical))
+                && masks[layer].iter().all(|open| *open)
+                && topmost.is_none_or(|(top, top_key)| self.draws_over((index, key), (top, top_key)) == Ordering::Greater) {
+                topmost = Some((index, key));
+            }
+        }
+        topmost.map(|(index, _)| index)
+    }
+
+    fn mesh_covers(mesh: &Mesh, transform: &Transform2D, point: Vec2) -> bool {
+        transform.inverse().is_some_and(|inverse| mesh.contains(inverse.transform_point(point)))
+    }
+
+    // which of two commands ends up on top, depth tested layers go by z and the rest by draw order
+    fn draws_over(&self, a: (usize, SortKey), b: (usize, SortKey)) -> Ordering {
+        let ((a_index, a), (b_index, b)) = (a, b);
+        let layer = self.compare_layers(a.layer, b.layer);
+        if layer != Ordering::Equal {
+            return layer;
+        }
+        if self.layers[a.layer.0].depth_test {
+            a.z.total_cmp(&b.z).then(a.segment.cmp(&b.segment)).then(a_index.cmp(&b_index))
+        } else {
+            a.segment.cmp(&b.segment).then(a.z.total_cmp(&b.z)).then(a_index.cmp(&b_index))
+        }
+    }
+
+    // masks nest, each one narrows the area the ones before it left open
+    pub fn begin_mask(&mut self, transform: Transform2D, shape: Mesh) -> &mut Self {
+        self.push_mask(transform, shape, false)
+    }
+
+    // draws until end_mask only land outside the shape
+    pub fn begin_inverted_mask(&mut self, transform: Transform2D, shape: Mesh) -> &mut Self {
+        self.push_mask(transform, shape, true)
+    }
+
+    fn push_mask(&mut self, transform: Transform2D, mesh: Mesh, inverted: bool) -> &mut Self {
+        // the stencil buffer holds 8 bits
+        if self.mask_depth == u8::MAX as u32 {
+            warn!("Too many nested masks, ignoring begin_mask");
+            return self;
+        }
+        self.mask_depth += 1;
+        self.push_command(DrawCommand::BeginMask { mesh, transform, inverted });
+        self
+    }
+
+    pub fn end_mask(&mut self) -> &mut Self {
+        if self.mask_depth == 0 {
+            warn!("end_mask called without a matching begin_mask");
+            return self;
+        }
+        self.mask_depth -= 1;
+        self.push_command(DrawCommand::EndMask);
+        self
+    }
+
+    pub fn mask_depth(&self) -> u32 {
+        self.mask_depth
+    }
+
+    // clips everything drawn until the matching pop, nested clips only ever shrink, the rect goes through the
+    // transform stack and the current layer's camera as it is when pushed
+    pub fn push_clip_rect(&mut self, rect: Rect) -> &mut Self {
+        // rotated or skewed clips are widened to the box around them
+        let transform = self.current_transform();
+        let view = self.layers[self.current_layer.0].camera.view_matrix();
+        let corners = [rect.min, Vec2::new(rect.max.x, rect.min.y), rect.max, Vec2::new(rect.min.x, rect.max.y)]
+            .map(|corner| view.transform_point3(transform.transform_point(corner).extend(0.0)).truncate())
+            .map(|corner| self.to_physical_pixels(corner));
+        let rect = Rect::bounding(&corners);
+        let rect = match self.clip_stack.last() {
+            Some(parent) => parent.intersect(&rect),
+            None => rect
+        };
+        self.clip_stack.push(rect);
+        self.push_command(DrawCommand::Clip(Some(rect)));
+        self
+    }
+
+    pub fn pop_clip_rect(&mut self) -> &mut Self {
+        if self.clip_stack.pop().is_none() {
+            warn!("pop_clip_rect called without a matching push_clip_rect");
+            return self;
+        }
+        self.push_command(DrawCommand::Clip(self.clip_stack.last().copied()));
+        self
+    }
+
+    // the current clip in physical pixels
+    pub fn clip_rect(&self) -> Option<Rect> {
+        self.clip_stack.last().copied()
+    }
+
+    // maps a point in the current coordinate space to physical pixels, y down from the top left
+    pub fn to_physical_pixels(&self, point: Vec2) -> Vec2 {
+        match self.coordinate_space {
+            CoordinateSpace::Normalized => Vec2::new(point.x + 1.0, 1.0 - point.y) * 0.5 * self.physical_size(),
+            CoordinateSpace::Logical => point * self.scale_factor,
+            CoordinateSpace::Physical => point
+        }
+    }
+
+    // uploads the mesh once, it stays on the gpu until released
+    pub fn create_mesh(&mut self, mesh: Mesh) -> MeshHandle {
+        self.retain_mesh(mesh, false)
+    }
+
+    // keeps a cpu copy that can be edited with mesh_mut and is re-uploaded when changed
+    pub fn create_dynamic_mesh(&mut self, mesh: Mesh) -> MeshHandle {
+        self.retain_mesh(mesh, true)
+    }
+
+    fn retain_mesh(&mut self, mesh: Mesh, dynamic: bool) -> MeshHandle {
+        let handle = MeshHandle::next();
+        self.retained_meshes.insert(handle, RetainedMesh { opaque: mesh.is_opaque(), bounds: mesh.bounds(), mesh: Some(mesh), dynamic, dirty: true });
+        handle
+    }
+
+    pub fn mesh_mut(&mut self, handle: &MeshHandle) -> Option<&mut Mesh> {
+        let retained = self.retained_meshes.get_mut(handle).filter(|retained| retained.dynamic)?;
+        retained.dirty = true;
+        retained.mesh.as_mut()
+    }
+
+    pub fn update_mesh(&mut self, handle: &MeshHandle, mesh: Mesh) {
+        if let Some(retained) = self.retained_meshes.get_mut(handle) {
+            retained.bounds = mesh.bounds();
+            retained.mesh = Some(mesh);
+            retained.dirty = true;
+        }
+    }
+
+    pub fn release_mesh(&mut self, handle: MeshHandle) {
+        if self.retained_meshes.remove(&handle).is_some() {
+            self.released_meshes.push(handle);
+        }
+    }
+
+    pub(crate) fn upload_dirty_meshes(&mut self, mut upload: impl FnMut(MeshHandle, &Mesh)) {
+        for (handle, retained) in self.retained_meshes.iter_mut().filter(|(_, retained)| retained.dirty) {
+            if let Some(ref mesh) = retained.mesh {
+                retained.opaque = mesh.is_opaque();
+                retained.bounds = mesh.bounds();
+                upload(*handle, mesh);
+            }
+            retained.dirty = false;
+            if !retained.dynamic {
+                retained.mesh = None;
+            }
+        }
+    }
+
+    pub(crate) fn take_released_meshes(&mut self) -> Vec<MeshHandle> {
+        std::mem::take(&mut self.released_meshes)
     }
 
     pub fn set_background_color(&mut self, color: Color) -> &mut Self {
@@ -124,44 +865,658 @@ impl Renderer {
         self
     }
 
-    pub fn draw_triangle(&mut self, transform: Transform2D, color: Color) -> &mut Self {
-        self.commands.push(DrawCommand::Mesh2D {
+    pub fn draw_triangle(&mut self, transform: Transform2D, paint: impl Into<Paint>) -> &mut Self {
+        if self.edge_feather.is_some() {
+            return self.fill_path(transform, Mesh::triangle_path(), 0.5, paint);
+        }
+        self.push_command(DrawCommand::Mesh2D {
             mesh: Mesh::new_triangle(),
             image: None,
             transform,
-            color
+            paint: paint.into()
         });
         self
     }
 
-    pub fn draw_rectangle(&mut self, transform: Transform2D, dimension: Vec2, color: Color) -> &mut Self {
-        self.commands.push(DrawCommand::Mesh2D {
+    pub fn draw_rectangle(&mut self, transform: Transform2D, dimension: Vec2, paint: impl Into<Paint>) -> &mut Self {
+        if self.edge_feather.is_some() {
+            return self.fill_path(transform, Mesh::rectangle_path(dimension), dimension.max_element(), paint);
+        }
+        self.push_command(DrawCommand::Mesh2D {
             mesh: Mesh::new_rectangle(dimension.x, dimension.y),
             image: None,
             transform,
-            color
+            paint: paint.into()
         });
         self
     }
 
-    pub fn draw_circle(&mut self, transform: Transform2D, radius: f32, segments: u16, color: Color) -> &mut Self {
-        self.commands.push(DrawCommand::Mesh2D {
-            mesh: Mesh::new_circle(radius, segments),
+    pub fn draw_line(&mut self, start: Vec2, end: Vec2, stroke: impl Into<StrokeStyle>, paint: impl Into<Paint>) -> &mut Self {
+        let radius = (end - start).length();
+        self.stroke_path(Transform2D::at(0.0, 0.0), Mesh::line_path(start, end), radius, stroke, paint)
+    }
+
+    // a segment count of zero uses the renderer's tessellation setting
+    pub fn draw_circle(&mut self, transform: Transform2D, radius: f32, segments: u16, paint: impl Into<Paint>) -> &mut Self {
+        let tessellation = match segments {
+            0 => self.tessellation,
+            _ => Tessellation::Segments(segments)
+        };
+        self.draw_circle_tessellated(transform, radius, tessellation, paint)
+    }
+
+    pub fn draw_circle_tessellated(&mut self, transform: Transform2D, radius: f32, tessellation: Tessellation, paint: impl Into<Paint>) -> &mut Self {
+        if let Some(feather) = self.feather(&transform) {
+            let (path, tolerance) = match tessellation {
+                Tessellation::Segments(segments) if segments >= 3 => (Mesh::circle_path(radius, segments), FillOptions::DEFAULT_TOLERANCE),
+                _ => (Mesh::ellipse_path(Vec2::splat(radius)), self.tolerance(tessellation, radius, &transform))
+            };
+            self.push_command(DrawCommand::Mesh2D {
+                mesh: Mesh::new_feathered_fill(path, tolerance, feather),
+                image: None,
+                transform,
+                paint: paint.into()
+            });
+            return self;
+        }
+
+        let mesh = match tessellation {
+            Tessellation::Segments(segments) => Mesh::new_circle(radius, segments),
+            _ => Mesh::new_circle_with_tolerance(radius, self.tolerance(tessellation, radius, &transform))
+        };
+        self.push_command(DrawCommand::Mesh2D {
+            mesh,
             image: None,
             transform,
-            color
+            paint: paint.into()
+        });
+        self
+    }
+
+    pub fn stroke_circle(&mut self, transform: Transform2D, radius: f32, stroke: impl Into<StrokeStyle>, paint: impl Into<Paint>) -> &mut Self {
+        self.stroke_circle_tessellated(transform, radius, self.tessellation, stroke, paint)
+    }
+
+    pub fn stroke_circle_tessellated(&mut self, transform: Transform2D, radius: f32, tessellation: Tessellation, stroke: impl Into<StrokeStyle>, paint: impl Into<Paint>) -> &mut Self {
+        self.stroke_path_tessellated(transform, Mesh::ellipse_path(Vec2::splat(radius)), radius, tessellation, stroke, paint)
+    }
+
+    pub fn stroke_rectangle(&mut self, transform: Transform2D, dimension: Vec2, stroke: impl Into<StrokeStyle>, paint: impl Into<Paint>) -> &mut Self {
+        self.stroke_path(transform, Mesh::rectangle_path(dimension), dimension.max_element(), stroke, paint)
+    }
+
+    pub fn draw_ellipse(&mut self, transform: Transform2D, radii: Vec2, paint: impl Into<Paint>) -> &mut Self {
+        self.draw_ellipse_tessellated(transform, radii, self.tessellation, paint)
+    }
+
+    pub fn draw_ellipse_tessellated(&mut self, transform: Transform2D, radii: Vec2, tessellation: Tessellation, paint: impl Into<Paint>) -> &mut Self {
+        self.fill_path_tessellated(transform, Mesh::ellipse_path(radii), radii.max_element(), tessellation, paint)
+    }
+
+    pub fn stroke_ellipse(&mut self, transform: Transform2D, radii: Vec2, stroke: impl Into<StrokeStyle>, paint: impl Into<Paint>) -> &mut Self {
+        self.stroke_ellipse_tessellated(transform, radii, self.tessellation, stroke, paint)
+    }
+
+    pub fn stroke_ellipse_tessellated(&mut self, transform: Transform2D, radii: Vec2, tessellation: Tessellation, stroke: impl Into<StrokeStyle>, paint: impl Into<Paint>) -> &mut Self {
+        self.stroke_path_tessellated(transform, Mesh::ellipse_path(radii), radii.max_element(), tessellation, stroke, paint)
+    }
+
+    // angles are in radians from the positive x axis
+    pub fn draw_arc(&mut self, transform: Transform2D, radius: f32, start_angle: f32, sweep_angle: f32, stroke: impl Into<StrokeStyle>, paint: impl Into<Paint>) -> &mut Self {
+        self.stroke_path(transform, Mesh::arc_path(Vec2::splat(radius), start_angle, sweep_angle), radius, stroke, paint)
+    }
+
+    pub fn draw_pie(&mut self, transform: Transform2D, radius: f32, start_angle: f32, sweep_angle: f32, paint: impl Into<Paint>) -> &mut Self {
+        self.fill_path(transform, Mesh::pie_path(Vec2::splat(radius), start_angle, sweep_angle), radius, paint)
+    }
+
+    pub fn stroke_pie(&mut self, transform: Transform2D, radius: f32, start_angle: f32, sweep_angle: f32, stroke: impl Into<StrokeStyle>, paint: impl Into<Paint>) -> &mut Self {
+        self.stroke_path(transform, Mesh::pie_path(Vec2::splat(radius), start_angle, sweep_angle), radius, stroke, paint)
+    }
+
+    pub fn draw_rounded_rectangle(&mut self, transform: Transform2D, dimension: Vec2, radii: CornerRadii, paint: impl Into<Paint>) -> &mut Self {
+        self.draw_rounded_rectangle_tessellated(transform, dimension, radii, self.tessellation, paint)
+    }
+
+    pub fn draw_rounded_rectangle_tessellated(&mut self, transform: Transform2D, dimension: Vec2, radii: CornerRadii, tessellation: Tessellation, paint: impl Into<Paint>) -> &mut Self {
+        self.fill_path_tessellated(transform, Mesh::rounded_rectangle_path(dimension, radii), radii.max(), tessellation, paint)
+    }
+
+    pub fn stroke_rounded_rectangle(&mut self, transform: Transform2D, dimension: Vec2, radii: CornerRadii, stroke: impl Into<StrokeStyle>, paint: impl Into<Paint>) -> &mut Self {
+        self.stroke_rounded_rectangle_tessellated(transform, dimension, radii, self.tessellation, stroke, paint)
+    }
+
+    pub fn stroke_rounded_rectangle_tessellated(&mut self, transform: Transform2D, dimension: Vec2, radii: CornerRadii, tessellation: Tessellation, stroke: impl Into<StrokeStyle>, paint: impl Into<Paint>) -> &mut Self {
+        self.stroke_path_tessellated(transform, Mesh::rounded_rectangle_path(dimension, radii), radii.max(), tessellation, stroke, paint)
+    }
+
+    pub fn draw_regular_polygon(&mut self, transform: Transform2D, radius: f32, sides: u16, paint: impl Into<Paint>) -> &mut Self {
+        self.fill_path(transform, Mesh::regular_polygon_path(radius, sides), radius, paint)
+    }
+
+    pub fn stroke_regular_polygon(&mut self, transform: Transform2D, radius: f32, sides: u16, stroke: impl Into<StrokeStyle>, paint: impl Into<Paint>) -> &mut Self {
+        self.stroke_path(transform, Mesh::regular_polygon_path(radius, sides), radius, stroke, paint)
+    }
+
+    pub fn draw_star(&mut self, transform: Transform2D, points: u16, outer_radius: f32, inner_radius: f32, paint: impl Into<Paint>) -> &mut Self {
+        self.fill_path(transform, Mesh::star_path(points, outer_radius, inner_radius), outer_radius, paint)
+    }
+
+    pub fn stroke_star(&mut self, transform: Transform2D, points: u16, outer_radius: f32, inner_radius: f32, stroke: impl Into<StrokeStyle>, paint: impl Into<Paint>) -> &mut Self {
+        self.stroke_path(transform, Mesh::star_path(points, outer_radius, inner_radius), outer_radius, stroke, paint)
+    }
+
+    // radius is the size of the largest curve in the path and is used to pick a tessellation tolerance
+    pub fn fill_path(&mut self, transform: Transform2D, path: Path, radius: f32, paint: impl Into<Paint>) -> &mut Self {
+        self.fill_path_tessellated(transform, path, radius, self.tessellation, paint)
+    }
+
+    // the _tessellated variants flatten curves with the given setting instead of the renderer's,
+    // arcs and pies can be drawn through these with Mesh::arc_path and Mesh::pie_path
+    pub fn fill_path_tessellated(&mut self, transform: Transform2D, path: Path, radius: f32, tessellation: Tessellation, paint: impl Into<Paint>) -> &mut Self {
+        let tolerance = self.tolerance(tessellation, radius, &transform);
+        let mesh = match self.feather(&transform) {
+            Some(feather) => Mesh::new_feathered_fill(path, tolerance, feather),
+            None => Mesh::new_filled_path_with_tolerance(path, tolerance)
+        };
+        self.push_command(DrawCommand::Mesh2D {
+            mesh,
+            image: None,
+            transform,
+            paint: paint.into()
+        });
+        self
+    }
+
+    pub fn stroke_path(&mut self, transform: Transform2D, path: Path, radius: f32, stroke: impl Into<StrokeStyle>, paint: impl Into<Paint>) -> &mut Self {
+        self.stroke_path_tessellated(transform, path, radius, self.tessellation, stroke, paint)
+    }
+
+    pub fn stroke_path_tessellated(&mut self, transform: Transform2D, path: Path, radius: f32, tessellation: Tessellation, stroke: impl Into<StrokeStyle>, paint: impl Into<Paint>) -> &mut Self {
+        let tolerance = self.tolerance(tessellation, radius, &transform);
+        let mesh = match self.feather(&transform) {
+            Some(feather) => Mesh::new_feathered_stroke(path, &stroke.into(), tolerance, feather),
+            None => Mesh::new_stroked_path_with_style(path, &stroke.into(), tolerance)
+        };
+        self.push_command(DrawCommand::Mesh2D {
+            mesh,
+            image: None,
+            transform,
+            paint: paint.into()
+        });
+        self
+    }
+
+    pub fn draw_mesh(&mut self, transform: Transform2D, handle: &MeshHandle, paint: impl Into<Paint>, image: Option<Arc<Image>>) -> &mut Self {
+        self.push_command(DrawCommand::RetainedMesh2D {
+            handle: *handle,
+            image,
+            transform,
+            paint: paint.into()
         });
         self
     }
 
     pub fn draw_image(&mut self, transform: Transform2D, img: Arc<Image>) -> &mut Self {
-        self.commands.push(DrawCommand::Mesh2D {
-            mesh: Mesh::new_rectangle(img.image.width() as f32, img.image.height() as f32),
+        let region = Rect::new(Vec2::ZERO, Vec2::new(img.image.width() as f32, img.image.height() as f32));
+        self.draw_image_region(transform, img, region)
+    }
+
+    // draws every instance as a textured quad in a single instanced draw, white squares without an image
+    pub fn draw_particles(&mut self, transform: Transform2D, mut instances: Vec<ParticleInstance>, image: Option<Arc<Image>>) -> &mut Self {
+        // the quad's uvs run top to bottom in a y down space, mirror it so images stay upright
+        if self.coordinate_space == CoordinateSpace::Normalized {
+            for instance in instances.iter_mut() {
+                instance.size[1] = -instance.size[1];
+            }
+        }
+
+        self.push_command(DrawCommand::Particles { instances, transform, image });
+        self
+    }
+
+    // draws a scalable panel of size logical pixels, the corners cut from the image by insets keep their
+    // pixel size while the edges and center stretch or tile between them
+    pub fn draw_nine_slice(&mut self, transform: Transform2D, img: Arc<Image>, insets: impl Into<NineSlice>, size: Vec2) -> &mut Self {
+        let image_size = Vec2::new(img.image.width() as f32, img.image.height() as f32);
+        let mut mesh = Mesh::new_nine_slice(size, image_size, &insets.into());
+        self.scale_from_logical_pixels(&mut mesh);
+
+        self.push_command(DrawCommand::Mesh2D {
+            mesh,
             image: Some(img),
             transform,
-            color: Color::NONE
+            paint: Paint::Solid(Color::WHITE)
         });
         self
     }
 
-}
\ No newline at end of file
+    // draws text with its top left corner at the transform's position, size is the font's pixel height
+    // in logical pixels and lines are split on newlines
+    // gradients color the glyphs, image patterns are not supported as the glyph atlas needs the texture slot
+    pub fn draw_text(&mut self, transform: Transform2D, text: &str, font: &Font, size: f32, paint: impl Into<Paint>) -> &mut Self {
+        let (mut mesh, atlas) = font.mesh(text, size);
+        if mesh.indices.is_empty() {
+            return self;
+        }
+        self.scale_from_logical_pixels(&mut mesh);
+
+        self.push_command(DrawCommand::Mesh2D {
+            mesh,
+            image: Some(atlas),
+            transform,
+            paint: paint.into()
+        });
+        self
+    }
+
+    // scales a mesh built in y down logical pixels into the coordinate space, mirrored rather than
+    // flipping the uvs so that what was on top stays on top
+    fn scale_from_logical_pixels(&self, mesh: &mut Mesh) {
+        let mut scale = self.logical_pixel();
+        if self.coordinate_space == CoordinateSpace::Normalized {
+            scale.y = -scale.y;
+        }
+        for vertex in mesh.vertices.iter_mut() {
+            vertex.position[0] *= scale.x;
+            vertex.position[1] *= scale.y;
+        }
+    }
+
+    // draws part of an image, region is in image pixels from the top left corner, e.g. one frame of a sprite sheet
+    pub fn draw_image_region(&mut self, transform: Transform2D, img: Arc<Image>, region: Rect) -> &mut Self {
+        // images are drawn at their native size in logical pixels
+        let size = region.size() * self.logical_pixel();
+        let mut mesh = Mesh::new_rectangle(size.x, size.y);
+        if self.coordinate_space == CoordinateSpace::Normalized {
+            mesh.flip_uv_vertical();
+        }
+
+        let image_size = Vec2::new(img.image.width() as f32, img.image.height() as f32).max(Vec2::ONE);
+        for vertex in mesh.vertices.iter_mut() {
+            let uv = (region.min + Vec2::from(vertex.uv) * region.size()) / image_size;
+            vertex.uv = uv.into();
+        }
+
+        self.push_command(DrawCommand::Mesh2D {
+            mesh,
+            image: Some(img),
+            transform,
+            paint: Paint::Solid(Color::WHITE)
+        });
+        self
+    }
+
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::layer::Camera2D;
+
+    fn logical_renderer() -> Renderer {
+        let mut renderer = Renderer::new();
+        renderer.set_viewport(PhysicalSize::new(800, 600), 2.0);
+        renderer.set_coordinate_space(CoordinateSpace::Logical);
+        renderer
+    }
+
+    #[test]
+    fn clip_rects_follow_the_transform_stack() {
+        let mut renderer = logical_renderer();
+        renderer.push_transform(Transform2D::at(10.0, 0.0));
+        renderer.push_clip_rect(Rect::new(Vec2::new(0.0, 10.0), Vec2::new(20.0, 30.0)));
+        assert_eq!(renderer.clip_rect(), Some(Rect::new(Vec2::new(20.0, 20.0), Vec2::new(60.0, 60.0))));
+    }
+
+    #[test]
+    fn clip_rects_follow_the_layer_camera() {
+        let mut renderer = logical_renderer();
+        let world = renderer.add_layer("world", 0);
+        renderer.layer_mut(world).unwrap().camera = Camera2D { position: Vec2::new(100.0, 0.0), zoom: 2.0, rotation: 0.0 };
+        renderer.set_layer(world);
+        renderer.push_clip_rect(Rect::new(Vec2::new(100.0, 0.0), Vec2::new(150.0, 50.0)));
+        assert_eq!(renderer.clip_rect(), Some(Rect::new(Vec2::ZERO, Vec2::new(200.0, 200.0))));
+    }
+
+    fn square(renderer: &mut Renderer, x: f32, y: f32, size: f32) -> usize {
+        renderer.draw_rectangle(Transform2D::at(x, y), Vec2::splat(size), Color::WHITE);
+        renderer.commands.len() - 1
+    }
+
+    #[test]
+    fn command_at_finds_the_topmost_draw_by_order_and_z() {
+        let mut renderer = logical_renderer();
+        let bottom = square(&mut renderer, 100.0, 100.0, 100.0);
+        let top = square(&mut renderer, 120.0, 100.0, 100.0);
+        assert_eq!(renderer.command_at(Vec2::new(100.0, 100.0)), Some(top));
+        assert_eq!(renderer.command_at(Vec2::new(60.0, 100.0)), Some(bottom));
+        assert_eq!(renderer.command_at(Vec2::new(300.0, 100.0)), None);
+
+        renderer.set_z(1.0);
+        let raised = square(&mut renderer, 80.0, 100.0, 20.0);
+        renderer.set_z(0.0);
+        square(&mut renderer, 80.0, 100.0, 20.0);
+        assert_eq!(renderer.command_at(Vec2::new(80.0, 100.0)), Some(raised));
+    }
+
+    #[test]
+    fn command_at_follows_layer_order_visibility_and_cameras() {
+        let mut renderer = logical_renderer();
+        let ui = renderer.add_layer("ui", 1);
+        let background = renderer.add_layer("background", -1);
+
+        renderer.set_layer(ui);
+        let on_ui = square(&mut renderer, 100.0, 100.0, 50.0);
+        renderer.set_layer(LayerId::DEFAULT);
+        let on_default = square(&mut renderer, 100.0, 100.0, 50.0);
+        renderer.set_layer(background);
+        let on_background = square(&mut renderer, 150.0, 100.0, 50.0);
+
+        assert_eq!(renderer.command_at(Vec2::new(100.0, 100.0)), Some(on_ui));
+        renderer.layer_mut(ui).unwrap().visible = false;
+        assert_eq!(renderer.command_at(Vec2::new(100.0, 100.0)), Some(on_default));
+
+        // the background camera looks 50 to the right, so its square shows up where the others are
+        assert_eq!(renderer.command_at(Vec2::new(160.0, 100.0)), Some(on_background));
+        renderer.layer_mut(background).unwrap().camera = Camera2D::new(Vec2::new(50.0, 0.0));
+        assert_eq!(renderer.command_at(Vec2::new(160.0, 100.0)), None);
+        assert_eq!(renderer.command_at(Vec2::new(110.0, 100.0)), Some(on_default));
+        renderer.layer_mut(LayerId::DEFAULT).unwrap().visible = false;
+        assert_eq!(renderer.command_at(Vec2::new(110.0, 100.0)), Some(on_background));
+    }
+
+    #[test]
+    fn command_at_ignores_draws_outside_their_clip() {
+        let mut renderer = logical_renderer();
+        renderer.push_clip_rect(Rect::new(Vec2::ZERO, Vec2::new(50.0, 300.0)));
+        let clipped = square(&mut renderer, 100.0, 100.0, 200.0);
+        renderer.pop_clip_rect();
+        assert_eq!(renderer.command_at(Vec2::new(25.0, 100.0)), Some(clipped));
+        assert_eq!(renderer.command_at(Vec2::new(100.0, 100.0)), None);
+
+        let unclipped = square(&mut renderer, 100.0, 100.0, 20.0);
+        assert_eq!(renderer.command_at(Vec2::new(100.0, 100.0)), Some(unclipped));
+    }
+
+    #[test]
+    fn command_at_ignores_draws_masked_away() {
+        let mut renderer = logical_renderer();
+        renderer.begin_mask(Transform2D::at(100.0, 100.0), Mesh::new_rectangle(20.0, 20.0));
+        let masked = square(&mut renderer, 100.0, 100.0, 100.0);
+        renderer.end_mask();
+        assert_eq!(renderer.command_at(Vec2::new(100.0, 100.0)), Some(masked));
+        assert_eq!(renderer.command_at(Vec2::new(130.0, 100.0)), None);
+
+        renderer.begin_inverted_mask(Transform2D::at(100.0, 100.0), Mesh::new_rectangle(20.0, 20.0));
+        let inverted = square(&mut renderer, 100.0, 100.0, 100.0);
+        renderer.end_mask();
+        assert_eq!(renderer.command_at(Vec2::new(130.0, 100.0)), Some(inverted));
+        assert_eq!(renderer.command_at(Vec2::new(100.0, 100.0)), Some(masked));
+    }
+
+    fn last_vertex_count(renderer: &Renderer) -> usize {
+        match renderer.commands.last() {
+            Some(DrawCommand::Mesh2D { mesh, .. }) => mesh.vertices.len(),
+            _ => panic!("the last command is not a mesh")
+        }
+    }
+
+    #[test]
+    fn tessellated_shapes_honour_their_segment_counts() {
+        let mut renderer = logical_renderer();
+        renderer.set_tessellation(Tessellation::Segments(8));
+        // vertex counts at 8 and 64 segments, then with the renderer's setting
+        let mut count = |draw: &dyn Fn(&mut Renderer, Tessellation)| {
+            let counts = [8, 64].map(|segments| {
+                draw(&mut renderer, Tessellation::Segments(segments));
+                last_vertex_count(&renderer)
+            });
+            let tessellation = renderer.tessellation();
+            draw(&mut renderer, tessellation);
+            (counts, last_vertex_count(&renderer))
+        };
+
+        // polygon circles are exact, curves flattened to a matching tolerance get at least as many segments
+        // and at most twice as many, as the curve flattening is conservative
+        let (circle, _) = count(&|renderer, tessellation| { renderer.draw_circle_tessellated(Transform2D::default(), 100.0, tessellation, Color::WHITE); });
+        assert_eq!(circle, [8, 64]);
+        let (ellipse, default) = count(&|renderer, tessellation| { renderer.draw_ellipse_tessellated(Transform2D::default(), Vec2::splat(100.0), tessellation, Color::WHITE); });
+        assert!((8..=17).contains(&ellipse[0]) && (64..=129).contains(&ellipse[1]), "{ellipse:?}");
+        // without an override the renderer's setting is used
+        assert_eq!(default, ellipse[0]);
+        let (pie, _) = count(&|renderer, tessellation| { renderer.fill_path_tessellated(Transform2D::default(), Mesh::pie_path(Vec2::splat(100.0), 0.0, std::f32::consts::PI), 100.0, tessellation, Color::WHITE); });
+        assert!(pie[0] < pie[1], "{pie:?}");
+        let (stroke, default) = count(&|renderer, tessellation| { renderer.stroke_circle_tessellated(Transform2D::default(), 100.0, tessellation, 2.0, Color::WHITE); });
+        assert!(stroke[0] * 4 < stroke[1], "{stroke:?}");
+        assert_eq!(default, stroke[0]);
+    }
+
+    #[test]
+    fn tessellation_overrides_leave_the_renderer_setting_alone() {
+        let mut renderer = logical_renderer();
+        renderer.set_tessellation(Tessellation::Tolerance(5.0));
+        renderer.fill_path_tessellated(Transform2D::default(), Mesh::ellipse_path(Vec2::splat(100.0)), 100.0, Tessellation::Tolerance(0.01), Color::WHITE);
+        let fine = last_vertex_count(&renderer);
+        renderer.fill_path(Transform2D::default(), Mesh::ellipse_path(Vec2::splat(100.0)), 100.0, Color::WHITE);
+        assert!(last_vertex_count(&renderer) * 4 < fine);
+        assert_eq!(renderer.tessellation(), Tessellation::Tolerance(5.0));
+    }
+
+    fn square_at_z(renderer: &mut Renderer, z: f32, color: Color) -> usize {
+        renderer.set_z(z);
+        renderer.draw_rectangle(Transform2D::default(), Vec2::splat(10.0), color);
+        renderer.commands.len() - 1
+    }
+
+    fn sorted(renderer: &mut Renderer) -> Vec<usize> {
+        renderer.sort_commands().iter().map(|call| call.command).collect()
+    }
+
+    #[test]
+    fn sorting_is_stable_by_z_within_layers_in_layer_order() {
+        let mut renderer = logical_renderer();
+        let top = renderer.add_layer("top", 1);
+        let hidden = renderer.add_layer("hidden", 2);
+
+        renderer.set_layer(top);
+        let above = square_at_z(&mut renderer, -5.0, Color::WHITE);
+        renderer.set_layer(hidden);
+        square_at_z(&mut renderer, 0.0, Color::WHITE);
+        renderer.layer_mut(hidden).unwrap().visible = false;
+        renderer.set_layer(LayerId::DEFAULT);
+        let first = square_at_z(&mut renderer, 1.0, Color::WHITE);
+        let second = square_at_z(&mut renderer, 0.0, Color::WHITE);
+        let third = square_at_z(&mut renderer, 1.0, Color::WHITE);
+        let fourth = square_at_z(&mut renderer, 0.0, Color::WHITE);
+
+        // draws with the same z keep the order they were recorded in
+        assert_eq!(sorted(&mut renderer), vec![second, fourth, first, third, above]);
+    }
+
+    #[test]
+    fn draws_are_never_sorted_across_state_changes() {
+        let mut renderer = logical_renderer();
+        let before = square_at_z(&mut renderer, 2.0, Color::WHITE);
+        renderer.push_clip_rect(Rect::new(Vec2::ZERO, Vec2::splat(50.0)));
+        let clip = renderer.commands.len() - 1;
+        let clipped = square_at_z(&mut renderer, 1.0, Color::WHITE);
+        let clipped_below = square_at_z(&mut renderer, 0.0, Color::WHITE);
+        renderer.pop_clip_rect();
+        let unclip = renderer.commands.len() - 1;
+        let after = square_at_z(&mut renderer, -1.0, Color::WHITE);
+
+        // the clip opens its segment and the draws inside only sort among themselves
+        assert_eq!(sorted(&mut renderer), vec![before, clip, clipped_below, clipped, unclip, after]);
+    }
+
+    #[test]
+    fn opaque_draws_go_front_to_back_before_translucent_ones() {
+        let mut renderer = logical_renderer();
+        renderer.layer_mut(LayerId::DEFAULT).unwrap().depth_test = true;
+        let translucent = Color::new(1.0, 1.0, 1.0, 0.5);
+        let opaque_low = square_at_z(&mut renderer, 0.0, Color::WHITE);
+        let translucent_high = square_at_z(&mut renderer, 3.0, translucent);
+        let opaque_high = square_at_z(&mut renderer, 2.0, Color::WHITE);
+        let translucent_low = square_at_z(&mut renderer, 0.0, translucent);
+        let opaque_middle = square_at_z(&mut renderer, 1.0, Color::WHITE);
+
+        assert_eq!(sorted(&mut renderer), vec![opaque_high, opaque_middle, opaque_low, translucent_low, translucent_high]);
+
+        // higher z is nearer, draws sharing a z share a depth
+        let depth = |command: usize| renderer.draw_calls().iter().find(|call| call.command == command).unwrap().depth;
+        assert_eq!(depth(opaque_low), depth(translucent_low));
+        assert!(depth(translucent_high) < depth(opaque_high) && depth(opaque_high) < depth(opaque_middle) && depth(opaque_middle) < depth(opaque_low));
+        assert!(renderer.draw_calls().iter().all(|call| call.depth > 0.0 && call.depth < 1.0));
+        assert_eq!(renderer.draw_calls().iter().filter(|call| call.opaque).count(), 3);
+    }
+
+    #[test]
+    fn layers_without_depth_testing_draw_everything_back_to_front() {
+        let mut renderer = logical_renderer();
+        let translucent = square_at_z(&mut renderer, 1.0, Color::new(1.0, 1.0, 1.0, 0.5));
+        let opaque = square_at_z(&mut renderer, 0.0, Color::WHITE);
+        assert_eq!(sorted(&mut renderer), vec![opaque, translucent]);
+        assert!(renderer.draw_calls().iter().all(|call| !call.opaque && call.depth == 0.0));
+    }
+
+    fn uploads(renderer: &mut Renderer) -> Vec<(MeshHandle, usize)> {
+        let mut uploaded = Vec::new();
+        renderer.upload_dirty_meshes(|handle, mesh| uploaded.push((handle, mesh.vertices.len())));
+        uploaded
+    }
+
+    #[test]
+    fn static_meshes_upload_once_and_keep_their_bounds() {
+        let mut renderer = logical_renderer();
+        let handle = renderer.create_mesh(Mesh::new_rectangle(20.0, 20.0));
+        assert!(renderer.mesh_mut(&handle).is_none());
+        assert_eq!(uploads(&mut renderer), vec![(handle, 4)]);
+        assert!(uploads(&mut renderer).is_empty());
+
+        // the cpu copy is gone but the bounds still hit test
+        renderer.draw_mesh(Transform2D::at(100.0, 100.0), &handle, Color::WHITE, None);
+        assert_eq!(renderer.command_at(Vec2::new(105.0, 105.0)), Some(0));
+        assert_eq!(renderer.command_at(Vec2::new(115.0, 105.0)), None);
+
+        // replacing the mesh uploads it again and moves the bounds with it
+        renderer.update_mesh(&handle, Mesh::new_rectangle(40.0, 40.0));
+        assert_eq!(renderer.command_at(Vec2::new(115.0, 105.0)), Some(0));
+        assert_eq!(uploads(&mut renderer), vec![(handle, 4)]);
+        assert!(uploads(&mut renderer).is_empty());
+    }
+
+    #[test]
+    fn dynamic_meshes_reupload_when_edited() {
+        let mut renderer = logical_renderer();
+        let handle = renderer.create_dynamic_mesh(Mesh::new_rectangle(20.0, 20.0));
+        assert_eq!(uploads(&mut renderer).len(), 1);
+        assert!(uploads(&mut renderer).is_empty());
+
+        renderer.mesh_mut(&handle).unwrap().set_color(Color::new(1.0, 1.0, 1.0, 0.5));
+        assert_eq!(uploads(&mut renderer), vec![(handle, 4)]);
+        // the edit made it translucent, so it leaves the opaque pass
+        renderer.layer_mut(LayerId::DEFAULT).unwrap().depth_test = true;
+        renderer.draw_mesh(Transform2D::default(), &handle, Color::WHITE, None);
+        assert!(!renderer.sort_commands()[0].opaque);
+    }
+
+    #[test]
+    fn released_meshes_are_reported_once() {
+        let mut renderer = logical_renderer();
+        let kept = renderer.create_mesh(Mesh::new_rectangle(20.0, 20.0));
+        let released = renderer.create_dynamic_mesh(Mesh::new_rectangle(20.0, 20.0));
+        renderer.release_mesh(released);
+        renderer.release_mesh(released);
+        assert_eq!(renderer.take_released_meshes(), vec![released]);
+        assert!(renderer.take_released_meshes().is_empty());
+
+        // a released handle is ignored everywhere else
+        assert!(renderer.mesh_mut(&released).is_none());
+        renderer.update_mesh(&released, Mesh::new_rectangle(20.0, 20.0));
+        assert_eq!(uploads(&mut renderer), vec![(kept, 4)]);
+        renderer.draw_mesh(Transform2D::default(), &released, Color::WHITE, None);
+        assert_eq!(renderer.command_at(Vec2::ZERO), None);
+    }
+
+    #[test]
+    fn edge_feathering_is_sized_in_physical_pixels() {
+        let mut renderer = logical_renderer();
+        renderer.set_edge_feather(Some(0.0));
+        assert_eq!(renderer.edge_feather(), None);
+        renderer.draw_rectangle(Transform2D::default(), Vec2::splat(10.0), Color::WHITE);
+        assert_eq!(last_vertex_count(&renderer), 4);
+
+        // two physical pixels are one logical pixel at a scale factor of 2, and half that on a shape drawn twice as big
+        renderer.set_edge_feather(Some(2.0));
+        let fringe = |renderer: &Renderer| match renderer.commands.last() {
+            Some(DrawCommand::Mesh2D { mesh, .. }) => mesh.vertices.iter()
+                .filter(|vertex| vertex.color[3] == 0.0)
+                .map(|vertex| vertex.position[0].abs().max(vertex.position[1].abs()) - 5.0)
+                .fold(0.0, f32::max),
+            _ => panic!("the last command is not a mesh")
+        };
+        renderer.draw_rectangle(Transform2D::default(), Vec2::splat(10.0), Color::WHITE);
+        assert!((fringe(&renderer) - 1.0).abs() < 1e-4);
+        renderer.draw_rectangle(Transform2D::default().with_scale(Vec2::splat(2.0)), Vec2::splat(10.0), Color::WHITE);
+        assert!((fringe(&renderer) - 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn masks_nest_and_ignore_unmatched_ends() {
+        let mut renderer = logical_renderer();
+        renderer.end_mask();
+        assert!(renderer.commands.is_empty());
+
+        renderer.begin_mask(Transform2D::default(), Mesh::new_rectangle(10.0, 10.0));
+        renderer.begin_inverted_mask(Transform2D::default(), Mesh::new_rectangle(5.0, 5.0));
+        assert_eq!(renderer.mask_depth(), 2);
+        renderer.end_mask().end_mask().end_mask();
+        assert_eq!(renderer.mask_depth(), 0);
+        assert_eq!(renderer.commands.len(), 4);
+
+        // the stencil buffer only counts to 255
+        for _ in 0..300 {
+            renderer.begin_mask(Transform2D::default(), Mesh::new_rectangle(10.0, 10.0));
+        }
+        assert_eq!(renderer.mask_depth(), u8::MAX as u32);
+        assert_eq!(renderer.commands.len(), 4 + u8::MAX as usize);
+        renderer.end_frame();
+        assert_eq!(renderer.mask_depth(), 0);
+    }
+
+    #[test]
+    fn nested_masks_narrow_the_area_left_open() {
+        let mut renderer = logical_renderer();
+        // the mask goes through the transform stack like any other draw
+        renderer.push_transform(Transform2D::at(100.0, 100.0));
+        renderer.begin_mask(Transform2D::default(), Mesh::new_rectangle(40.0, 40.0));
+        renderer.begin_inverted_mask(Transform2D::at(-10.0, 0.0), Mesh::new_rectangle(20.0, 40.0));
+        renderer.pop_transform();
+        let masked = square(&mut renderer, 100.0, 100.0, 100.0);
+        renderer.end_mask();
+        renderer.end_mask();
+
+        // open inside the outer mask, but not in its left half covered by the inverted one
+        assert_eq!(renderer.command_at(Vec2::new(110.0, 100.0)), Some(masked));
+        assert_eq!(renderer.command_at(Vec2::new(90.0, 100.0)), None);
+        assert_eq!(renderer.command_at(Vec2::new(130.0, 100.0)), None);
+    }
+
+    #[test]
+    fn draws_are_not_sorted_in_or_out_of_masks() {
+        let mut renderer = logical_renderer();
+        let outside = square_at_z(&mut renderer, 1.0, Color::WHITE);
+        renderer.begin_mask(Transform2D::default(), Mesh::new_rectangle(10.0, 10.0));
+        let inside = square_at_z(&mut renderer, 0.0, Color::WHITE);
+        renderer.end_mask();
+        let after = square_at_z(&mut renderer, -1.0, Color::WHITE);
+        assert_eq!(sorted(&mut renderer), vec![outside, 1, inside, 3, after]);
+    }
+}
@@ -0,0 +1,388 @@
+use glam::{Mat2, Vec2};
+use lyon::path::iterator::PathIterator;
+use lyon::path::{Path, PathEvent};
+use crate::graphics::draw::Rect;
+use crate::graphics::mesh::Mesh;
+use crate::graphics::transform::Transform2D;
+
+// a filled shape in its own units, placed with the same Transform2D it is drawn with
+#[derive(Clone, Debug, PartialEq)]
+pub enum Shape {
+    // centered on the origin, as draw_rectangle draws it
+    Rectangle(Vec2),
+    // centered on the origin
+    Circle(f32),
+    // closed outlines filled even-odd like lyon fills them, so an outline inside another cuts a hole
+    Polygon(Vec<Vec<Vec2>>)
+}
+
+// a rectangle turned to follow a shape's x axis, rotation is in radians like Transform2D's
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct OrientedRect {
+    pub center: Vec2,
+    pub half_size: Vec2,
+    pub rotation: f32
+}
+
+impl OrientedRect {
+
+    pub fn size(&self) -> Vec2 {
+        self.half_size * 2.0
+    }
+
+    // the transform that draws a rectangle of this size over it
+    pub fn transform(&self) -> Transform2D {
+        Transform2D::at(self.center.x, self.center.y).with_rotation(self.rotation)
+    }
+
+    pub fn corners(&self) -> [Vec2; 4] {
+        let (u, v) = Self::axes(self.rotation);
+        let (x, y) = (u * self.half_size.x, v * self.half_size.y);
+        [self.center - x - y, self.center + x - y, self.center + x + y, self.center - x + y]
+    }
+
+    pub fn contains(&self, point: Vec2) -> bool {
+        let (u, v) = Self::axes(self.rotation);
+        let offset = point - self.center;
+        offset.dot(u).abs() <= self.half_size.x && offset.dot(v).abs() <= self.half_size.y
+    }
+
+    fn axes(rotation: f32) -> (Vec2, Vec2) {
+        let u = Vec2::from_angle(rotation);
+        (u, u.perp())
+    }
+
+}
+
+// a shape moved into the space its transform places it in
+enum Placed {
+    Circle(Vec2, f32),
+    Outlines(Vec<Vec<Vec2>>)
+}
+
+impl Shape {
+
+    pub fn polygon(points: Vec<Vec2>) -> Self {
+        Shape::Polygon(vec![points])
+    }
+
+    // flattens any path made by Mesh, such as Mesh::star_path or Mesh::rounded_rectangle_path, every sub path
+    // is treated as closed the way it is filled
+    pub fn from_path(path: &Path, tolerance: f32) -> Self {
+        let mut outlines = Vec::new();
+        let mut outline = Vec::new();
+        for event in path.iter().flattened(tolerance) {
+            match event {
+                PathEvent::Begin { at } => outline = vec![Vec2::new(at.x, at.y)],
+                PathEvent::Line { to, .. } => outline.push(Vec2::new(to.x, to.y)),
+                PathEvent::End { .. } => outlines.push(std::mem::take(&mut outline)),
+                _ => {}
+            }
+        }
+        outlines.retain(|outline| !outline.is_empty());
+        Shape::Polygon(outlines)
+    }
+
+    // the box around the shape in its own units
+    pub fn local_bounds(&self) -> Rect {
+        match self {
+            Shape::Rectangle(size) => Rect::from_center(Vec2::ZERO, size.abs()),
+            Shape::Circle(radius) => Rect::from_center(Vec2::ZERO, Vec2::splat(radius.abs() * 2.0)),
+            Shape::Polygon(outlines) => Rect::bounding(&outlines.concat())
+        }
+    }
+
+    pub fn contains(&self, transform: &Transform2D, point: Vec2) -> bool {
+        let matrix = transform.to_mat3();
+        if matrix.determinant().abs() <= f32::EPSILON {
+            return false;
+        }
+        let local = matrix.inverse().transform_point2(point);
+        match self {
+            Shape::Rectangle(size) => local.abs().cmple(size.abs() * 0.5).all(),
+            Shape::Circle(radius) => local.length() <= radius.abs(),
+            Shape::Polygon(outlines) => outlines_contain(outlines, local)
+        }
+    }
+
+    // the axis aligned box around the shape once transformed
+    pub fn bounds(&self, transform: &Transform2D) -> Rect {
+        match self {
+            Shape::Circle(radius) => {
+                let linear = linear_part(transform);
+                let half = Vec2::new(linear.row(0).length(), linear.row(1).length()) * radius.abs();
+                Rect::from_center(transform.transform_point(Vec2::ZERO), half * 2.0)
+            }
+            _ => Rect::bounding(&self.outlines(transform).concat())
+        }
+    }
+
+    // the smallest box around the shape once transformed that is turned along the transformed x axis
+    pub fn oriented_bounds(&self, transform: &Transform2D) -> OrientedRect {
+        let x_axis = transform.transform_vector(Vec2::X);
+        let rotation = if x_axis.length_squared() > 0.0 { x_axis.y.atan2(x_axis.x) } else { 0.0 };
+        let (u, v) = OrientedRect::axes(rotation);
+        let project = |point: Vec2| Vec2::new(point.dot(u), point.dot(v));
+
+        let (min, max) = match self {
+            Shape::Circle(radius) => {
+                // a transformed circle is an ellipse, which reaches along a direction by the radius times that
+                // direction mapped through the transposed matrix
+                let transposed = linear_part(transform).transpose();
+                let half = Vec2::new((transposed * u).length(), (transposed * v).length()) * radius.abs();
+                let center = project(transform.transform_point(Vec2::ZERO));
+                (center - half, center + half)
+            }
+            _ => {
+                let projected: Vec<Vec2> = self.outlines(transform).concat().into_iter().map(project).collect();
+                let bounds = Rect::bounding(&projected);
+                (bounds.min, bounds.max)
+            }
+        };
+
+        let center = (min + max) * 0.5;
+        OrientedRect { center: u * center.x + v * center.y, half_size: (max - min) * 0.5, rotation }
+    }
+
+    // whether the two shapes share any area once each is placed with its own transform, touching counts
+    pub fn overlaps(&self, transform: &Transform2D, other: &Shape, other_transform: &Transform2D) -> bool {
+        if !self.bounds(transform).intersects(&other.bounds(other_transform)) {
+            return false;
+        }
+
+        match (self.place(transform), other.place(other_transform)) {
+            (Placed::Circle(a, a_radius), Placed::Circle(b, b_radius)) => a.distance(b) <= a_radius + b_radius,
+            (Placed::Circle(center, radius), Placed::Outlines(outlines))
+            | (Placed::Outlines(outlines), Placed::Circle(center, radius)) => {
+                outlines_contain(&outlines, center) || edges(&outlines).any(|(a, b)| distance_to_segment(center, a, b) <= radius)
+            }
+            (Placed::Outlines(a), Placed::Outlines(b)) => {
+                // outlines that never cross are each wholly inside or outside the other shape
+                edges(&a).any(|(a0, a1)| edges(&b).any(|(b0, b1)| segments_intersect(a0, a1, b0, b1)))
+                    || a.iter().filter_map(|outline| outline.first()).any(|point| outlines_contain(&b, *point))
+                    || b.iter().filter_map(|outline| outline.first()).any(|point| outlines_contain(&a, *point))
+            }
+        }
+    }
+
+    fn place(&self, transform: &Transform2D) -> Placed {
+        if let Shape::Circle(radius) = self {
+            // circles stay circles unless the transform stretches or skews them
+            let linear = linear_part(transform);
+            let (x, y) = (linear.x_axis, linear.y_axis);
+            let scale = x.length().max(y.length());
+            if (x.length() - y.length()).abs() <= scale * 1e-4 && x.dot(y).abs() <= scale * scale * 1e-4 {
+                return Placed::Circle(transform.transform_point(Vec2::ZERO), radius.abs() * scale);
+            }
+        }
+        Placed::Outlines(self.outlines(transform))
+    }
+
+    // the outlines once transformed, circles are flattened finely enough that the error is not noticeable
+    fn outlines(&self, transform: &Transform2D) -> Vec<Vec<Vec2>> {
+        let matrix = transform.to_mat3();
+        let outlines = match self {
+            Shape::Rectangle(size) => {
+                let half = size.abs() * 0.5;
+                vec![vec![Vec2::new(-half.x, -half.y), Vec2::new(half.x, -half.y), half, Vec2::new(-half.x, half.y)]]
+            }
+            Shape::Circle(radius) => match Shape::from_path(&Mesh::ellipse_path(Vec2::splat(radius.abs())), radius.abs() * 1e-3) {
+                Shape::Polygon(outlines) => outlines,
+                _ => Vec::new()
+            },
+            Shape::Polygon(outlines) => outlines.clone()
+        };
+        outlines.into_iter()
+            .map(|outline| outline.into_iter().map(|point| matrix.transform_point2(point)).collect())
+            .collect()
+    }
+
+}
+
+fn linear_part(transform: &Transform2D) -> Mat2 {
+    Mat2::from_mat3(transform.to_mat3())
+}
+
+// every edge of every outline, including the one closing it
+fn edges(outlines: &[Vec<Vec2>]) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+    outlines.iter()
+        .filter(|outline| outline.len() > 1)
+        .flat_map(|outline| outline.iter().copied().zip(outline.iter().copied().cycle().skip(1)))
+}
+
+// even-odd, counting the edges a ray to the right of the point crosses
+fn outlines_contain(outlines: &[Vec<Vec2>], point: Vec2) -> bool {
+    if edges(outlines).any(|(a, b)| distance_to_segment(point, a, b) <= f32::EPSILON) {
+        return true;
+    }
+    edges(outlines)
+        .filter(|(a, b)| (a.y > point.y) != (b.y > point.y))
+        .filter(|(a, b)| point.x < a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x))
+        .count() % 2 == 1
+}
+
+fn distance_to_segment(point: Vec2, a: Vec2, b: Vec2) -> f32 {
+    let edge = b - a;
+    let t = if edge.length_squared() > 0.0 { ((point - a).dot(edge) / edge.length_squared()).clamp(0.0, 1.0) } else { 0.0 };
+    point.distance(a + edge * t)
+}
+
+fn segments_intersect(a0: Vec2, a1: Vec2, b0: Vec2, b1: Vec2) -> bool {
+    let side = |p: Vec2, q: Vec2, r: Vec2| (q - p).perp_dot(r - p);
+    let (d0, d1) = (side(b0, b1, a0), side(b0, b1, a1));
+    let (d2, d3) = (side(a0, a1, b0), side(a0, a1, b1));
+    if ((d0 > 0.0 && d1 < 0.0) || (d0 < 0.0 && d1 > 0.0)) && ((d2 > 0.0 && d3 < 0.0) || (d2 < 0.0 && d3 > 0.0)) {
+        return true;
+    }
+    // an end resting on the other segment
+    distance_to_segment(a0, b0, b1) <= f32::EPSILON
+        || distance_to_segment(a1, b0, b1) <= f32::EPSILON
+        || distance_to_segment(b0, a0, a1) <= f32::EPSILON
+        || distance_to_segment(b1, a0, a1) <= f32::EPSILON
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn square(half: f32) -> Vec<Vec2> {
+        vec![Vec2::new(-half, -half), Vec2::new(half, -half), Vec2::new(half, half), Vec2::new(-half, half)]
+    }
+
+    // a 4x4 square with a 2x2 hole in the middle, the hole wound the same way as the outside
+    fn frame() -> Shape {
+        Shape::Polygon(vec![square(2.0), square(1.0)])
+    }
+
+    fn assert_rect_eq(a: Rect, b: Rect) {
+        assert!(a.min.abs_diff_eq(b.min, 1e-3) && a.max.abs_diff_eq(b.max, 1e-3), "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn outlines_contain_points_even_odd() {
+        let outlines = [square(2.0), square(1.0)];
+        assert!(outlines_contain(&outlines, Vec2::new(1.5, 0.0)));
+        assert!(!outlines_contain(&outlines, Vec2::ZERO));
+        assert!(!outlines_contain(&outlines, Vec2::new(3.0, 0.0)));
+        // edges count as inside, the hole's too
+        assert!(outlines_contain(&outlines, Vec2::new(1.0, 0.5)));
+        assert!(outlines_contain(&outlines, Vec2::new(2.0, 2.0)));
+        // a ray through a vertex is only counted once
+        assert!(outlines_contain(&[vec![Vec2::new(0.0, -1.0), Vec2::new(1.0, 0.0), Vec2::new(0.0, 1.0), Vec2::new(-1.0, 0.0)]], Vec2::new(-0.5, 0.0)));
+    }
+
+    #[test]
+    fn segments_intersect_when_crossing_or_touching() {
+        assert!(segments_intersect(Vec2::new(-1.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(0.0, -1.0), Vec2::new(0.0, 1.0)));
+        assert!(!segments_intersect(Vec2::new(-1.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(2.0, -1.0), Vec2::new(2.0, 1.0)));
+        // one end resting on the other segment
+        assert!(segments_intersect(Vec2::new(-1.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(0.0, 0.0), Vec2::new(0.0, 1.0)));
+        // collinear, sharing an end, overlapping and apart
+        assert!(segments_intersect(Vec2::ZERO, Vec2::X, Vec2::X, Vec2::new(2.0, 0.0)));
+        assert!(segments_intersect(Vec2::ZERO, Vec2::new(2.0, 0.0), Vec2::X, Vec2::new(3.0, 0.0)));
+        assert!(!segments_intersect(Vec2::ZERO, Vec2::X, Vec2::new(2.0, 0.0), Vec2::new(3.0, 0.0)));
+        // parallel but apart
+        assert!(!segments_intersect(Vec2::ZERO, Vec2::X, Vec2::Y, Vec2::new(1.0, 1.0)));
+    }
+
+    #[test]
+    fn circles_stay_circles_under_uniform_transforms() {
+        let circle = Shape::Circle(2.0);
+        let uniform = Transform2D::at(3.0, 4.0).with_scale(Vec2::splat(1.5)).with_rotation(0.7);
+        match circle.place(&uniform) {
+            Placed::Circle(center, radius) => {
+                assert!(center.abs_diff_eq(Vec2::new(3.0, 4.0), 1e-5));
+                assert!((radius - 3.0).abs() < 1e-5);
+            }
+            Placed::Outlines(_) => panic!("a uniformly scaled circle should stay a circle")
+        }
+        // a flip keeps it round too
+        assert!(matches!(circle.place(&Transform2D::default().with_scale(Vec2::new(-2.0, 2.0))), Placed::Circle(_, _)));
+        assert!(matches!(circle.place(&Transform2D::default().with_scale(Vec2::new(2.0, 1.0))), Placed::Outlines(_)));
+        assert!(matches!(circle.place(&Transform2D::default().with_skew(Vec2::new(0.3, 0.0))), Placed::Outlines(_)));
+    }
+
+    #[test]
+    fn contains_works_through_transforms_and_holes() {
+        let transform = Transform2D::at(10.0, 0.0).with_rotation(FRAC_PI_2).with_scale(Vec2::new(2.0, 1.0));
+        // the rectangle's local x runs along world y once rotated, and is stretched to 8
+        let rectangle = Shape::Rectangle(Vec2::new(4.0, 2.0));
+        assert!(rectangle.contains(&transform, Vec2::new(10.0, 3.9)));
+        assert!(!rectangle.contains(&transform, Vec2::new(11.1, 0.0)));
+        assert!(Shape::Circle(1.0).contains(&transform, Vec2::new(10.0, 1.9)));
+        assert!(!Shape::Circle(1.0).contains(&transform, Vec2::new(11.1, 0.0)));
+
+        assert!(frame().contains(&transform, Vec2::new(10.0, 3.0)));
+        assert!(!frame().contains(&transform, Vec2::new(10.0, 0.0)));
+        assert!(!frame().contains(&Transform2D::default().with_scale(Vec2::new(0.0, 1.0)), Vec2::ZERO));
+    }
+
+    #[test]
+    fn bounds_cover_transformed_shapes() {
+        let transform = Transform2D::at(5.0, 0.0).with_rotation(FRAC_PI_2).with_scale(Vec2::new(2.0, 1.0));
+        assert_rect_eq(Shape::Rectangle(Vec2::new(2.0, 2.0)).bounds(&transform), Rect::new(Vec2::new(4.0, -2.0), Vec2::new(6.0, 2.0)));
+        assert_rect_eq(Shape::Circle(1.0).bounds(&transform), Rect::new(Vec2::new(4.0, -2.0), Vec2::new(6.0, 2.0)));
+        assert_rect_eq(frame().bounds(&Transform2D::at(1.0, 1.0)), Rect::new(Vec2::new(-1.0, -1.0), Vec2::new(3.0, 3.0)));
+    }
+
+    #[test]
+    fn oriented_bounds_follow_the_x_axis_under_skew() {
+        // skewing along x shears the square into a parallelogram whose x axis is unchanged
+        let skewed = Transform2D::default().with_skew(Vec2::new(0.5, 0.0));
+        let bounds = Shape::Rectangle(Vec2::splat(2.0)).oriented_bounds(&skewed);
+        assert!(bounds.rotation.abs() < 1e-5);
+        let shear = 0.5f32.tan();
+        assert!(bounds.half_size.abs_diff_eq(Vec2::new(1.0 + shear, 1.0), 1e-4), "{bounds:?}");
+        assert!(bounds.center.abs_diff_eq(Vec2::ZERO, 1e-4));
+
+        // a skewed circle is an ellipse, its box along x is as tall as the circle and wider
+        let ellipse = Shape::Circle(1.0).oriented_bounds(&skewed);
+        assert!(ellipse.half_size.abs_diff_eq(Vec2::new((1.0 + shear * shear).sqrt(), 1.0), 1e-4), "{ellipse:?}");
+
+        // turned with the shape, the box stays as tight as the unturned one
+        let turned = Transform2D::at(2.0, 3.0).with_rotation(0.6).with_skew(Vec2::new(0.5, 0.0));
+        let bounds = Shape::Rectangle(Vec2::splat(2.0)).oriented_bounds(&turned);
+        assert!((bounds.rotation - 0.6).abs() < 1e-5);
+        assert!(bounds.half_size.abs_diff_eq(Vec2::new(1.0 + shear, 1.0), 1e-4), "{bounds:?}");
+        assert!(bounds.center.abs_diff_eq(Vec2::new(2.0, 3.0), 1e-4));
+        assert!(bounds.corners().iter().all(|corner| bounds.contains(*corner * 0.999 + bounds.center * 0.001)));
+    }
+
+    #[test]
+    fn every_pair_of_shapes_overlaps_when_touching_and_not_when_apart() {
+        let shapes = [Shape::Rectangle(Vec2::splat(2.0)), Shape::Circle(1.0), Shape::polygon(square(1.0))];
+        for a in &shapes {
+            for b in &shapes {
+                let origin = Transform2D::default();
+                assert!(a.overlaps(&origin, b, &Transform2D::at(1.5, 0.0)), "{a:?} and {b:?} overlapping");
+                assert!(a.overlaps(&origin, b, &Transform2D::at(2.0, 0.0)), "{a:?} and {b:?} touching");
+                assert!(!a.overlaps(&origin, b, &Transform2D::at(2.1, 0.0)), "{a:?} and {b:?} apart");
+                // stretched and turned
+                let stretched = Transform2D::at(0.0, 4.0).with_scale(Vec2::new(1.0, 3.0));
+                assert!(a.overlaps(&origin, b, &stretched), "{a:?} and stretched {b:?}");
+                assert!(!a.overlaps(&origin, b, &stretched.with_rotation(FRAC_PI_2)), "{a:?} and turned {b:?}");
+                // one inside the other without their edges crossing
+                assert!(a.overlaps(&origin, b, &Transform2D::default().with_scale(Vec2::splat(0.2))), "{b:?} inside {a:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn corners_of_rectangles_miss_circles_their_bounds_touch() {
+        let diagonal = Transform2D::at(1.9, 1.9);
+        assert!(!Shape::Rectangle(Vec2::splat(2.0)).overlaps(&Transform2D::default(), &Shape::Circle(1.0), &diagonal));
+        assert!(Shape::Rectangle(Vec2::splat(2.0)).overlaps(&Transform2D::default(), &Shape::Circle(1.0), &Transform2D::at(1.6, 1.6)));
+    }
+
+    #[test]
+    fn shapes_inside_a_hole_do_not_overlap() {
+        let origin = Transform2D::default();
+        for inner in [Shape::Rectangle(Vec2::splat(1.0)), Shape::Circle(0.5), Shape::polygon(square(0.5))] {
+            assert!(!frame().overlaps(&origin, &inner, &origin), "{inner:?} in the hole");
+            assert!(frame().overlaps(&origin, &inner, &Transform2D::at(1.5, 0.0)), "{inner:?} on the frame");
+            // a circle is only placed as one when it stays round
+            assert!(!frame().overlaps(&origin, &inner, &origin.with_scale(Vec2::new(1.5, 1.0))), "{inner:?} stretched in the hole");
+        }
+    }
+}
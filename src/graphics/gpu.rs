@@ -1,12 +1,17 @@
+use std::cell::RefCell;
 use std::cmp::max;
 use std::collections::HashMap;
-use crate::graphics::draw::{Color, DrawCommand, Image, Renderer};
+use std::rc::Rc;
+use crate::graphics::draw::{Color, CoordinateSpace, DrawCommand, Image, Paint, Rect, Renderer, SpreadMode};
+use crate::graphics::mesh::{Indices, Mesh, MeshHandle};
+#[cfg(feature = "egui")]
+use crate::egui_backend::EguiFrame;
 use bytemuck::{Pod, Zeroable};
 use image::{DynamicImage,  RgbaImage};
 use log::warn;
 use pollster::FutureExt;
 use std::sync::Arc;
-use glam::Mat4;
+use glam::{Mat4, Vec2};
 use wgpu::util::DeviceExt;
 use wgpu::{Adapter, AdapterInfo, BindGroup, BindGroupLayout, Buffer, Device, Instance, PresentMode, Queue, Surface, SurfaceCapabilities};
 use winit::dpi::PhysicalSize;
@@ -16,10 +21,22 @@ use winit::window::Window;
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
 pub struct Vertex {
     pub position: [f32; 3],
-    pub uv: [f32; 2]
+    pub uv: [f32; 2],
+    // multiplied with the paint, white leaves it untouched
+    pub color: [f32; 4]
 }
 
 impl Vertex {
+
+    pub fn new(position: [f32; 3], uv: [f32; 2]) -> Self {
+        Self { position, uv, color: Color::WHITE.into() }
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color.into();
+        self
+    }
+
     pub fn desc() -> wgpu::VertexBufferLayout<'static> {
         wgpu::VertexBufferLayout {
             array_stride: size_of::<Vertex>() as wgpu::BufferAddress,
@@ -34,6 +51,53 @@ impl Vertex {
                     offset: size_of::<[f32; 3]>() as wgpu::BufferAddress,
                     shader_location: 1,
                     format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: size_of::<[f32; 5]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32x4,
+                }
+            ]
+        }
+    }
+}
+
+// one particle drawn as a rotated and scaled copy of a unit quad
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct ParticleInstance {
+    pub position: [f32; 2],
+    // a negative height mirrors the quad, which flips the texture for y up spaces
+    pub size: [f32; 2],
+    pub rotation: f32,
+    pub color: [f32; 4]
+}
+
+impl ParticleInstance {
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: size_of::<ParticleInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                    shader_location: 4,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                    shader_location: 5,
+                    format: wgpu::VertexFormat::Float32,
+                },
+                wgpu::VertexAttribute {
+                    offset: size_of::<[f32; 5]>() as wgpu::BufferAddress,
+                    shader_location: 6,
+                    format: wgpu::VertexFormat::Float32x4,
                 }
             ]
         }
@@ -44,7 +108,72 @@ impl Vertex {
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
 pub struct DrawUniform2D {
     pub transform_matrix: [[f32; 4]; 4],
-    pub color: [f32; 4]
+    pub color: [f32; 4],
+    // 0 solid, 1 linear, 2 radial, 3 conic, 4 image pattern
+    pub paint_kind: u32,
+    pub spread: u32,
+    pub stop_count: u32,
+    // clip space depth, written by the vertex shader
+    pub depth: f32,
+    // start/end for linear, center for radial and conic, origin/size for patterns
+    pub points: [f32; 4],
+    // x is the radius for radial and the start angle for conic
+    pub params: [f32; 4],
+    pub stop_colors: [[f32; 4]; Paint::MAX_STOPS],
+    pub stop_offsets: [[f32; 4]; Paint::MAX_STOPS / 4]
+}
+
+impl DrawUniform2D {
+
+    pub fn new(transform_matrix: Mat4, paint: &Paint) -> Self {
+        let mut uniforms = DrawUniform2D {
+            transform_matrix: transform_matrix.to_cols_array_2d(),
+            color: Color::WHITE.into(),
+            ..Zeroable::zeroed()
+        };
+
+        let (kind, spread, points, params) = match paint {
+            Paint::Solid(color) => {
+                uniforms.color = (*color).into();
+                (0, SpreadMode::Pad, [0.0; 4], [0.0; 4])
+            }
+            Paint::LinearGradient { start, end, spread, .. } => (1, *spread, [start.x, start.y, end.x, end.y], [0.0; 4]),
+            Paint::RadialGradient { center, radius, spread, .. } => (2, *spread, [center.x, center.y, 0.0, 0.0], [*radius, 0.0, 0.0, 0.0]),
+            Paint::ConicGradient { center, angle, spread, .. } => (3, *spread, [center.x, center.y, 0.0, 0.0], [*angle, 0.0, 0.0, 0.0]),
+            Paint::ImagePattern { origin, size, spread, .. } => (4, *spread, [origin.x, origin.y, size.x, size.y], [0.0; 4])
+        };
+
+        uniforms.paint_kind = kind;
+        uniforms.spread = match spread {
+            SpreadMode::Pad => 0,
+            SpreadMode::Repeat => 1,
+            SpreadMode::Reflect => 2
+        };
+        uniforms.points = points;
+        uniforms.params = params;
+
+        // the shader walks the stops in order, so they have to be sorted
+        let mut stops = paint.stops().to_vec();
+        stops.sort_by(|a, b| a.offset.total_cmp(&b.offset));
+        if stops.len() > Paint::MAX_STOPS {
+            warn!("Gradient has {} color stops, only the first {} are used", stops.len(), Paint::MAX_STOPS);
+            stops.truncate(Paint::MAX_STOPS);
+        }
+        if kind != 0 && kind != 4 && stops.is_empty() {
+            // a gradient without stops draws nothing, as a fully transparent solid color
+            uniforms.paint_kind = 0;
+            uniforms.color = Color::new(0.0, 0.0, 0.0, 0.0).into();
+        }
+
+        uniforms.stop_count = stops.len() as u32;
+        for (i, stop) in stops.iter().enumerate() {
+            uniforms.stop_colors[i] = stop.color.into();
+            uniforms.stop_offsets[i / 4][i % 4] = stop.offset;
+        }
+
+        uniforms
+    }
+
 }
 
 pub struct Texture {
@@ -102,32 +231,292 @@ impl Texture {
 
 }
 
-pub struct Display {
-    surface: Surface<'static>,
+struct GpuMesh {
+    vertex_buffer: Buffer,
+    index_buffer: Buffer,
+    index_count: u32,
+    index_format: wgpu::IndexFormat
+}
+
+// device, queue and caches shared by every window's display
+#[derive(Clone)]
+pub struct Gpu {
+    instance: Instance,
     adapter: Adapter,
     device: Device,
     queue: Queue,
+    texture_cache: Rc<RefCell<HashMap<String, Texture>>>,
+    mesh_cache: Rc<RefCell<HashMap<MeshHandle, GpuMesh>>>
+}
+
+impl Gpu {
+
+    fn new(instance: Instance, surface: &Surface) -> Self {
+        let adapter = Display::create_adapter(&instance, surface);
+        let (device, queue) = Display::create_device(&adapter);
+        Self {
+            instance,
+            adapter,
+            device,
+            queue,
+            texture_cache: Rc::new(RefCell::new(HashMap::new())),
+            mesh_cache: Rc::new(RefCell::new(HashMap::new()))
+        }
+    }
+
+    pub fn device(&self) -> &Device {
+        &self.device
+    }
+
+    pub fn queue(&self) -> &Queue {
+        &self.queue
+    }
+
+    pub fn get_adaptor_info(&self) -> AdapterInfo {
+        self.adapter.get_info()
+    }
+
+    pub fn load_texture(&self, image: &Image) {
+        let mut texture_cache = self.texture_cache.borrow_mut();
+        if !texture_cache.contains_key(&image.path) {
+            let texture = Texture::from_image(&self.device, image.image.clone());
+            Display::write_texture_to_queue(&self.queue, &texture);
+            texture_cache.insert(image.path.clone(), texture);
+        }
+    }
+
+    pub fn unload_texture(&self, path: &str) {
+        self.texture_cache.borrow_mut().remove(path);
+    }
+
+    pub fn upload_mesh(&self, handle: MeshHandle, mesh: &Mesh) {
+        let vertex_bytes = Gpu::padded_bytes(bytemuck::cast_slice(&mesh.vertices));
+        let index_bytes = Gpu::padded_bytes(mesh.indices.as_bytes());
+        let mut mesh_cache = self.mesh_cache.borrow_mut();
+
+        match mesh_cache.get_mut(&handle) {
+            // dynamic meshes that still fit are written in place
+            Some(gpu_mesh) if gpu_mesh.vertex_buffer.size() >= vertex_bytes.len() as u64 && gpu_mesh.index_buffer.size() >= index_bytes.len() as u64 => {
+                self.queue.write_buffer(&gpu_mesh.vertex_buffer, 0, &vertex_bytes);
+                self.queue.write_buffer(&gpu_mesh.index_buffer, 0, &index_bytes);
+                gpu_mesh.index_count = mesh.indices.len() as u32;
+                gpu_mesh.index_format = mesh.indices.format();
+            }
+            _ => {
+                let vertex_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("Retained Vertex Buffer"),
+                    contents: &vertex_bytes,
+                    usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                });
+                let index_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("Retained Index Buffer"),
+                    contents: &index_bytes,
+                    usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
+                });
+                mesh_cache.insert(handle, GpuMesh {
+                    vertex_buffer,
+                    index_buffer,
+                    index_count: mesh.indices.len() as u32,
+                    index_format: mesh.indices.format()
+                });
+            }
+        }
+    }
+
+    pub fn release_mesh(&self, handle: MeshHandle) {
+        self.mesh_cache.borrow_mut().remove(&handle);
+    }
+
+    pub fn mesh_count(&self) -> usize {
+        self.mesh_cache.borrow().len()
+    }
+
+    // buffer writes must be a non zero multiple of four bytes
+    fn padded_bytes(bytes: &[u8]) -> Vec<u8> {
+        let len = bytes.len().max(1).next_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT as usize);
+        let mut padded = bytes.to_vec();
+        padded.resize(len, 0);
+        padded
+    }
+
+}
+
+const DEPTH_STENCIL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth24PlusStencil8;
+
+// how a pipeline uses the depth buffer
+#[derive(Copy, Clone, PartialEq)]
+enum DepthMode {
+    Off,
+    Test,
+    Write
+}
+
+// the stencil holds how many masks cover a pixel, draws pass where it equals the current mask depth
+struct Pipelines {
+    draw: wgpu::RenderPipeline,
+    // opaque and translucent draws on layers with depth testing
+    depth_write: wgpu::RenderPipeline,
+    depth_test: wgpu::RenderPipeline,
+    mask_increment: wgpu::RenderPipeline,
+    mask_decrement: wgpu::RenderPipeline,
+    // instanced quads, particles are always translucent so they never write depth
+    particles: wgpu::RenderPipeline,
+    particles_depth_test: wgpu::RenderPipeline
+}
+
+impl Pipelines {
+
+    fn new(device: &Device, layout: &wgpu::PipelineLayout, config: &wgpu::SurfaceConfiguration, sample_count: u32) -> Self {
+        use wgpu::StencilOperation;
+        Self {
+            draw: Self::create(device, layout, config, sample_count, StencilOperation::Keep, DepthMode::Off, false),
+            depth_write: Self::create(device, layout, config, sample_count, StencilOperation::Keep, DepthMode::Write, false),
+            depth_test: Self::create(device, layout, config, sample_count, StencilOperation::Keep, DepthMode::Test, false),
+            mask_increment: Self::create(device, layout, config, sample_count, StencilOperation::IncrementClamp, DepthMode::Off, false),
+            mask_decrement: Self::create(device, layout, config, sample_count, StencilOperation::DecrementClamp, DepthMode::Off, false),
+            particles: Self::create(device, layout, config, sample_count, StencilOperation::Keep, DepthMode::Off, true),
+            particles_depth_test: Self::create(device, layout, config, sample_count, StencilOperation::Keep, DepthMode::Test, true)
+        }
+    }
+
+    fn draw_pipeline(&self, depth_test: bool, opaque: bool) -> &wgpu::RenderPipeline {
+        match (depth_test, opaque) {
+            (false, _) => &self.draw,
+            (true, true) => &self.depth_write,
+            (true, false) => &self.depth_test
+        }
+    }
+
+    fn particle_pipeline(&self, depth_test: bool) -> &wgpu::RenderPipeline {
+        if depth_test { &self.particles_depth_test } else { &self.particles }
+    }
+
+    fn create(device: &Device, layout: &wgpu::PipelineLayout, config: &wgpu::SurfaceConfiguration, sample_count: u32, stencil_pass: wgpu::StencilOperation, depth: DepthMode, instanced: bool) -> wgpu::RenderPipeline {
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("shader2d.wgsl"));
+        let vertex_buffers = [Vertex::desc(), ParticleInstance::desc()];
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Render Pipeline"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some(if instanced { "vs_particle" } else { "vs_main" }),
+                compilation_options: Default::default(),
+                buffers: if instanced { &vertex_buffers } else { &vertex_buffers[..1] },
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: config.format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    // mask shapes only touch the stencil buffer
+                    write_mask: if stencil_pass == wgpu::StencilOperation::Keep { wgpu::ColorWrites::ALL } else { wgpu::ColorWrites::empty() },
+                })],
+            }),
+            multiview: None,
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Cw,
+                // y down projections flip the winding, and 2d shapes are never seen from behind
+                cull_mode: None,
+                unclipped_depth: false,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                conservative: false,
+            },
+            // every draw is tested against the stencil value of the innermost mask
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DEPTH_STENCIL_FORMAT,
+                depth_write_enabled: depth == DepthMode::Write,
+                depth_compare: if depth == DepthMode::Off { wgpu::CompareFunction::Always } else { wgpu::CompareFunction::LessEqual },
+                stencil: wgpu::StencilState {
+                    front: wgpu::StencilFaceState {
+                        compare: wgpu::CompareFunction::Equal,
+                        fail_op: wgpu::StencilOperation::Keep,
+                        depth_fail_op: wgpu::StencilOperation::Keep,
+                        pass_op: stencil_pass,
+                    },
+                    back: wgpu::StencilFaceState {
+                        compare: wgpu::CompareFunction::Equal,
+                        fail_op: wgpu::StencilOperation::Keep,
+                        depth_fail_op: wgpu::StencilOperation::Keep,
+                        pass_op: stencil_pass,
+                    },
+                    read_mask: 0xff,
+                    write_mask: 0xff,
+                },
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            cache: None,
+        })
+    }
+
+}
+
+pub struct Display {
+    surface: Surface<'static>,
+    gpu: Gpu,
     config: wgpu::SurfaceConfiguration,
-    render_pipeline: wgpu::RenderPipeline,
+    present_modes: Vec<PresentMode>,
+    pipeline_layout: wgpu::PipelineLayout,
+    pipelines: Pipelines,
+    sample_count: u32,
+    // multisampled target that resolves into the surface texture, None without msaa
+    msaa_view: Option<wgpu::TextureView>,
+    depth_stencil_view: wgpu::TextureView,
+    // unit quad every particle instance is drawn from
+    particle_quad: GpuMesh,
     size: PhysicalSize<u32>,
-    texture_cache: HashMap<String, Texture>,
-    background_color: wgpu::Color
+    scale_factor: f64,
+    background_color: wgpu::Color,
+    #[cfg(feature = "egui")]
+    egui_renderer: egui_wgpu::Renderer,
+    #[cfg(feature = "egui")]
+    egui_frame: Option<EguiFrame>
 }
 
 impl Display {
-    pub fn new(window: Arc<Window>) -> Self {
+    pub fn new(window: Arc<Window>, present_mode: PresentMode, sample_count: u32) -> Self {
+        let instance = Self::create_gpu_instance();
+        let surface = instance.create_surface(window.clone()).unwrap();
+        let gpu = Gpu::new(instance, &surface);
+        Self::with_surface(window, surface, gpu, present_mode, sample_count)
+    }
+
+    // creates a display for another window that shares this gpu's device and caches
+    pub fn with_gpu(window: Arc<Window>, gpu: &Gpu, present_mode: PresentMode, sample_count: u32) -> Self {
+        let surface = gpu.instance.create_surface(window.clone()).unwrap();
+        Self::with_surface(window, surface, gpu.clone(), present_mode, sample_count)
+    }
+
+    fn with_surface(window: Arc<Window>, surface: Surface<'static>, gpu: Gpu, present_mode: PresentMode, sample_count: u32) -> Self {
         
         let size = window.inner_size();
-        let instance = Self::create_gpu_instance();
-        let surface = instance.create_surface(window).unwrap();
-        let adapter = Self::create_adapter(instance, &surface);
-        let (device, queue) = Self::create_device(&adapter);
-        let surface_caps = surface.get_capabilities(&adapter);
-        let config = Self::create_surface_config(size, surface_caps);
-        let render_pipeline_layout = Self::create_pipeline_layout(&device);
-        let render_pipeline = Self::create_render_pipeline(&device, &render_pipeline_layout, &config);
+        let scale_factor = window.scale_factor();
+        let device = &gpu.device;
+        let surface_caps = surface.get_capabilities(&gpu.adapter);
+        let present_modes = surface_caps.present_modes.clone();
+        let present_mode = Self::select_present_mode(present_mode, &present_modes);
+        let config = Self::create_surface_config(size, surface_caps, present_mode);
+        let sample_count = Self::select_sample_count(sample_count, &Self::supported_sample_counts(&gpu, config.format));
+        let pipeline_layout = Self::create_pipeline_layout(device);
+        let pipelines = Pipelines::new(device, &pipeline_layout, &config, sample_count);
+        let msaa_view = Self::create_msaa_view(device, &config, sample_count);
+        let depth_stencil_view = Self::create_depth_stencil_view(device, &config, sample_count);
+        let particle_quad = Self::create_particle_quad(device);
+        // egui draws in a pass of its own straight onto the surface, without msaa or depth
+        #[cfg(feature = "egui")]
+        let egui_renderer = egui_wgpu::Renderer::new(device, config.format, None, 1, false);
 
-        surface.configure(&device, &config);
+        surface.configure(device, &config);
 
         let background_rgba = Color::WHITE;
 
@@ -140,22 +529,165 @@ impl Display {
 
         Self {
             surface,
-            adapter,
-            device,
-            queue,
+            gpu,
             config,
+            present_modes,
             size,
-            render_pipeline,
-            texture_cache: HashMap::new(),
-            background_color
+            scale_factor,
+            pipeline_layout,
+            pipelines,
+            sample_count,
+            msaa_view,
+            depth_stencil_view,
+            particle_quad,
+            background_color,
+            #[cfg(feature = "egui")]
+            egui_renderer,
+            #[cfg(feature = "egui")]
+            egui_frame: None
         }
     }
 
+    pub fn gpu(&self) -> &Gpu {
+        &self.gpu
+    }
+
     pub fn get_adaptor_info(&self) -> AdapterInfo {
-        self.adapter.get_info()
+        self.gpu.get_adaptor_info()
+    }
+
+    pub fn max_texture_side(&self) -> usize {
+        self.gpu.device.limits().max_texture_dimension_2d as usize
+    }
+
+    // drawn over the renderer's commands by the next render
+    #[cfg(feature = "egui")]
+    pub fn set_egui_frame(&mut self, frame: EguiFrame) {
+        self.egui_frame = Some(frame);
+    }
+
+    pub fn present_mode(&self) -> PresentMode {
+        self.config.present_mode
+    }
+
+    pub fn set_present_mode(&mut self, present_mode: PresentMode) {
+        self.config.present_mode = Self::select_present_mode(present_mode, &self.present_modes);
+        self.surface.configure(&self.gpu.device, &self.config);
+    }
+
+    pub fn select_present_mode(requested: PresentMode, supported: &[PresentMode]) -> PresentMode {
+        // the auto modes do their own fallback inside wgpu
+        if matches!(requested, PresentMode::AutoVsync | PresentMode::AutoNoVsync) || supported.contains(&requested) {
+            return requested;
+        }
+
+        let fallback = match requested {
+            PresentMode::Immediate => PresentMode::AutoNoVsync,
+            PresentMode::Mailbox | PresentMode::FifoRelaxed => PresentMode::Fifo,
+            _ => PresentMode::AutoVsync
+        };
+
+        warn!("Present mode {:?} is not supported, falling back to {:?}", requested, fallback);
+        fallback
     }
 
-    fn create_surface_config(size: PhysicalSize<u32>, capabilities: SurfaceCapabilities) -> wgpu::SurfaceConfiguration {
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    pub fn set_sample_count(&mut self, sample_count: u32) {
+        let sample_count = Self::select_sample_count(sample_count, &Self::supported_sample_counts(&self.gpu, self.config.format));
+        if sample_count == self.sample_count {
+            return;
+        }
+        self.sample_count = sample_count;
+        self.pipelines = Pipelines::new(&self.gpu.device, &self.pipeline_layout, &self.config, sample_count);
+        self.msaa_view = Self::create_msaa_view(&self.gpu.device, &self.config, sample_count);
+        self.depth_stencil_view = Self::create_depth_stencil_view(&self.gpu.device, &self.config, sample_count);
+    }
+
+    // counts other than 1 and 4 are only usable when the device enabled adapter specific format features
+    fn supported_sample_counts(gpu: &Gpu, format: wgpu::TextureFormat) -> Vec<u32> {
+        let flags = gpu.adapter.get_texture_format_features(format).flags;
+        let adapter_specific = gpu.device.features().contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES);
+        flags.supported_sample_counts()
+            .into_iter()
+            .filter(|count| *count == 1 || *count == 4 || adapter_specific)
+            .collect()
+    }
+
+    // picks the largest supported count that does not exceed the request
+    pub fn select_sample_count(requested: u32, supported: &[u32]) -> u32 {
+        let selected = [8, 4, 2, 1]
+            .into_iter()
+            .find(|count| *count <= requested.max(1) && (*count == 1 || supported.contains(count)))
+            .unwrap_or(1);
+
+        if selected != requested {
+            warn!("MSAA sample count {} is not supported, falling back to {}", requested, selected);
+        }
+        selected
+    }
+
+    fn create_msaa_view(device: &Device, config: &wgpu::SurfaceConfiguration, sample_count: u32) -> Option<wgpu::TextureView> {
+        if sample_count <= 1 {
+            return None;
+        }
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("MSAA Texture"),
+            size: wgpu::Extent3d {
+                width: config.width.max(1),
+                height: config.height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        Some(texture.create_view(&wgpu::TextureViewDescriptor::default()))
+    }
+
+    fn create_depth_stencil_view(device: &Device, config: &wgpu::SurfaceConfiguration, sample_count: u32) -> wgpu::TextureView {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Depth Stencil Texture"),
+            size: wgpu::Extent3d {
+                width: config.width.max(1),
+                height: config.height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: DEPTH_STENCIL_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        texture.create_view(&wgpu::TextureViewDescriptor::default())
+    }
+
+    fn create_particle_quad(device: &Device) -> GpuMesh {
+        let mesh = Mesh::new_rectangle(1.0, 1.0);
+        GpuMesh {
+            vertex_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Particle Vertex Buffer"),
+                contents: bytemuck::cast_slice(&mesh.vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            }),
+            index_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Particle Index Buffer"),
+                contents: &Gpu::padded_bytes(mesh.indices.as_bytes()),
+                usage: wgpu::BufferUsages::INDEX,
+            }),
+            index_count: mesh.indices.len() as u32,
+            index_format: mesh.indices.format()
+        }
+    }
+
+    fn create_surface_config(size: PhysicalSize<u32>, capabilities: SurfaceCapabilities, present_mode: PresentMode) -> wgpu::SurfaceConfiguration {
         let surface_format = capabilities
             .formats
             .iter()
@@ -168,7 +700,7 @@ impl Display {
             format: surface_format,
             width: size.width,
             height: size.height,
-            present_mode: PresentMode::AutoNoVsync,
+            present_mode,
             alpha_mode: capabilities.alpha_modes[0],
             view_formats: vec![],
             desired_maximum_frame_latency: 2,
@@ -179,7 +711,8 @@ impl Display {
         adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
-                    required_features: wgpu::Features::empty(),
+                    // lets msaa use every sample count the adapter supports, not just 4
+                    required_features: adapter.features() & wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES,
                     required_limits: wgpu::Limits::default(),
                     label: Some("Device"),
                     memory_hints: Default::default(),
@@ -190,11 +723,11 @@ impl Display {
             .unwrap()
     }
 
-    fn create_adapter(instance: Instance, surface: &Surface) -> Adapter {
+    fn create_adapter(instance: &Instance, surface: &Surface) -> Adapter {
         instance
             .request_adapter(&wgpu::RequestAdapterOptions {
                 power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: Some(&surface),
+                compatible_surface: Some(surface),
                 force_fallback_adapter: false,
             })
             .block_on()
@@ -205,8 +738,8 @@ impl Display {
         device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("Render Pipeline Layout"),
             bind_group_layouts: &[
-                &Display::create_uniform_bind_layout(&device),
-                &Display::create_texture_bind_group_layout(&device),
+                &Display::create_uniform_bind_layout(device),
+                &Display::create_texture_bind_group_layout(device),
             ],
             push_constant_ranges: &[],
         })
@@ -231,7 +764,7 @@ impl Display {
     }
 
     fn create_uniform_bind_group(device: &Device, uniforms: DrawUniform2D) -> BindGroup {
-        let uniform_bind_group_layout = Display::create_uniform_bind_layout(&device);
+        let uniform_bind_group_layout = Display::create_uniform_bind_layout(device);
         let transform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Uniforms Buffer"),
             contents: bytemuck::cast_slice(&[uniforms]),
@@ -274,7 +807,7 @@ impl Display {
     }
 
     fn create_texture_bind_group(device: &Device, texture: &Texture) -> BindGroup {
-        let texture_bind_group_layout = Display::create_texture_bind_group_layout(&device);
+        let texture_bind_group_layout = Display::create_texture_bind_group_layout(device);
         device.create_bind_group(&wgpu::BindGroupDescriptor {
             layout: &texture_bind_group_layout,
             entries: &[
@@ -291,52 +824,6 @@ impl Display {
         })
     }
 
-    fn create_render_pipeline(device: &Device, layout: &wgpu::PipelineLayout, config: &wgpu::SurfaceConfiguration) -> wgpu::RenderPipeline {
-
-        let shader = device.create_shader_module(wgpu::include_wgsl!("shader2d.wgsl"));
-
-        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: Some("vs_main"),
-                compilation_options: Default::default(),
-                buffers: &[Vertex::desc()],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: Some("fs_main"),
-                compilation_options: Default::default(),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState {
-                        color: wgpu::BlendComponent::REPLACE,
-                        alpha: wgpu::BlendComponent::REPLACE,
-                    }),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            multiview: None,
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Cw,
-                cull_mode: Some(wgpu::Face::Back),
-                unclipped_depth: false,
-                polygon_mode: wgpu::PolygonMode::Fill,
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            cache: None,
-        })
-    }
-
     fn create_gpu_instance() -> Instance {
         Instance::new(&wgpu::InstanceDescriptor {
             backends: wgpu::Backends::PRIMARY,
@@ -348,7 +835,48 @@ impl Display {
         self.size = new_size;
         self.config.width = max(new_size.width, 1);
         self.config.height = max(new_size.height, 1);
-        self.surface.configure(&self.device, &self.config);
+        self.surface.configure(&self.gpu.device, &self.config);
+        self.msaa_view = Self::create_msaa_view(&self.gpu.device, &self.config, self.sample_count);
+        self.depth_stencil_view = Self::create_depth_stencil_view(&self.gpu.device, &self.config, self.sample_count);
+    }
+
+    pub fn size(&self) -> PhysicalSize<u32> {
+        self.size
+    }
+
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
+    }
+
+    fn projection_matrix(&self, space: CoordinateSpace) -> Mat4 {
+        let (width, height) = (self.config.width as f32, self.config.height as f32);
+        match space {
+            CoordinateSpace::Normalized => Mat4::IDENTITY,
+            CoordinateSpace::Logical => {
+                let scale = self.scale_factor as f32;
+                Display::create_orthographic_matrix(width / scale, height / scale)
+            }
+            CoordinateSpace::Physical => Display::create_orthographic_matrix(width, height)
+        }
+    }
+
+    // clamps a clip rect to the surface and snaps it to whole pixels
+    fn scissor_rect(&self, rect: Option<Rect>) -> (u32, u32, u32, u32) {
+        let (width, height) = (self.config.width as f32, self.config.height as f32);
+        let Some(rect) = rect else {
+            return (0, 0, self.config.width, self.config.height);
+        };
+        let min = rect.min.round().clamp(Vec2::ZERO, Vec2::new(width, height));
+        let max = rect.max.round().clamp(min, Vec2::new(width, height));
+        (min.x as u32, min.y as u32, (max.x - min.x) as u32, (max.y - min.y) as u32)
+    }
+
+    fn create_orthographic_matrix(width: f32, height: f32) -> Mat4 {
+        Mat4::orthographic_rh(0.0, width, height, 0.0, -1.0, 1.0)
     }
 
     fn write_texture_to_queue(queue: &Queue, texture: &Texture) {
@@ -374,31 +902,93 @@ impl Display {
         );
     }
     
-    pub fn load_texture(mut self, image: Image) {
-        if !self.texture_cache.contains_key(&image.path) {
-            let texture = Texture::from_image(&self.device, image.image);
-            Display::write_texture_to_queue(&self.queue, &texture);
-            self.texture_cache.insert(image.path, texture);
-        }
+    pub fn load_texture(&self, image: &Image) {
+        self.gpu.load_texture(image);
     }
     
-    fn create_vertex_buffer(&mut self, vertices: &[Vertex]) -> Buffer {
-        self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+    fn create_vertex_buffer(&self, vertices: &[Vertex]) -> Buffer {
+        self.gpu.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Vertex Buffer"),
             contents: bytemuck::cast_slice(vertices),
             usage: wgpu::BufferUsages::VERTEX,
         })
     }
 
-    fn create_index_buffer(&mut self, indices: &[u16]) -> Buffer {
-        self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+    fn create_index_buffer(&self, indices: &Indices) -> Buffer {
+        self.gpu.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Index Buffer"),
-            contents: bytemuck::cast_slice(indices),
+            contents: &Gpu::padded_bytes(indices.as_bytes()),
             usage: wgpu::BufferUsages::INDEX,
         })
     }
     
+    fn bind_draw_state(&self, render_pass: &mut wgpu::RenderPass, texture_cache: &mut HashMap<String, Texture>, transform_matrix: Mat4, paint: &Paint, image: Option<&Image>, depth: f32) {
+
+        let mut draw_uniforms = DrawUniform2D::new(transform_matrix, paint);
+        draw_uniforms.depth = depth;
+
+        let bind_group = Display::create_uniform_bind_group(&self.gpu.device, draw_uniforms);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+
+        // a pattern paint takes the texture slot from the mesh's own image
+        let image = paint.image().map(|image| image.as_ref()).or(image);
+
+        let texture = match image {
+            None => {
+                let pixel  = Image::single_pixel(Color::WHITE);
+                texture_cache.entry(pixel.path.clone()).or_insert_with(|| {
+                    let texture = Texture::from_image(&self.gpu.device, pixel.image.clone());
+                    Display::write_texture_to_queue(&self.gpu.queue, &texture);
+                    texture
+                })
+            },
+            Some(image) => {
+                if !texture_cache.contains_key(&image.path) {
+                    // load texture
+                    let texture = Texture::from_image(&self.gpu.device, image.image.clone());
+                    Display::write_texture_to_queue(&self.gpu.queue, &texture);
+                    texture_cache.insert(image.path.clone(), texture);
+                }
+                texture_cache.get(&image.path).unwrap()
+            }
+        };
+
+        let bg = Display::create_texture_bind_group(&self.gpu.device, texture);
+        render_pass.set_bind_group(1, &bg, &[]);
+
+    }
+
+    // covers the whole surface in clip space, used to raise or lower the stencil everywhere
+    fn draw_full_screen(&self, render_pass: &mut wgpu::RenderPass, texture_cache: &mut HashMap<String, Texture>) {
+        self.bind_draw_state(render_pass, texture_cache, Mat4::IDENTITY, &Paint::default(), None, 0.0);
+        self.draw_immediate(render_pass, &Mesh::new_rectangle(2.0, 2.0));
+    }
+
+    // only pixels inside the innermost mask sit at its depth, drop them back down
+    fn end_mask(&self, render_pass: &mut wgpu::RenderPass, texture_cache: &mut HashMap<String, Texture>, full_scissor: (u32, u32, u32, u32), mask_depth: u32) {
+        render_pass.set_scissor_rect(full_scissor.0, full_scissor.1, full_scissor.2, full_scissor.3);
+        render_pass.set_pipeline(&self.pipelines.mask_decrement);
+        render_pass.set_stencil_reference(mask_depth);
+        self.draw_full_screen(render_pass, texture_cache);
+    }
+
+    // uploads a mesh into temporary buffers and draws it with the bound state
+    fn draw_immediate(&self, render_pass: &mut wgpu::RenderPass, mesh: &Mesh) {
+        let vertex_buffer = self.create_vertex_buffer(&mesh.vertices);
+        let index_buffer = self.create_index_buffer(&mesh.indices);
+
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.set_index_buffer(index_buffer.slice(..), mesh.indices.format());
+        render_pass.draw_indexed(0..mesh.indices.len() as u32, 0, 0..1)
+    }
+
     pub fn render(&mut self, renderer: &mut Renderer) {
+
+        // sync retained meshes before anything is drawn with them
+        for handle in renderer.take_released_meshes() {
+            self.gpu.release_mesh(handle);
+        }
+        renderer.upload_dirty_meshes(|handle, mesh| self.gpu.upload_mesh(handle, mesh));
         
         let output = match self.surface.get_current_texture() {
             Ok(o) => o,
@@ -413,6 +1003,7 @@ impl Display {
             .create_view(&wgpu::TextureViewDescriptor::default());
     
         let mut encoder = self
+            .gpu
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                 label: Some("Render Encoder"),
@@ -422,81 +1013,250 @@ impl Display {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
+                    // with msaa we draw into the multisampled texture and resolve into the surface
+                    view: self.msaa_view.as_ref().unwrap_or(&view),
+                    resolve_target: self.msaa_view.as_ref().map(|_| &view),
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Clear(self.background_color),
-                        store: wgpu::StoreOp::Store,
+                        store: if self.msaa_view.is_some() { wgpu::StoreOp::Discard } else { wgpu::StoreOp::Store },
                     },
                 })],
-                depth_stencil_attachment: None,
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_stencil_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Discard,
+                    }),
+                    stencil_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(0),
+                        store: wgpu::StoreOp::Discard,
+                    }),
+                }),
                 occlusion_query_set: None,
                 timestamp_writes: None,
             });
 
-            render_pass.set_pipeline(&self.render_pipeline);
-    
-            for command in renderer.commands.iter() {
+            render_pass.set_stencil_reference(0);
+
+            let texture_cache = self.gpu.texture_cache.clone();
+            let mut texture_cache = texture_cache.borrow_mut();
+
+            // rebuilt every frame so resizes and scale factor changes apply straight away
+            let projection = self.projection_matrix(renderer.coordinate_space());
+
+            let mesh_cache = self.gpu.mesh_cache.clone();
+            let mesh_cache = mesh_cache.borrow();
+
+            renderer.sort_commands();
+            let renderer: &Renderer = renderer;
+
+            // draws are skipped while the clip covers no pixels, wgpu has no empty scissor
+            let full_scissor = self.scissor_rect(None);
+            let mut scissor = full_scissor;
+            let mut clipped_out = false;
+            let mut mask_depth: u32 = 0;
+            let mut layer = None;
+            let mut view_projection = projection;
+            let mut depth_test = false;
+
+            for call in renderer.draw_calls() {
+                let command = &renderer.commands[call.command];
+
+                // every layer starts with its own camera, no clip and no mask
+                if layer != Some(call.layer) {
+                    while mask_depth > 0 {
+                        self.end_mask(&mut render_pass, &mut texture_cache, full_scissor, mask_depth);
+                        mask_depth -= 1;
+                    }
+                    render_pass.set_stencil_reference(0);
+                    render_pass.set_scissor_rect(full_scissor.0, full_scissor.1, full_scissor.2, full_scissor.3);
+                    scissor = full_scissor;
+                    clipped_out = false;
+
+                    let render_layer = renderer.layer(call.layer).expect("draw call for a missing layer");
+                    view_projection = projection * render_layer.camera.view_matrix();
+                    depth_test = render_layer.depth_test;
+                    layer = Some(call.layer);
+                }
+
+                if clipped_out && matches!(command, DrawCommand::Mesh2D { .. } | DrawCommand::RetainedMesh2D { .. } | DrawCommand::Particles { .. }) {
+                    continue;
+                }
                 match command {
-                    DrawCommand::Mesh2D {mesh, transform, color, image, ..} => {
+                    DrawCommand::Clip(rect) => {
+                        scissor = self.scissor_rect(*rect);
+                        clipped_out = scissor.2 == 0 || scissor.3 == 0;
+                        if !clipped_out {
+                            render_pass.set_scissor_rect(scissor.0, scissor.1, scissor.2, scissor.3);
+                        }
+                    }
+                    DrawCommand::BeginMask {mesh, transform, inverted} => {
+                        // masks ignore the clip so the stencil can always be restored
+                        render_pass.set_scissor_rect(full_scissor.0, full_scissor.1, full_scissor.2, full_scissor.3);
 
-                        let transform_matrix = {
-                            let translation_matrix = Mat4::from_translation(transform.position.extend(0.0));
-                            let rotation_matrix = Mat4::from_rotation_z(transform.rotation);
-                            let scale_matrix = Mat4::from_scale(transform.scale.extend(1.0));
-                            translation_matrix * rotation_matrix * scale_matrix
-                        };
+                        render_pass.set_pipeline(&self.pipelines.mask_increment);
+                        render_pass.set_stencil_reference(mask_depth);
+                        if *inverted {
+                            // raise everything, then lower the shape back down
+                            self.draw_full_screen(&mut render_pass, &mut texture_cache);
+                            render_pass.set_pipeline(&self.pipelines.mask_decrement);
+                            render_pass.set_stencil_reference(mask_depth + 1);
+                        }
+                        self.bind_draw_state(&mut render_pass, &mut texture_cache, view_projection * transform.to_mat4(), &Paint::default(), None, 0.0);
+                        self.draw_immediate(&mut render_pass, mesh);
 
-                        let draw_uniforms = DrawUniform2D {
-                            transform_matrix: transform_matrix.to_cols_array_2d(),
-                            color: [color.r, color.g, color.b, color.a]
-                        };
+                        mask_depth += 1;
+                        render_pass.set_stencil_reference(mask_depth);
+                        if !clipped_out {
+                            render_pass.set_scissor_rect(scissor.0, scissor.1, scissor.2, scissor.3);
+                        }
+                    }
+                    DrawCommand::EndMask => {
+                        if mask_depth == 0 {
+                            continue;
+                        }
+                        self.end_mask(&mut render_pass, &mut texture_cache, full_scissor, mask_depth);
+                        mask_depth -= 1;
+                        render_pass.set_stencil_reference(mask_depth);
+                        if !clipped_out {
+                            render_pass.set_scissor_rect(scissor.0, scissor.1, scissor.2, scissor.3);
+                        }
+                    }
+                    DrawCommand::Mesh2D {mesh, transform, paint, image} => {
+                        render_pass.set_pipeline(self.pipelines.draw_pipeline(depth_test, call.opaque));
+                        self.bind_draw_state(&mut render_pass, &mut texture_cache, view_projection * transform.to_mat4(), paint, image.as_deref(), call.depth);
+                        self.draw_immediate(&mut render_pass, mesh);
+                    }
+                    DrawCommand::RetainedMesh2D {handle, transform, paint, image} => {
 
-                        let bind_group = Display::create_uniform_bind_group(&self.device, draw_uniforms);
-                        render_pass.set_bind_group(0, &bind_group, &[]);
-
-                        let texture = match image {
-                            None => {
-                                let pixel  = Image::single_pixel(Color::WHITE);
-                                self.texture_cache.entry(pixel.path.clone()).or_insert(Texture::from_image(&self.device, pixel.image.clone()))
-                            },
-                            Some(image) => {
-                                match self.texture_cache.get(&image.path) {
-                                    Some(t) => t,
-                                    None => {
-                                        // load texture
-                                        let texture = Texture::from_image(&self.device, image.image.clone());
-                                        Display::write_texture_to_queue(&self.queue, &texture);
-                                        self.texture_cache.insert(image.path.clone(), texture);
-                                        self.texture_cache.get(&image.path).unwrap()
-                                    }
-                                }
-                            }
+                        let Some(gpu_mesh) = mesh_cache.get(handle) else {
+                            warn!("Mesh {:?} has not been uploaded or was released", handle);
+                            continue;
                         };
 
-                        let bg = Display::create_texture_bind_group(&self.device, &texture);
-                        render_pass.set_bind_group(1, &bg, &[]);
-                        
-                        let vertex_buffer = self.create_vertex_buffer(&mesh.vertices);
-                        let index_buffer = self.create_index_buffer(&mesh.indices);
+                        render_pass.set_pipeline(self.pipelines.draw_pipeline(depth_test, call.opaque));
+                        self.bind_draw_state(&mut render_pass, &mut texture_cache, view_projection * transform.to_mat4(), paint, image.as_deref(), call.depth);
+
+                        render_pass.set_vertex_buffer(0, gpu_mesh.vertex_buffer.slice(..));
+                        render_pass.set_index_buffer(gpu_mesh.index_buffer.slice(..), gpu_mesh.index_format);
+                        render_pass.draw_indexed(0..gpu_mesh.index_count, 0, 0..1)
+                    }
+                    DrawCommand::Particles {instances, transform, image} => {
+                        if instances.is_empty() {
+                            continue;
+                        }
+
+                        render_pass.set_pipeline(self.pipelines.particle_pipeline(depth_test));
+                        self.bind_draw_state(&mut render_pass, &mut texture_cache, view_projection * transform.to_mat4(), &Paint::default(), image.as_deref(), call.depth);
 
-                        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
-                        render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-                        render_pass.draw_indexed(0..mesh.indices.len() as u32, 0, 0..1)
+                        // every particle in one draw, the quad per vertex and the particles per instance
+                        let instance_buffer = self.gpu.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                            label: Some("Particle Instance Buffer"),
+                            contents: bytemuck::cast_slice(instances),
+                            usage: wgpu::BufferUsages::VERTEX,
+                        });
+                        render_pass.set_vertex_buffer(0, self.particle_quad.vertex_buffer.slice(..));
+                        render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+                        render_pass.set_index_buffer(self.particle_quad.index_buffer.slice(..), self.particle_quad.index_format);
+                        render_pass.draw_indexed(0..self.particle_quad.index_count, 0, 0..instances.len() as u32)
                     }
                 }
-                
+
             }
     
         }
     
-        self.queue.submit(std::iter::once(encoder.finish()));
+        #[cfg(feature = "egui")]
+        let egui_commands = self.render_egui(&mut encoder, &view);
+        #[cfg(not(feature = "egui"))]
+        let egui_commands: Vec<wgpu::CommandBuffer> = Vec::new();
+
+        self.gpu.queue.submit(egui_commands.into_iter().chain(std::iter::once(encoder.finish())));
         output.present();
     }
 
+    // returns command buffers that have to be submitted before the encoder's
+    #[cfg(feature = "egui")]
+    fn render_egui(&mut self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) -> Vec<wgpu::CommandBuffer> {
+        let Some(frame) = self.egui_frame.take() else {
+            return Vec::new();
+        };
+        let (device, queue) = (&self.gpu.device, &self.gpu.queue);
 
-    fn create_orthographic_matrix(width: f32, height: f32) -> Mat4 {
-        Mat4::orthographic_rh(0.0, width, height, 0.0, -1.0, 1.0)
+        for (id, delta) in &frame.textures.set {
+            self.egui_renderer.update_texture(device, queue, *id, delta);
+        }
+        let screen = egui_wgpu::ScreenDescriptor {
+            size_in_pixels: [self.config.width, self.config.height],
+            pixels_per_point: frame.pixels_per_point
+        };
+        let commands = self.egui_renderer.update_buffers(device, queue, encoder, &frame.primitives, &screen);
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Egui Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            }).forget_lifetime();
+            self.egui_renderer.render(&mut render_pass, &frame.primitives, &screen);
+        }
+
+        for id in &frame.textures.free {
+            self.egui_renderer.free_texture(id);
+        }
+        commands
     }
 
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec2;
+    use super::*;
+    use crate::graphics::draw::ColorStop;
+
+    #[test]
+    fn gradients_without_stops_are_transparent() {
+        for paint in [
+            Paint::linear_gradient(Vec2::ZERO, Vec2::X, Vec::new()),
+            Paint::radial_gradient(Vec2::ZERO, 1.0, Vec::new()),
+            Paint::conic_gradient(Vec2::ZERO, 0.0, Vec::new())
+        ] {
+            let uniforms = DrawUniform2D::new(Mat4::IDENTITY, &paint);
+            assert_eq!(uniforms.paint_kind, 0);
+            assert_eq!(uniforms.color, [0.0; 4]);
+            assert!(!paint.is_opaque());
+        }
+    }
+
+    #[test]
+    fn gradient_stops_are_sorted_by_offset() {
+        let paint = Paint::linear_gradient(Vec2::ZERO, Vec2::X, vec![
+            ColorStop::new(1.0, Color::BLUE),
+            ColorStop::new(0.0, Color::RED)
+        ]);
+        let uniforms = DrawUniform2D::new(Mat4::IDENTITY, &paint);
+        assert_eq!(uniforms.paint_kind, 1);
+        assert_eq!(uniforms.stop_count, 2);
+        assert_eq!(uniforms.stop_colors[0], <[f32; 4]>::from(Color::RED));
+        assert_eq!(uniforms.stop_offsets[0][..2], [0.0, 1.0]);
+    }
+
+    #[test]
+    fn msaa_falls_back_to_the_largest_supported_count() {
+        assert_eq!(Display::select_sample_count(4, &[1, 2, 4, 8]), 4);
+        assert_eq!(Display::select_sample_count(8, &[1, 2, 4]), 4);
+        assert_eq!(Display::select_sample_count(3, &[1, 2, 4]), 2);
+        assert_eq!(Display::select_sample_count(4, &[]), 1);
+        assert_eq!(Display::select_sample_count(0, &[1, 2, 4]), 1);
+    }
+}
@@ -0,0 +1,64 @@
+use glam::{Mat4, Vec2};
+
+// index of a layer in the renderer, the default layer is always 0
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct LayerId(pub(crate) usize);
+
+impl LayerId {
+    pub const DEFAULT: LayerId = LayerId(0);
+}
+
+// looks at position, in the layer's coordinate space, zoom above 1 magnifies
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Camera2D {
+    pub position: Vec2,
+    pub zoom: f32,
+    pub rotation: f32
+}
+
+impl Camera2D {
+
+    pub fn new(position: Vec2) -> Self {
+        Self { position, ..Default::default() }
+    }
+
+    pub fn view_matrix(&self) -> Mat4 {
+        Mat4::from_scale(Vec2::splat(self.zoom).extend(1.0))
+            * Mat4::from_rotation_z(-self.rotation)
+            * Mat4::from_translation((-self.position).extend(0.0))
+    }
+
+}
+
+impl Default for Camera2D {
+    fn default() -> Self {
+        Self {
+            position: Vec2::ZERO,
+            zoom: 1.0,
+            rotation: 0.0
+        }
+    }
+}
+
+// layers draw in ascending order, each with its own camera, e.g. world below ui below debug
+#[derive(Clone, Debug)]
+pub struct RenderLayer {
+    pub name: String,
+    pub order: i32,
+    pub camera: Camera2D,
+    pub visible: bool,
+    // opaque draws write depth and are drawn front to back, translucent ones are tested against them
+    pub depth_test: bool
+}
+
+impl RenderLayer {
+    pub fn new(name: &str, order: i32) -> Self {
+        Self {
+            name: name.to_string(),
+            order,
+            camera: Camera2D::default(),
+            visible: true,
+            depth_test: false
+        }
+    }
+}
@@ -1,7 +1,14 @@
-use image::math::Rect;
-use lyon::math::Point;
-use lyon::path::traits::PathBuilder;
-use lyon::tessellation::{StrokeOptions, StrokeTessellator, StrokeVertex};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use glam::Vec2;
+use log::warn;
+use lyon::math::{vector, Angle, Point, Vector};
+use lyon::path::builder::BorderRadii;
+use lyon::path::iterator::PathIterator;
+use lyon::path::PathEvent;
+pub use lyon::tessellation::{LineCap, LineJoin};
+use lyon::tessellation::{Side, StrokeOptions, StrokeTessellator, StrokeVertex, TessellationResult};
+use crate::graphics::draw::{Color, Rect};
 use crate::graphics::gpu::Vertex;
 use lyon::lyon_tessellation::{BuffersBuilder, FillOptions, FillTessellator, FillVertex, VertexBuffers};
 use lyon::path::{Path, Winding};
@@ -10,7 +17,266 @@ use lyon::math::{point, Box2D};
 #[derive(Clone, Debug)]
 pub struct Mesh {
     pub vertices: Vec<Vertex>,
-    pub indices: Vec<u16>
+    pub indices: Indices
+}
+
+// 16 bit indices are enough for the built in shapes, larger meshes need 32 bit ones
+#[derive(Clone, Debug, PartialEq)]
+pub enum Indices {
+    U16(Vec<u16>),
+    U32(Vec<u32>)
+}
+
+impl Indices {
+
+    pub fn len(&self) -> usize {
+        match self {
+            Indices::U16(indices) => indices.len(),
+            Indices::U32(indices) => indices.len()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn iter(&self) -> Box<dyn Iterator<Item = u32> + '_> {
+        match self {
+            Indices::U16(indices) => Box::new(indices.iter().map(|i| *i as u32)),
+            Indices::U32(indices) => Box::new(indices.iter().copied())
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Indices::U16(indices) => bytemuck::cast_slice(indices),
+            Indices::U32(indices) => bytemuck::cast_slice(indices)
+        }
+    }
+
+    // stores the indices as 16 bit when every one of them fits
+    pub fn compact(indices: Vec<u32>) -> Self {
+        if indices.iter().all(|i| *i <= u16::MAX as u32) {
+            Indices::U16(indices.into_iter().map(|i| i as u16).collect())
+        } else {
+            Indices::U32(indices)
+        }
+    }
+
+    pub fn format(&self) -> wgpu::IndexFormat {
+        match self {
+            Indices::U16(_) => wgpu::IndexFormat::Uint16,
+            Indices::U32(_) => wgpu::IndexFormat::Uint32
+        }
+    }
+
+}
+
+impl From<Vec<u16>> for Indices {
+    fn from(indices: Vec<u16>) -> Self {
+        Indices::U16(indices)
+    }
+}
+
+impl From<Vec<u32>> for Indices {
+    fn from(indices: Vec<u32>) -> Self {
+        Indices::U32(indices)
+    }
+}
+
+// identifies a mesh uploaded once and kept on the gpu until it is released
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MeshHandle(u64);
+
+impl MeshHandle {
+
+    // ids are unique across renderers because every window shares one mesh cache
+    pub(crate) fn next() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        Self(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+
+    pub fn id(&self) -> u64 {
+        self.0
+    }
+
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Tessellation {
+    // a fixed number of segments for a full turn of a curve
+    Segments(u16),
+    // maximum distance from the true curve in the mesh's own units
+    Tolerance(f32),
+    // maximum distance from the true curve in physical pixels once drawn
+    Adaptive(f32)
+}
+
+impl Default for Tessellation {
+    fn default() -> Self {
+        Tessellation::Adaptive(0.25)
+    }
+}
+
+impl Tessellation {
+
+    // flattening tolerance for curves of the given radius drawn at screen_scale physical pixels per unit
+    pub fn tolerance(&self, radius: f32, screen_scale: f32) -> f32 {
+        let tolerance = match *self {
+            Tessellation::Segments(segments) => radius * (1.0 - (std::f32::consts::PI / segments.max(3) as f32).cos()),
+            Tessellation::Tolerance(tolerance) => tolerance,
+            Tessellation::Adaptive(pixels) => if screen_scale > 0.0 { pixels / screen_scale } else { FillOptions::DEFAULT_TOLERANCE }
+        };
+
+        // keep tiny tolerances from exploding the vertex count
+        tolerance.max(radius.abs() * 1e-4).max(f32::EPSILON)
+    }
+
+}
+
+// corners are named for a y down coordinate space
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct CornerRadii {
+    pub top_left: f32,
+    pub top_right: f32,
+    pub bottom_right: f32,
+    pub bottom_left: f32
+}
+
+impl CornerRadii {
+
+    pub fn new(top_left: f32, top_right: f32, bottom_right: f32, bottom_left: f32) -> Self {
+        Self { top_left, top_right, bottom_right, bottom_left }
+    }
+
+    pub fn uniform(radius: f32) -> Self {
+        Self::new(radius, radius, radius, radius)
+    }
+
+    pub fn max(&self) -> f32 {
+        self.top_left.max(self.top_right).max(self.bottom_right).max(self.bottom_left)
+    }
+
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StrokeStyle {
+    pub width: f32,
+    // alternating dash and gap lengths, an empty array draws a solid line
+    pub dash_array: Vec<f32>,
+    pub dash_offset: f32,
+    pub line_cap: LineCap,
+    pub line_join: LineJoin,
+    pub miter_limit: f32
+}
+
+impl Default for StrokeStyle {
+    fn default() -> Self {
+        Self {
+            width: 1.0,
+            dash_array: Vec::new(),
+            dash_offset: 0.0,
+            line_cap: LineCap::Butt,
+            line_join: LineJoin::Miter,
+            miter_limit: StrokeOptions::DEFAULT_MITER_LIMIT
+        }
+    }
+}
+
+impl From<f32> for StrokeStyle {
+    fn from(width: f32) -> Self {
+        Self::new(width)
+    }
+}
+
+impl StrokeStyle {
+
+    pub fn new(width: f32) -> Self {
+        Self { width, ..Default::default() }
+    }
+
+    pub fn dashed(width: f32, dash_array: Vec<f32>) -> Self {
+        Self { width, dash_array, ..Default::default() }
+    }
+
+    pub fn with_dash_offset(mut self, offset: f32) -> Self {
+        self.dash_offset = offset;
+        self
+    }
+
+    pub fn with_line_cap(mut self, cap: LineCap) -> Self {
+        self.line_cap = cap;
+        self
+    }
+
+    pub fn with_line_join(mut self, join: LineJoin) -> Self {
+        self.line_join = join;
+        self
+    }
+
+    pub fn with_miter_limit(mut self, limit: f32) -> Self {
+        self.miter_limit = limit;
+        self
+    }
+
+    pub fn is_dashed(&self) -> bool {
+        self.dash_array.iter().any(|dash| *dash > 0.0)
+    }
+
+    fn options(&self, tolerance: f32) -> StrokeOptions {
+        StrokeOptions::default()
+            .with_line_width(self.width)
+            .with_line_cap(self.line_cap)
+            .with_line_join(self.line_join)
+            .with_miter_limit(self.miter_limit.max(StrokeOptions::MINIMUM_MITER_LIMIT))
+            .with_tolerance(tolerance)
+    }
+
+}
+
+// most copies of a tiled nine slice edge along one axis
+const MAX_SLICE_TILES: u32 = 256;
+
+// how the edges and center of a nine slice fill the space between the corners
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum SliceFill {
+    #[default]
+    Stretch,
+    // repeats at the image's size, the last copy along each edge is cut short
+    Tile
+}
+
+// widths of the borders cut from an image for a nine slice, in image pixels and named for a y down space
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct NineSlice {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub fill: SliceFill
+}
+
+impl From<f32> for NineSlice {
+    fn from(inset: f32) -> Self {
+        Self::uniform(inset)
+    }
+}
+
+impl NineSlice {
+
+    pub fn new(left: f32, top: f32, right: f32, bottom: f32) -> Self {
+        Self { left, top, right, bottom, fill: SliceFill::Stretch }
+    }
+
+    pub fn uniform(inset: f32) -> Self {
+        Self::new(inset, inset, inset, inset)
+    }
+
+    pub fn with_fill(mut self, fill: SliceFill) -> Self {
+        self.fill = fill;
+        self
+    }
+
 }
 
 // vertex positions for wgpu
@@ -21,18 +287,62 @@ pub struct Mesh {
 
 impl Mesh {
 
+    // builds a mesh from user supplied vertices, triangles with an index past the last vertex are dropped
+    pub fn new(vertices: Vec<Vertex>, indices: impl Into<Indices>) -> Self {
+        let indices = indices.into();
+        if indices.iter().all(|i| (i as usize) < vertices.len()) {
+            return Self { vertices, indices };
+        }
+
+        warn!("Dropping mesh triangles with indices out of range of {} vertices", vertices.len());
+        let indices: Vec<u32> = indices.iter().collect::<Vec<u32>>()
+            .chunks_exact(3)
+            .filter(|triangle| triangle.iter().all(|i| (*i as usize) < vertices.len()))
+            .flatten()
+            .copied()
+            .collect();
+        Self { vertices, indices: Indices::compact(indices) }
+    }
+
+    pub fn empty() -> Self {
+        Self { vertices: Vec::new(), indices: Indices::U16(Vec::new()) }
+    }
+
+    // lyon reports paths it cannot tessellate, such as ones with NaN points, those draw nothing
+    fn from_tessellation(result: TessellationResult, geometry: VertexBuffers<Vertex, u32>) -> Self {
+        match result {
+            Ok(()) => Self { vertices: geometry.vertices, indices: Indices::compact(geometry.indices) },
+            Err(error) => {
+                warn!("Failed to tessellate path: {:?}", error);
+                Self::empty()
+            }
+        }
+    }
+
+    pub fn is_opaque(&self) -> bool {
+        self.vertices.iter().all(|vertex| vertex.color[3] >= 1.0)
+    }
+
+    pub fn set_color(&mut self, color: Color) {
+        for vertex in self.vertices.iter_mut() {
+            vertex.color = color.into();
+        }
+    }
+
+    // fewer than 3 points enclose nothing and give an empty mesh
     pub fn new_polygon(vertices: Vec<Point>) -> Self {
-        let mut builder = Path::builder();
-        builder.begin(vertices[0]);
-        for vertex in vertices.iter().skip(1) {
-            builder.line_to(*vertex);
+        if vertices.len() < 3 {
+            return Self::empty();
         }
-        builder.close();
-        Self::new_filled_path(builder.build())
+        Self::new_filled_path(Self::closed_path(vertices))
     }
 
     pub fn new_triangle() -> Self {
-        Self::new_polygon(vec![
+        Self::new_filled_path(Self::triangle_path())
+    }
+
+    pub fn triangle_path() -> Path {
+        Self::closed_path(vec![
             Point::new(0.0, 0.5),
             Point::new(0.5, -0.5),
             Point::new(-0.5, -0.5)
@@ -40,58 +350,722 @@ impl Mesh {
     }
 
     pub fn new_rectangle(width: f32, height: f32) -> Self {
-        let mut geometry: VertexBuffers<Vertex, u16> = VertexBuffers::new();
-        let mut tessellator = FillTessellator::new();
-        tessellator.tessellate_rectangle(  
+        let (half_width, half_height) = (width * 0.5, height * 0.5);
+        let mut geometry: VertexBuffers<Vertex, u32> = VertexBuffers::new();
+        let result = FillTessellator::new().tessellate_rectangle(
             &Box2D {
-                min: point(-1.0, -1.0),
-                max: point(1.0, 1.0),
+                min: point(-half_width, -half_height),
+                max: point(half_width, half_height),
             },
             &FillOptions::DEFAULT,
             &mut BuffersBuilder::new(&mut geometry, |vertex: FillVertex| {
-                Vertex {
-                    position: [vertex.position().x, vertex.position().y, 0.0],
-                    uv: [0.0, 0.0],
-                }
+                // uvs run top to bottom in a y down space
+                Vertex::new(
+                    [vertex.position().x, vertex.position().y, 0.0],
+                    [
+                        if width > 0.0 { vertex.position().x / width + 0.5 } else { 0.0 },
+                        if height > 0.0 { vertex.position().y / height + 0.5 } else { 0.0 }
+                    ]
+                )
             }),
-        ).unwrap();
+        );
+        Self::from_tessellation(result, geometry)
+    }
 
-        let indices = geometry.indices.clone();
-        let vertices = geometry.vertices.clone();
+    pub fn new_circle(radius: f32, segments: u16) -> Self {
+        if segments < 3 {
+            return Self::new_circle_with_tolerance(radius, FillOptions::DEFAULT_TOLERANCE);
+        }
 
-        Self { vertices, indices }
+        Self::new_filled_path(Self::circle_path(radius, segments))
+    }
 
+    // a circle as a polygon with the given number of segments, starting on the positive x axis
+    pub fn circle_path(radius: f32, segments: u16) -> Path {
+        let segments = segments.max(3);
+        let step = std::f32::consts::TAU / segments as f32;
+        Self::closed_path((0..segments)
+            .map(|i| point(radius * (i as f32 * step).cos(), radius * (i as f32 * step).sin()))
+            .collect())
     }
 
-    pub fn new_circle(radius: f32, segments: u16) -> Self {
+    pub fn new_circle_with_tolerance(radius: f32, tolerance: f32) -> Self {
         let mut builder  = Path::builder();
         builder.add_circle(point(0.0, 0.0), radius, Winding::Positive);
+        Self::new_filled_path_with_tolerance(builder.build(), tolerance)
+    }
+
+    pub fn ellipse_path(radii: Vec2) -> Path {
+        let mut builder = Path::builder();
+        builder.add_ellipse(point(0.0, 0.0), vector(radii.x, radii.y), Angle::radians(0.0), Winding::Positive);
+        builder.build()
+    }
+
+    // angles are in radians from the positive x axis, the arc is left open
+    pub fn arc_path(radii: Vec2, start_angle: f32, sweep_angle: f32) -> Path {
+        let mut builder = Path::svg_builder();
+        builder.move_to(point(radii.x * start_angle.cos(), radii.y * start_angle.sin()));
+        builder.arc(point(0.0, 0.0), vector(radii.x, radii.y), Angle::radians(sweep_angle), Angle::radians(0.0));
+        builder.build()
+    }
+
+    pub fn pie_path(radii: Vec2, start_angle: f32, sweep_angle: f32) -> Path {
+        let mut builder = Path::svg_builder();
+        builder.move_to(point(0.0, 0.0));
+        builder.line_to(point(radii.x * start_angle.cos(), radii.y * start_angle.sin()));
+        builder.arc(point(0.0, 0.0), vector(radii.x, radii.y), Angle::radians(sweep_angle), Angle::radians(0.0));
         builder.close();
-        Self::new_filled_path(builder.build())
+        builder.build()
+    }
+
+    pub fn rounded_rectangle_path(size: Vec2, radii: CornerRadii) -> Path {
+        let half = size * 0.5;
+        let mut builder = Path::builder();
+        builder.add_rounded_rectangle(
+            &Box2D { min: point(-half.x, -half.y), max: point(half.x, half.y) },
+            &BorderRadii {
+                top_left: radii.top_left,
+                top_right: radii.top_right,
+                bottom_left: radii.bottom_left,
+                bottom_right: radii.bottom_right
+            },
+            Winding::Positive
+        );
+        builder.build()
+    }
+
+    // the first vertex points towards negative y
+    pub fn regular_polygon_path(radius: f32, sides: u16) -> Path {
+        let sides = sides.max(3);
+        let step = std::f32::consts::TAU / sides as f32;
+        Self::closed_path((0..sides)
+            .map(|i| Self::polar(radius, i as f32 * step - std::f32::consts::FRAC_PI_2))
+            .collect())
+    }
+
+    pub fn star_path(points: u16, outer_radius: f32, inner_radius: f32) -> Path {
+        let points = points.max(2);
+        let step = std::f32::consts::PI / points as f32;
+        Self::closed_path((0..points * 2)
+            .map(|i| {
+                let radius = if i.is_multiple_of(2) { outer_radius } else { inner_radius };
+                Self::polar(radius, i as f32 * step - std::f32::consts::FRAC_PI_2)
+            })
+            .collect())
+    }
+
+    pub fn rectangle_path(size: Vec2) -> Path {
+        let half = size * 0.5;
+        let mut builder = Path::builder();
+        builder.add_rectangle(&Box2D { min: point(-half.x, -half.y), max: point(half.x, half.y) }, Winding::Positive);
+        builder.build()
+    }
+
+    // splits every sub path into dashes, the pattern carries on around the closing edge of closed shapes
+    pub fn dash_path(path: &Path, dash_array: &[f32], dash_offset: f32, tolerance: f32) -> Path {
+        // an odd number of entries is repeated to make an even pattern, as svg does
+        let mut dashes: Vec<f32> = dash_array.iter().map(|dash| dash.max(0.0)).collect();
+        if !dashes.len().is_multiple_of(2) {
+            dashes.extend_from_within(..);
+        }
+        if dashes.iter().sum::<f32>() <= 0.0 {
+            return path.clone();
+        }
+        // dashes shorter than the tolerance can't be seen and would stall the walk along long lines
+        for dash in &mut dashes {
+            *dash = dash.max(tolerance);
+        }
+        let pattern_length: f32 = dashes.iter().sum();
+
+        let mut builder = Path::builder();
+        let mut dasher = Dasher::new(&dashes, dash_offset.rem_euclid(pattern_length));
+
+        for event in path.iter().flattened(tolerance) {
+            match event {
+                PathEvent::Begin { .. } => {
+                    dasher = Dasher::new(&dashes, dash_offset.rem_euclid(pattern_length));
+                }
+                PathEvent::Line { from, to } => dasher.line(&mut builder, from, to),
+                PathEvent::End { last, first, close } => {
+                    if close {
+                        dasher.line(&mut builder, last, first);
+                    }
+                    dasher.finish(&mut builder);
+                }
+                _ => {}
+            }
+        }
+
+        builder.build()
+    }
+
+    fn closed_path(points: Vec<Point>) -> Path {
+        let mut builder = Path::builder();
+        let Some(first) = points.first() else {
+            return builder.build();
+        };
+        builder.begin(*first);
+        for p in points.iter().skip(1) {
+            builder.line_to(*p);
+        }
+        builder.close();
+        builder.build()
+    }
+
+    fn polar(radius: f32, angle: f32) -> Point {
+        point(radius * angle.cos(), radius * angle.sin())
+    }
+
+    pub fn line_path(start: Vec2, end: Vec2) -> Path {
+        let mut builder = Path::builder();
+        builder.begin(point(start.x, start.y));
+        builder.line_to(point(end.x, end.y));
+        builder.end(false);
+        builder.build()
+    }
+
+    pub fn new_line(start: Vec2, end: Vec2, thickness: f32) -> Self {
+        Self::new_stroked_path(Self::line_path(start, end), thickness)
+    }
+
+    pub fn new_stroked_path(path: Path, thickness: f32) -> Mesh {
+        Self::new_stroked_path_with_tolerance(path, thickness, StrokeOptions::DEFAULT_TOLERANCE)
+    }
+
+    pub fn new_stroked_path_with_tolerance(path: Path, thickness: f32, tolerance: f32) -> Mesh {
+        Self::new_stroked_path_with_style(path, &StrokeStyle::new(thickness), tolerance)
+    }
+
+    pub fn new_stroked_path_with_style(path: Path, style: &StrokeStyle, tolerance: f32) -> Mesh {
+        let path = if style.is_dashed() {
+            Self::dash_path(&path, &style.dash_array, style.dash_offset, tolerance)
+        } else {
+            path
+        };
+
+        let mut geometry: VertexBuffers<Vertex, u32> = VertexBuffers::new();
+        let result = StrokeTessellator::new().tessellate_path(
+            &path,
+            &style.options(tolerance),
+            &mut BuffersBuilder::new(&mut geometry, |vertex: StrokeVertex| {
+                Vertex::new([vertex.position().x, vertex.position().y, 0.0], [0.0, 0.0])
+            }),
+        );
+        Self::from_tessellation(result, geometry)
+    }
+
+    pub fn flip_uv_vertical(&mut self) {
+        for vertex in self.vertices.iter_mut() {
+            vertex.uv[1] = 1.0 - vertex.uv[1];
+        }
     }
 
     pub fn new_filled_path(path: Path) -> Mesh {
+        Self::new_filled_path_with_tolerance(path, FillOptions::DEFAULT_TOLERANCE)
+    }
+
+    pub fn new_filled_path_with_tolerance(path: Path, tolerance: f32) -> Mesh {
         // Create a destination vertex and index buffers.
-        let mut geometry: VertexBuffers<Vertex, u16> = VertexBuffers::new();
+        let mut geometry: VertexBuffers<Vertex, u32> = VertexBuffers::new();
 
         // Create the destination tessellator.
-        let mut tessellator = FillTessellator::new();
-
-        tessellator.tessellate_path(
+        let result = FillTessellator::new().tessellate_path(
             &path,
-            &FillOptions::default(),
+            &FillOptions::default().with_tolerance(tolerance),
             &mut BuffersBuilder::new(&mut geometry, |vertex: FillVertex| {
-                Vertex {
-                    position: [vertex.position().x, vertex.position().y, 0.0],
-                    uv: [0.0, 0.0],
-                }
+                Vertex::new([vertex.position().x, vertex.position().y, 0.0], [0.0, 0.0])
+            }),
+        );
+        Self::from_tessellation(result, geometry)
+    }
+
+    // analytic antialiasing: the fill plus a fringe outside its outline that fades to transparent over the feather width
+    pub fn new_feathered_fill(path: Path, tolerance: f32, feather: f32) -> Mesh {
+        let mut mesh = Self::new_filled_path_with_tolerance(path.clone(), tolerance);
+
+        // the fill is on the left of a path with positive area, holes wind the other way and keep it there
+        let inside = if Self::signed_area(&path, tolerance) >= 0.0 { Side::Positive } else { Side::Negative };
+
+        let mut geometry: VertexBuffers<Vertex, u32> = VertexBuffers::new();
+        let result = StrokeTessellator::new().tessellate_path(
+            &path,
+            &StrokeOptions::default().with_line_width(feather).with_tolerance(tolerance),
+            &mut BuffersBuilder::new(&mut geometry, |vertex: StrokeVertex| {
+                // inner vertices sit on the outline so translucent fills are not blended twice
+                let (position, alpha) = if vertex.side() == inside {
+                    (vertex.position_on_path(), 1.0)
+                } else {
+                    (vertex.position_on_path() + vertex.normal() * feather, 0.0)
+                };
+                Vertex::new([position.x, position.y, 0.0], [0.0, 0.0]).with_color(Color::new(1.0, 1.0, 1.0, alpha))
+            }),
+        );
+
+        // without a fringe the fill is still drawn, just with hard edges
+        if let Err(error) = result {
+            warn!("Failed to tessellate the feathered edge of a path: {:?}", error);
+            return mesh;
+        }
+        mesh.append(geometry.vertices, geometry.indices);
+        mesh
+    }
+
+    // analytic antialiasing for strokes: every open edge of the stroke is extruded along lyon's normals into a fading fringe
+    pub fn new_feathered_stroke(path: Path, style: &StrokeStyle, tolerance: f32, feather: f32) -> Mesh {
+        let path = if style.is_dashed() {
+            Self::dash_path(&path, &style.dash_array, style.dash_offset, tolerance)
+        } else {
+            path
+        };
+
+        let mut geometry: VertexBuffers<(Vertex, Vector), u32> = VertexBuffers::new();
+        let result = StrokeTessellator::new().tessellate_path(
+            &path,
+            &style.options(tolerance),
+            &mut BuffersBuilder::new(&mut geometry, |vertex: StrokeVertex| {
+                (Vertex::new([vertex.position().x, vertex.position().y, 0.0], [0.0, 0.0]), vertex.normal())
             }),
-        ).unwrap();
+        );
+        if let Err(error) = result {
+            warn!("Failed to tessellate path: {:?}", error);
+            return Self::empty();
+        }
+
+        // edges used by a single triangle are on the outside of the stroke
+        let mut edge_count: HashMap<(u32, u32), u32> = HashMap::new();
+        for triangle in geometry.indices.chunks_exact(3) {
+            for (a, b) in [(triangle[0], triangle[1]), (triangle[1], triangle[2]), (triangle[2], triangle[0])] {
+                *edge_count.entry((a.min(b), a.max(b))).or_default() += 1;
+            }
+        }
+
+        let mut vertices: Vec<Vertex> = geometry.vertices.iter().map(|(vertex, _)| *vertex).collect();
+        let mut indices = geometry.indices.clone();
+        let mut fringe: HashMap<u32, u32> = HashMap::new();
+
+        let mut edges: Vec<(u32, u32)> = edge_count.into_iter().filter(|(_, count)| *count == 1).map(|(edge, _)| edge).collect();
+        edges.sort_unstable();
+        for (a, b) in edges {
+            let [outer_a, outer_b] = [a, b].map(|i| *fringe.entry(i).or_insert_with(|| {
+                let (vertex, normal) = geometry.vertices[i as usize];
+                let position = [vertex.position[0] + normal.x * feather, vertex.position[1] + normal.y * feather, 0.0];
+                vertices.push(Vertex::new(position, [0.0, 0.0]).with_color(Color::new(1.0, 1.0, 1.0, 0.0)));
+                (vertices.len() - 1) as u32
+            }));
+            indices.extend_from_slice(&[a, b, outer_b, a, outer_b, outer_a]);
+        }
+
+        Self { vertices, indices: Indices::compact(indices) }
+    }
+
+    // a rectangle of size centered on the origin, y down, cut into a grid of quads so the corners keep
+    // their size in image pixels while the edges and center fill the rest, corners shrink to fit if the
+    // rectangle is smaller than them
+    pub fn new_nine_slice(size: Vec2, image_size: Vec2, slice: &NineSlice) -> Self {
+        let image_size = image_size.max(Vec2::ONE);
+        let tile = slice.fill == SliceFill::Tile;
+        let columns = Self::slice_axis(size.x, image_size.x, slice.left, slice.right, tile);
+        let rows = Self::slice_axis(size.y, image_size.y, slice.top, slice.bottom, tile);
+
+        let mut vertices = Vec::with_capacity(columns.len() * rows.len() * 4);
+        let mut indices = Vec::with_capacity(columns.len() * rows.len() * 6);
+        for row in &rows {
+            for column in &columns {
+                let base = vertices.len() as u32;
+                for (x, y) in [(0, 0), (1, 0), (1, 1), (0, 1)] {
+                    let position = Vec2::new(column.0[x], row.0[y]) - size * 0.5;
+                    let uv = Vec2::new(column.1[x], row.1[y]) / image_size;
+                    vertices.push(Vertex::new([position.x, position.y, 0.0], uv.into()));
+                }
+                indices.extend([base, base + 1, base + 2, base, base + 2, base + 3]);
+            }
+        }
+        Self::new(vertices, Indices::compact(indices))
+    }
+
+    // spans along one axis as ([start, end] in the mesh, [start, end] in the image)
+    fn slice_axis(length: f32, image_length: f32, start: f32, end: f32, tile: bool) -> Vec<([f32; 2], [f32; 2])> {
+        let (start, end) = (start.clamp(0.0, image_length), end.clamp(0.0, image_length));
+        let border = start + end;
+        let fit = if border > image_length { image_length / border } else { 1.0 };
+        let (start, end) = (start * fit, end * fit);
+        let length = length.max(0.0);
+        let shrink = if border * fit > length { length / (border * fit) } else { 1.0 };
+        let (head, tail) = (start * shrink, end * shrink);
+
+        let mut spans = vec![([0.0, head], [0.0, start])];
+        let (middle, source) = (length - tail - head, image_length - end - start);
+        if tile && source > 0.0 {
+            // tiles are at least a pixel wide and there are never more than MAX_SLICE_TILES of them, stretching
+            // each one a little when they would be thinner
+            let step = source.max(1.0).max(middle / MAX_SLICE_TILES as f32);
+            let count = (middle / step).ceil() as u32;
+            for index in 0..count {
+                let position = head + index as f32 * step;
+                let width = step.min(head + middle - position);
+                // a little slack so rounding never leaves a sliver of a tile at the end
+                if width > 1e-3 {
+                    spans.push(([position, position + width], [start, start + width / step * source]));
+                }
+            }
+        } else {
+            spans.push(([head, head + middle], [start, image_length - end]));
+        }
+        spans.push(([length - tail, length], [image_length - end, image_length]));
+        spans.retain(|(span, _)| span[1] > span[0]);
+        spans
+    }
+
+    // adds vertices and indices relative to them, widening to 32 bit indices when needed
+    pub fn append(&mut self, vertices: Vec<Vertex>, indices: impl IntoIterator<Item = u32>) {
+        let base = self.vertices.len() as u32;
+        let mut combined: Vec<u32> = self.indices.iter().collect();
+        combined.extend(indices.into_iter().map(|i| i + base));
+        self.vertices.extend(vertices);
+        self.indices = Indices::compact(combined);
+    }
+
+    // the box around every vertex in the mesh's own units
+    pub fn bounds(&self) -> Rect {
+        let points: Vec<Vec2> = self.vertices.iter().map(|vertex| Vec2::new(vertex.position[0], vertex.position[1])).collect();
+        Rect::bounding(&points)
+    }
 
-        let indices = geometry.indices.clone();
-        let vertices = geometry.vertices.clone();
+    // whether any triangle covers the point, in the mesh's own units, edges count as inside
+    pub fn contains(&self, point: Vec2) -> bool {
+        let position = |i: u32| Vec2::from_slice(&self.vertices[i as usize].position[..2]);
+        let mut indices = self.indices.iter();
+        let mut triangles = std::iter::from_fn(|| Some([indices.next()?, indices.next()?, indices.next()?]));
+        triangles.any(|triangle| {
+            let (a, b, c) = (position(triangle[0]), position(triangle[1]), position(triangle[2]));
+            // the point is inside when it is on the same side of all three edges, whichever way they wind
+            let sides = [(b - a).perp_dot(point - a), (c - b).perp_dot(point - b), (a - c).perp_dot(point - c)];
+            sides.iter().all(|side| *side >= 0.0) || sides.iter().all(|side| *side <= 0.0)
+        })
+    }
+
+    fn signed_area(path: &Path, tolerance: f32) -> f32 {
+        let cross = |a: Point, b: Point| a.x * b.y - b.x * a.y;
+        path.iter().flattened(tolerance).map(|event| match event {
+            PathEvent::Line { from, to } => cross(from, to),
+            // every subpath is filled as if closed
+            PathEvent::End { last, first, .. } => cross(last, first),
+            _ => 0.0
+        }).sum::<f32>() * 0.5
+    }
+
+}
+
+// walks flattened line segments and emits the parts that fall inside a dash
+struct Dasher<'a> {
+    dashes: &'a [f32],
+    index: usize,
+    remaining: f32,
+    drawing: bool
+}
+
+impl<'a> Dasher<'a> {
+
+    fn new(dashes: &'a [f32], offset: f32) -> Self {
+        let mut dasher = Self { dashes, index: 0, remaining: dashes[0], drawing: false };
+        let mut offset = offset;
+        while offset > 0.0 {
+            if offset < dasher.remaining {
+                dasher.remaining -= offset;
+                break;
+            }
+            offset -= dasher.remaining;
+            dasher.next_dash();
+        }
+        dasher
+    }
+
+    fn is_dash(&self) -> bool {
+        self.index.is_multiple_of(2)
+    }
+
+    fn next_dash(&mut self) {
+        self.index = (self.index + 1) % self.dashes.len();
+        self.remaining = self.dashes[self.index];
+    }
+
+    fn line(&mut self, builder: &mut lyon::path::path::Builder, from: Point, to: Point) {
+        let length = (to - from).length();
+        let mut travelled = 0.0;
+
+        while travelled < length {
+            let step = self.remaining.min(length - travelled);
+            let start = from.lerp(to, travelled / length);
+            let end = from.lerp(to, (travelled + step) / length);
+
+            if self.is_dash() {
+                if !self.drawing {
+                    builder.begin(start);
+                    self.drawing = true;
+                }
+                builder.line_to(end);
+            }
+
+            // a step below the precision of travelled would never reach the end of the line
+            if travelled + step <= travelled {
+                break;
+            }
+            travelled += step;
+            self.remaining -= step;
+            if self.remaining <= 0.0 {
+                if self.drawing {
+                    builder.end(false);
+                    self.drawing = false;
+                }
+                self.next_dash();
+            }
+        }
+    }
+
+    fn finish(&mut self, builder: &mut lyon::path::path::Builder) {
+        if self.drawing {
+            builder.end(false);
+            self.drawing = false;
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quad_rects(mesh: &Mesh) -> Vec<(Rect, Rect)> {
+        mesh.vertices.chunks_exact(4).map(|quad| {
+            let positions: Vec<Vec2> = quad.iter().map(|vertex| Vec2::new(vertex.position[0], vertex.position[1])).collect();
+            let uvs: Vec<Vec2> = quad.iter().map(|vertex| Vec2::from(vertex.uv)).collect();
+            (Rect::bounding(&positions), Rect::bounding(&uvs))
+        }).collect()
+    }
+
+    fn assert_spans(spans: &[([f32; 2], [f32; 2])], expected: &[([f32; 2], [f32; 2])]) {
+        let close = |a: [f32; 2], b: [f32; 2]| (a[0] - b[0]).abs() < 1e-4 && (a[1] - b[1]).abs() < 1e-4;
+        assert!(spans.len() == expected.len() && spans.iter().zip(expected).all(|(a, b)| close(a.0, b.0) && close(a.1, b.1)), "{spans:?} != {expected:?}");
+    }
+
+    #[test]
+    fn nine_slice_corners_keep_their_pixel_size() {
+        let mesh = Mesh::new_nine_slice(Vec2::new(100.0, 60.0), Vec2::splat(32.0), &NineSlice::uniform(8.0));
+        let quads = quad_rects(&mesh);
+        assert_eq!(quads.len(), 9);
+        // rows top to bottom, columns left to right, centered on the origin
+        assert_eq!(quads[0], (Rect::new(Vec2::new(-50.0, -30.0), Vec2::new(-42.0, -22.0)), Rect::new(Vec2::ZERO, Vec2::splat(0.25))));
+        assert_eq!(quads[8], (Rect::new(Vec2::new(42.0, 22.0), Vec2::new(50.0, 30.0)), Rect::new(Vec2::splat(0.75), Vec2::ONE)));
+        // the edges and center stretch the middle of the image
+        assert_eq!(quads[1], (Rect::new(Vec2::new(-42.0, -30.0), Vec2::new(42.0, -22.0)), Rect::new(Vec2::new(0.25, 0.0), Vec2::new(0.75, 0.25))));
+        assert_eq!(quads[4].1, Rect::new(Vec2::splat(0.25), Vec2::splat(0.75)));
+    }
+
+    #[test]
+    fn tiled_nine_slice_edges_repeat_and_cut_the_last_copy() {
+        let spans = Mesh::slice_axis(100.0, 32.0, 8.0, 8.0, true);
+        assert_spans(&spans, &[
+            ([0.0, 8.0], [0.0, 8.0]),
+            ([8.0, 24.0], [8.0, 24.0]), ([24.0, 40.0], [8.0, 24.0]), ([40.0, 56.0], [8.0, 24.0]),
+            ([56.0, 72.0], [8.0, 24.0]), ([72.0, 88.0], [8.0, 24.0]), ([88.0, 92.0], [8.0, 12.0]),
+            ([92.0, 100.0], [24.0, 32.0])
+        ]);
+        let mesh = Mesh::new_nine_slice(Vec2::new(100.0, 32.0), Vec2::splat(32.0), &NineSlice::uniform(8.0).with_fill(SliceFill::Tile));
+        assert_eq!(quad_rects(&mesh).len(), 8 * 3);
+    }
 
-        Self { vertices, indices }
+    #[test]
+    fn nine_slice_insets_shrink_to_fit() {
+        // insets wider than the image are scaled down together, leaving no middle to stretch
+        assert_spans(&Mesh::slice_axis(100.0, 32.0, 20.0, 20.0, false), &[([0.0, 16.0], [0.0, 16.0]), ([16.0, 84.0], [16.0, 16.0]), ([84.0, 100.0], [16.0, 32.0])]);
+        // with nothing left to tile the seam is stretched instead of leaving a hole
+        assert_spans(&Mesh::slice_axis(100.0, 32.0, 20.0, 20.0, true), &[([0.0, 16.0], [0.0, 16.0]), ([16.0, 84.0], [16.0, 16.0]), ([84.0, 100.0], [16.0, 32.0])]);
+        // a target smaller than the corners squeezes them, still showing all of each corner
+        assert_spans(&Mesh::slice_axis(10.0, 32.0, 8.0, 12.0, false), &[([0.0, 4.0], [0.0, 8.0]), ([4.0, 10.0], [20.0, 32.0])]);
+        assert!(Mesh::slice_axis(0.0, 32.0, 8.0, 8.0, true).is_empty());
     }
 
+    #[test]
+    fn tiny_tiled_sources_are_bounded() {
+        // a thousandth of a pixel between the corners would otherwise need millions of tiles
+        let spans = Mesh::slice_axis(100_000.0, 16.001, 8.0, 8.0, true);
+        assert!(spans.len() <= MAX_SLICE_TILES as usize + 2);
+        assert!((spans[spans.len() - 2].0[1] - (100_000.0 - 8.0)).abs() < 0.1);
+        let spans = Mesh::slice_axis(100.0, 16.001, 8.0, 8.0, true);
+        assert_eq!(spans.len(), 84 + 2);
+        assert!(spans[1..85].iter().all(|(span, source)| span[1] - span[0] == 1.0 && (source[1] - source[0] - 0.001).abs() < 1e-4));
+    }
+
+    #[test]
+    fn polygons_with_fewer_than_three_points_are_empty() {
+        for points in [vec![], vec![point(0.0, 0.0)], vec![point(0.0, 0.0), point(1.0, 0.0)]] {
+            let mesh = Mesh::new_polygon(points);
+            assert!(mesh.vertices.is_empty());
+            assert!(mesh.indices.is_empty());
+        }
+        assert_eq!(Mesh::new_polygon(vec![point(0.0, 0.0), point(1.0, 0.0), point(0.0, 1.0)]).indices.len(), 3);
+    }
+
+    #[test]
+    fn long_dashed_strokes_switch_to_32_bit_indices() {
+        // 20000 dashes of 4 vertices each is well past what 16 bit indices can address
+        let path = Mesh::line_path(Vec2::ZERO, Vec2::new(40000.0, 0.0));
+        let mesh = Mesh::new_stroked_path_with_style(path, &StrokeStyle::dashed(1.0, vec![1.0, 1.0]), 0.1);
+        assert!(mesh.vertices.len() > u16::MAX as usize);
+        assert!(matches!(mesh.indices, Indices::U32(_)));
+        assert!(mesh.indices.iter().all(|i| (i as usize) < mesh.vertices.len()));
+
+        let feathered = Mesh::new_feathered_stroke(Mesh::line_path(Vec2::ZERO, Vec2::new(40000.0, 0.0)), &StrokeStyle::dashed(1.0, vec![1.0, 1.0]), 0.1, 1.0);
+        assert!(feathered.indices.iter().all(|i| (i as usize) < feathered.vertices.len()));
+    }
+
+    #[test]
+    fn small_meshes_keep_16_bit_indices() {
+        assert!(matches!(Mesh::new_circle(1.0, 32).indices, Indices::U16(_)));
+        assert!(matches!(Mesh::new_rectangle(1.0, 1.0).indices, Indices::U16(_)));
+    }
+
+    // the start and end of every dash in a dashed path
+    fn dash_ends(path: &Path) -> Vec<(Vec2, Vec2)> {
+        let mut dashes = Vec::new();
+        let mut start = Vec2::ZERO;
+        for event in path.iter() {
+            match event {
+                PathEvent::Begin { at } => start = Vec2::new(at.x, at.y),
+                PathEvent::End { last, .. } => dashes.push((start, Vec2::new(last.x, last.y))),
+                _ => {}
+            }
+        }
+        dashes
+    }
+
+    fn assert_dashes(path: &Path, expected: &[(f32, f32)]) {
+        let dashes: Vec<(f32, f32)> = dash_ends(path).iter().map(|(start, end)| (start.x, end.x)).collect();
+        let close = |a: &(f32, f32), b: &(f32, f32)| (a.0 - b.0).abs() < 1e-4 && (a.1 - b.1).abs() < 1e-4;
+        assert!(dashes.len() == expected.len() && dashes.iter().zip(expected).all(|(a, b)| close(a, b)), "{dashes:?} != {expected:?}");
+    }
+
+    #[test]
+    fn dash_offsets_wrap_around_the_pattern() {
+        let line = Mesh::line_path(Vec2::ZERO, Vec2::new(10.0, 0.0));
+        assert_dashes(&Mesh::dash_path(&line, &[2.0, 1.0], 0.0, 0.1), &[(0.0, 2.0), (3.0, 5.0), (6.0, 8.0), (9.0, 10.0)]);
+        // an offset of a whole pattern and a bit, or a negative one, lands in the same place
+        let expected = [(0.0, 1.5), (2.5, 4.5), (5.5, 7.5), (8.5, 10.0)];
+        assert_dashes(&Mesh::dash_path(&line, &[2.0, 1.0], 3.5, 0.1), &expected);
+        assert_dashes(&Mesh::dash_path(&line, &[2.0, 1.0], -2.5, 0.1), &expected);
+        // starting in a gap skips it
+        assert_dashes(&Mesh::dash_path(&line, &[2.0, 1.0], 2.5, 0.1), &[(0.5, 2.5), (3.5, 5.5), (6.5, 8.5), (9.5, 10.0)]);
+    }
+
+    #[test]
+    fn odd_dash_arrays_are_repeated() {
+        // [3] is a 3 long dash followed by a 3 long gap, [1, 2, 3] alternates which entries are gaps
+        let line = Mesh::line_path(Vec2::ZERO, Vec2::new(14.0, 0.0));
+        assert_dashes(&Mesh::dash_path(&line, &[3.0], 0.0, 0.1), &[(0.0, 3.0), (6.0, 9.0), (12.0, 14.0)]);
+        assert_dashes(&Mesh::dash_path(&line, &[1.0, 2.0, 3.0], 0.0, 0.1), &[(0.0, 1.0), (3.0, 6.0), (7.0, 9.0), (12.0, 13.0)]);
+    }
+
+    #[test]
+    fn empty_dash_patterns_keep_the_path_solid() {
+        let line = Mesh::line_path(Vec2::ZERO, Vec2::new(10.0, 0.0));
+        assert_dashes(&Mesh::dash_path(&line, &[0.0, 0.0], 0.0, 0.1), &[(0.0, 10.0)]);
+        assert_dashes(&Mesh::dash_path(&line, &[-1.0], 0.0, 0.1), &[(0.0, 10.0)]);
+    }
+
+    #[test]
+    fn dashes_carry_on_around_closed_shapes() {
+        // a 4x4 square dashed 3 on 2 off draws 0-3, 5-8, 10-13 and 15-16 along its 16 long perimeter
+        let square = Mesh::rectangle_path(Vec2::splat(4.0));
+        let dashes = dash_ends(&Mesh::dash_path(&square, &[3.0, 2.0], 0.0, 0.1));
+        assert_eq!(dashes.len(), 4);
+        let dashed: f32 = dashes.iter().map(|(start, end)| {
+            // every dash turns at most one corner, so its length is its manhattan distance
+            (*end - *start).abs().element_sum()
+        }).sum();
+        assert!((dashed - 10.0).abs() < 1e-4, "{dashed}");
+        // the last dash runs along the closing edge back to the start, but is not joined to the first
+        let (first, last) = (dashes[0], dashes[3]);
+        assert_eq!(last.1, first.0);
+        assert!(last.0.distance(first.0) > 0.5);
+    }
+
+    #[test]
+    fn tiny_dashes_are_clamped_to_the_tolerance() {
+        // dashes far below the precision of the distance along the line used to never finish
+        let line = Mesh::line_path(Vec2::ZERO, Vec2::new(1000.0, 0.0));
+        let dashes = dash_ends(&Mesh::dash_path(&line, &[1e-5, 1e-5], 0.0, 0.5));
+        assert_eq!(dashes.len(), 1000);
+        assert!(dashes.iter().all(|(start, end)| (end.x - start.x - 0.5).abs() < 1e-3));
+    }
+
+    #[test]
+    fn tessellation_tolerances() {
+        // the sagitta of one segment, so a full turn of the curve flattens to about that many segments
+        let sagitta = 100.0 * (1.0 - (std::f32::consts::PI / 16.0).cos());
+        assert!((Tessellation::Segments(16).tolerance(100.0, 1.0) - sagitta).abs() < 1e-4);
+        assert_eq!(Tessellation::Segments(0).tolerance(100.0, 1.0), Tessellation::Segments(3).tolerance(100.0, 1.0));
+        // fixed tolerances ignore the scale, adaptive ones are in physical pixels
+        assert_eq!(Tessellation::Tolerance(0.5).tolerance(100.0, 4.0), 0.5);
+        assert_eq!(Tessellation::Adaptive(0.5).tolerance(100.0, 4.0), 0.125);
+        assert_eq!(Tessellation::Adaptive(0.5).tolerance(100.0, 0.0), FillOptions::DEFAULT_TOLERANCE);
+        // tiny tolerances are kept to a fraction of the radius, and never reach zero
+        assert_eq!(Tessellation::Tolerance(0.0).tolerance(100.0, 1.0), 0.01);
+        assert_eq!(Tessellation::Adaptive(0.5).tolerance(1000.0, 1e9), 1000.0 * 1e-4);
+        assert_eq!(Tessellation::Tolerance(0.0).tolerance(0.0, 1.0), f32::EPSILON);
+    }
+
+    #[test]
+    fn triangles_with_out_of_range_indices_are_dropped() {
+        let vertices = vec![Vertex::new([0.0; 3], [0.0; 2]); 4];
+        let mesh = Mesh::new(vertices.clone(), vec![0u16, 1, 2, 1, 2, 4, 2, 3, 0]);
+        assert_eq!(mesh.indices.iter().collect::<Vec<u32>>(), vec![0, 1, 2, 2, 3, 0]);
+        assert_eq!(mesh.vertices.len(), 4);
+
+        let untouched = Mesh::new(vertices.clone(), vec![0u32, 1, 2]);
+        assert!(matches!(untouched.indices, Indices::U32(_)));
+        assert!(Mesh::new(Vec::new(), vec![0u16, 1, 2]).indices.is_empty());
+    }
+
+    fn alphas(mesh: &Mesh) -> (Vec<Vec2>, Vec<Vec2>) {
+        let position = |vertex: &Vertex| Vec2::new(vertex.position[0], vertex.position[1]);
+        let solid = mesh.vertices.iter().filter(|vertex| vertex.color[3] == 1.0).map(position).collect();
+        let faded = mesh.vertices.iter().filter(|vertex| vertex.color[3] == 0.0).map(position).collect();
+        (solid, faded)
+    }
+
+    #[test]
+    fn feathered_fills_fade_out_past_their_edge() {
+        let square = Rect::new(Vec2::splat(-5.0), Vec2::splat(5.0));
+        // both windings put the fringe outside the shape
+        let clockwise = Mesh::closed_path(vec![point(-5.0, -5.0), point(-5.0, 5.0), point(5.0, 5.0), point(5.0, -5.0)]);
+        for path in [Mesh::rectangle_path(Vec2::splat(10.0)), clockwise] {
+            let mesh = Mesh::new_feathered_fill(path, 0.1, 0.5);
+            let (solid, faded) = alphas(&mesh);
+            assert_eq!(solid.len() + faded.len(), mesh.vertices.len());
+            assert!(solid.iter().all(|position| square.contains(*position)), "{solid:?}");
+            assert!(!faded.is_empty());
+            // every faded vertex sits half a unit outside the square
+            assert!(faded.iter().all(|position| {
+                let outside = (position.abs() - Vec2::splat(5.0)).max_element();
+                (outside - 0.5).abs() < 1e-4
+            }), "{faded:?}");
+            assert!(mesh.indices.iter().all(|i| (i as usize) < mesh.vertices.len()));
+        }
+    }
+
+    #[test]
+    fn feathered_strokes_fade_out_on_both_sides() {
+        let line = Mesh::line_path(Vec2::new(0.0, 0.0), Vec2::new(10.0, 0.0));
+        let style = StrokeStyle::new(2.0);
+        let mesh = Mesh::new_feathered_stroke(line.clone(), &style, 0.1, 0.5);
+        let (solid, faded) = alphas(&mesh);
+        // the stroke keeps its width, the fringe reaches half a unit past it all round
+        assert!(solid.iter().all(|position| position.y.abs() <= 1.0 + 1e-4));
+        assert!(faded.iter().any(|position| (position.y - 1.5).abs() < 1e-4));
+        assert!(faded.iter().any(|position| (position.y + 1.5).abs() < 1e-4));
+        assert!(faded.iter().all(|position| position.y.abs() > 1.0 || position.x < 0.0 || position.x > 10.0));
+        // a fringe quad of two triangles for each of the four outer edges
+        let plain = Mesh::new_stroked_path_with_style(line, &style, 0.1);
+        assert_eq!(mesh.indices.len(), plain.indices.len() + 4 * 6);
+    }
 }
@@ -1,4 +1,11 @@
 pub mod gpu;
 pub mod draw;
-mod mesh;
-
+pub mod mesh;
+pub mod layer;
+pub mod transform;
+pub mod sprite;
+pub mod particles;
+pub mod tilemap;
+pub mod tiled;
+pub mod text;
+pub mod geometry;
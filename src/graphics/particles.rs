@@ -0,0 +1,484 @@
+use std::f32::consts::FRAC_PI_2;
+use std::sync::Arc;
+use glam::Vec2;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use crate::graphics::draw::{Color, Image, Renderer, Transform2D};
+use crate::graphics::gpu::ParticleInstance;
+use crate::tween::Lerp;
+
+// a value at a point in a particle's life, time runs from 0 at birth to 1 at death
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Keyframe<T> {
+    pub time: f32,
+    pub value: T
+}
+
+impl<T> Keyframe<T> {
+    pub fn new(time: f32, value: T) -> Self {
+        Self { time, value }
+    }
+}
+
+// linear between keyframes sorted by time, the first and last values hold past either end
+fn sample<T: Lerp + Copy>(keys: &[Keyframe<T>], t: f32, default: T) -> T {
+    let (Some(first), Some(last)) = (keys.first(), keys.last()) else {
+        return default;
+    };
+    if t <= first.time {
+        return first.value;
+    }
+    for pair in keys.windows(2) {
+        if t <= pair[1].time {
+            let span = (pair[1].time - pair[0].time).max(f32::EPSILON);
+            return pair[0].value.lerp(&pair[1].value, (t - pair[0].time) / span);
+        }
+    }
+    last.value
+}
+
+// where new particles appear around the emitter's position
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum EmitterShape {
+    #[default]
+    Point,
+    Circle { radius: f32 },
+    Rectangle { size: Vec2 }
+}
+
+// spawns count particles at once when the emitter's clock reaches time
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Burst {
+    pub time: f32,
+    pub count: u32
+}
+
+// everything that defines an emitter, so effects can be saved and loaded as json, ranges are (min, max)
+// and each particle picks a value between them
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EmitterConfig {
+    // the same seed and the same update deltas give the same particles
+    pub seed: u64,
+    pub max_particles: usize,
+    // particles per second
+    pub rate: f32,
+    pub bursts: Vec<Burst>,
+    // seconds of emission, None emits until stopped
+    pub duration: Option<f32>,
+    // starts over after duration, firing the bursts again
+    pub looping: bool,
+    pub shape: EmitterShape,
+    pub lifetime: (f32, f32),
+    pub speed: (f32, f32),
+    // radians from the x axis towards the y axis, spread is the full width of the cone around it
+    pub direction: f32,
+    pub spread: f32,
+    pub gravity: Vec2,
+    // fraction of the velocity lost per second
+    pub damping: f32,
+    pub size: (f32, f32),
+    // multiplies size, a constant 1 when empty
+    pub size_over_life: Vec<Keyframe<f32>>,
+    // constant white when empty
+    pub color_over_life: Vec<Keyframe<Color>>,
+    pub rotation: (f32, f32),
+    pub angular_velocity: (f32, f32),
+    // image path loaded by ParticleEmitter::from_config, None draws plain squares
+    pub texture: Option<String>
+}
+
+impl Default for EmitterConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            max_particles: 1000,
+            rate: 10.0,
+            bursts: Vec::new(),
+            duration: None,
+            looping: true,
+            shape: EmitterShape::Point,
+            lifetime: (1.0, 1.0),
+            speed: (0.5, 0.5),
+            direction: FRAC_PI_2,
+            spread: 0.0,
+            gravity: Vec2::ZERO,
+            damping: 0.0,
+            size: (0.05, 0.05),
+            size_over_life: Vec::new(),
+            color_over_life: Vec::new(),
+            rotation: (0.0, 0.0),
+            angular_velocity: (0.0, 0.0),
+            texture: None
+        }
+    }
+}
+
+impl EmitterConfig {
+
+    pub fn from_json(json: &str) -> Option<Self> {
+        match serde_json::from_str(json) {
+            Ok(config) => Some(config),
+            Err(error) => {
+                warn!("Failed to parse emitter config: {}", error);
+                None
+            }
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("emitter configs always serialize")
+    }
+
+}
+
+// splitmix64, small and the same on every platform so seeded runs repeat exactly
+#[derive(Clone, Debug)]
+struct Rng(u64);
+
+impl Rng {
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    // uniform in 0..1
+    fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn range(&mut self, (min, max): (f32, f32)) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Particle {
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub rotation: f32,
+    pub angular_velocity: f32,
+    pub size: f32,
+    pub age: f32,
+    pub lifetime: f32
+}
+
+impl Particle {
+    // 0 at birth and 1 at death
+    pub fn life(&self) -> f32 {
+        (self.age / self.lifetime).clamp(0.0, 1.0)
+    }
+}
+
+// simulated on the cpu from on_update and drawn as one instanced draw
+#[derive(Clone, Debug)]
+pub struct ParticleEmitter {
+    config: EmitterConfig,
+    image: Option<Arc<Image>>,
+    // where new particles spawn, moving it leaves live particles where they are
+    pub position: Vec2,
+    particles: Vec<Particle>,
+    rng: Rng,
+    // time into the current emission cycle
+    time: f32,
+    // fractional particles owed by the rate
+    pending: f32,
+    emitting: bool
+}
+
+impl ParticleEmitter {
+
+    pub fn new(config: EmitterConfig) -> Self {
+        Self {
+            rng: Rng(config.seed),
+            config,
+            image: None,
+            position: Vec2::ZERO,
+            particles: Vec::new(),
+            time: 0.0,
+            pending: 0.0,
+            emitting: true
+        }
+    }
+
+    // also loads the config's texture, None when it fails to load
+    pub fn from_config(config: EmitterConfig) -> Option<Self> {
+        let image = match config.texture.as_deref() {
+            Some(path) => Some(Arc::new(Image::load(path)?)),
+            None => None
+        };
+        Some(Self { image, ..Self::new(config) })
+    }
+
+    pub fn with_image(mut self, image: Arc<Image>) -> Self {
+        self.image = Some(image);
+        self
+    }
+
+    pub fn with_position(mut self, position: Vec2) -> Self {
+        self.position = position;
+        self
+    }
+
+    pub fn config(&self) -> &EmitterConfig {
+        &self.config
+    }
+
+    pub fn particles(&self) -> &[Particle] {
+        &self.particles
+    }
+
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    pub fn is_emitting(&self) -> bool {
+        self.emitting
+    }
+
+    // stops spawning, live particles play out
+    pub fn stop(&mut self) {
+        self.emitting = false;
+    }
+
+    pub fn start(&mut self) {
+        self.emitting = true;
+    }
+
+    // emission has ended and every particle has died
+    pub fn is_finished(&self) -> bool {
+        !self.emitting && self.particles.is_empty()
+    }
+
+    // back to the state right after new, with the seed reapplied
+    pub fn reset(&mut self) {
+        self.particles.clear();
+        self.rng = Rng(self.config.seed);
+        self.time = 0.0;
+        self.pending = 0.0;
+        self.emitting = true;
+    }
+
+    // spawns count particles straight away, even when stopped
+    pub fn burst(&mut self, count: u32) {
+        for _ in 0..count {
+            self.spawn();
+        }
+    }
+
+    // advances by delta seconds, as passed to on_update
+    pub fn update(&mut self, delta: f32) {
+        if delta <= 0.0 {
+            return;
+        }
+
+        let gravity = self.config.gravity;
+        let damping = 1.0 / (1.0 + self.config.damping.max(0.0) * delta);
+        for particle in self.particles.iter_mut() {
+            particle.age += delta;
+            particle.velocity = (particle.velocity + gravity * delta) * damping;
+            particle.position += particle.velocity * delta;
+            particle.rotation += particle.angular_velocity * delta;
+        }
+        self.particles.retain(|particle| particle.age < particle.lifetime);
+
+        if self.emitting {
+            self.emit(delta);
+        }
+    }
+
+    fn emit(&mut self, delta: f32) {
+        let cycle = self.config.duration.unwrap_or(f32::INFINITY);
+        let mut remaining = delta;
+
+        while remaining > 0.0 {
+            if self.time >= cycle {
+                if !self.config.looping {
+                    self.emitting = false;
+                    return;
+                }
+                self.time = 0.0;
+            }
+            let step = remaining.min(cycle - self.time);
+            if step <= 0.0 {
+                return;
+            }
+            let (start, end) = (self.time, self.time + step);
+
+            self.pending += self.config.rate.max(0.0) * step;
+            let count = self.pending.floor();
+            self.pending -= count;
+            for _ in 0..count as u32 {
+                self.spawn();
+            }
+
+            let bursts: u32 = self.config.bursts.iter()
+                .filter(|burst| burst.time >= start && burst.time < end)
+                .map(|burst| burst.count)
+                .sum();
+            self.burst(bursts);
+
+            self.time = end;
+            remaining -= step;
+        }
+    }
+
+    fn spawn(&mut self) {
+        if self.particles.len() >= self.config.max_particles {
+            return;
+        }
+
+        let config = &self.config;
+        let rng = &mut self.rng;
+        let offset = match config.shape {
+            EmitterShape::Point => Vec2::ZERO,
+            EmitterShape::Circle { radius } => {
+                // the square root spreads points evenly over the area instead of bunching at the center
+                let distance = radius * rng.next_f32().sqrt();
+                Vec2::from_angle(rng.next_f32() * std::f32::consts::TAU) * distance
+            }
+            EmitterShape::Rectangle { size } => (Vec2::new(rng.next_f32(), rng.next_f32()) - 0.5) * size
+        };
+        let angle = config.direction + (rng.next_f32() - 0.5) * config.spread;
+        let speed = rng.range(config.speed);
+
+        let particle = Particle {
+            position: self.position + offset,
+            velocity: Vec2::from_angle(angle) * speed,
+            rotation: rng.range(config.rotation),
+            angular_velocity: rng.range(config.angular_velocity),
+            size: rng.range(config.size),
+            age: 0.0,
+            lifetime: rng.range(config.lifetime).max(f32::EPSILON)
+        };
+        self.particles.push(particle);
+    }
+
+    pub fn instances(&self) -> Vec<ParticleInstance> {
+        self.particles.iter()
+            .map(|particle| {
+                let life = particle.life();
+                let size = particle.size * sample(&self.config.size_over_life, life, 1.0);
+                ParticleInstance {
+                    position: particle.position.into(),
+                    size: [size, size],
+                    rotation: particle.rotation,
+                    color: sample(&self.config.color_over_life, life, Color::WHITE).into()
+                }
+            })
+            .collect()
+    }
+
+    pub fn draw(&self, renderer: &mut Renderer, transform: Transform2D) {
+        if self.particles.is_empty() {
+            return;
+        }
+        renderer.draw_particles(transform, self.instances(), self.image.clone());
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quiet() -> EmitterConfig {
+        EmitterConfig { rate: 0.0, ..EmitterConfig::default() }
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_particles() {
+        let config = EmitterConfig {
+            seed: 42,
+            rate: 120.0,
+            shape: EmitterShape::Circle { radius: 0.3 },
+            lifetime: (0.5, 2.0),
+            speed: (0.1, 1.0),
+            spread: 1.0,
+            size: (0.01, 0.1),
+            rotation: (-1.0, 1.0),
+            ..EmitterConfig::default()
+        };
+        let mut a = ParticleEmitter::new(config.clone());
+        let mut b = ParticleEmitter::new(config.clone());
+        let mut other = ParticleEmitter::new(EmitterConfig { seed: 7, ..config });
+        for delta in [0.016, 0.033, 0.016, 0.1, 0.02] {
+            a.update(delta);
+            b.update(delta);
+            other.update(delta);
+        }
+        assert!(!a.is_empty());
+        assert_eq!(a.particles(), b.particles());
+        assert_ne!(a.particles(), other.particles());
+
+        a.reset();
+        a.update(0.016);
+        let mut fresh = ParticleEmitter::new(a.config().clone());
+        fresh.update(0.016);
+        assert_eq!(a.particles(), fresh.particles());
+    }
+
+    #[test]
+    fn bursts_fire_their_count_once_their_time_is_reached() {
+        let mut emitter = ParticleEmitter::new(EmitterConfig { bursts: vec![Burst { time: 0.5, count: 7 }], ..quiet() });
+        emitter.update(0.25);
+        emitter.update(0.25);
+        assert_eq!(emitter.len(), 0);
+        emitter.update(0.1);
+        assert_eq!(emitter.len(), 7);
+        emitter.update(0.1);
+        assert_eq!(emitter.len(), 7);
+    }
+
+    #[test]
+    fn looping_emitters_fire_their_bursts_every_cycle() {
+        let config = EmitterConfig { bursts: vec![Burst { time: 0.0, count: 3 }], duration: Some(1.0), lifetime: (10.0, 10.0), ..quiet() };
+        let mut emitter = ParticleEmitter::new(config.clone());
+        emitter.update(0.5);
+        assert_eq!(emitter.len(), 3);
+        emitter.update(1.0);
+        assert_eq!(emitter.len(), 6);
+
+        let mut once = ParticleEmitter::new(EmitterConfig { looping: false, ..config });
+        once.update(0.5);
+        once.update(1.0);
+        assert_eq!(once.len(), 3);
+        assert!(!once.is_emitting());
+    }
+
+    #[test]
+    fn bursts_respect_max_particles() {
+        let mut emitter = ParticleEmitter::new(EmitterConfig { max_particles: 4, ..quiet() });
+        emitter.burst(10);
+        assert_eq!(emitter.len(), 4);
+    }
+
+    #[test]
+    fn particles_are_removed_when_their_lifetime_ends() {
+        let mut emitter = ParticleEmitter::new(EmitterConfig { lifetime: (0.5, 1.5), ..quiet() });
+        emitter.burst(50);
+        emitter.update(1.0);
+        assert!(emitter.len() < 50);
+        assert!(emitter.particles().iter().all(|particle| particle.age < particle.lifetime));
+
+        emitter.stop();
+        emitter.update(0.6);
+        assert!(emitter.is_finished());
+    }
+
+    #[test]
+    fn missing_textures_fail_to_load() {
+        let config = EmitterConfig { texture: Some("does/not/exist.png".to_string()), ..EmitterConfig::default() };
+        assert!(ParticleEmitter::from_config(config).is_none());
+        assert!(ParticleEmitter::from_config(EmitterConfig::default()).is_some());
+    }
+}
@@ -1,18 +1,39 @@
 struct VertexInput {
   @location(0) position: vec3<f32>,
   @location(1) uv: vec2<f32>,
+  @location(2) color: vec4<f32>,
+};
+
+// per instance data for particles, see ParticleInstance
+struct ParticleInput {
+  @location(3) center: vec2<f32>,
+  @location(4) size: vec2<f32>,
+  @location(5) rotation: f32,
+  @location(6) color: vec4<f32>,
 };
 
 struct VertexOutput {
   @builtin(position) position: vec4<f32>,
   @location(1) uv: vec2<f32>,
+  @location(2) local: vec2<f32>,
+  @location(3) color: vec4<f32>,
 };
 
 struct DrawUniforms {
   transform: mat4x4<f32>,
-  color: vec4<f32>
+  color: vec4<f32>,
+  paint_kind: u32,
+  spread: u32,
+  stop_count: u32,
+  depth: f32,
+  points: vec4<f32>,
+  params: vec4<f32>,
+  stop_colors: array<vec4<f32>, 8>,
+  stop_offsets: array<vec4<f32>, 2>,
 };
 
+const TAU: f32 = 6.283185307179586;
+
 @group(0) @binding(0)
 var<uniform> draw_uniforms: DrawUniforms;
 
@@ -26,11 +47,90 @@ var s_diffuse: sampler;
 fn vs_main(input: VertexInput) -> VertexOutput {
   var output: VertexOutput;
   output.position = draw_uniforms.transform * vec4<f32>(input.position, 1.0);
+  // depth comes from the z rank of the draw, not from the mesh
+  output.position.z = draw_uniforms.depth * output.position.w;
+  output.uv = input.uv;
+  output.local = input.position.xy;
+  output.color = input.color;
+  return output;
+}
+
+// places a unit quad corner for one particle, then carries on as vs_main does
+@vertex
+fn vs_particle(input: VertexInput, particle: ParticleInput) -> VertexOutput {
+  var output: VertexOutput;
+  let corner = input.position.xy * particle.size;
+  let c = cos(particle.rotation);
+  let s = sin(particle.rotation);
+  let local = vec2<f32>(corner.x * c - corner.y * s, corner.x * s + corner.y * c) + particle.center;
+  output.position = draw_uniforms.transform * vec4<f32>(local, 0.0, 1.0);
+  output.position.z = draw_uniforms.depth * output.position.w;
   output.uv = input.uv;
+  output.local = local;
+  output.color = input.color * particle.color;
   return output;
 }
 
+// maps t onto 0..1 with pad, repeat or reflect
+fn apply_spread(t: f32) -> f32 {
+  if draw_uniforms.spread == 1u {
+    return fract(t);
+  }
+  if draw_uniforms.spread == 2u {
+    return 1.0 - abs(fract(t * 0.5) * 2.0 - 1.0);
+  }
+  return clamp(t, 0.0, 1.0);
+}
+
+fn stop_offset(i: u32) -> f32 {
+  return draw_uniforms.stop_offsets[i / 4u][i % 4u];
+}
+
+fn gradient_color(t: f32) -> vec4<f32> {
+  let count = draw_uniforms.stop_count;
+  if t <= stop_offset(0u) {
+    return draw_uniforms.stop_colors[0];
+  }
+  for (var i = 1u; i < count; i++) {
+    let start = stop_offset(i - 1u);
+    let end = stop_offset(i);
+    if t <= end {
+      let f = clamp((t - start) / max(end - start, 1e-6), 0.0, 1.0);
+      return mix(draw_uniforms.stop_colors[i - 1u], draw_uniforms.stop_colors[i], f);
+    }
+  }
+  return draw_uniforms.stop_colors[count - 1u];
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return draw_uniforms.color + textureSample(t_diffuse, s_diffuse, in.uv);
-}
\ No newline at end of file
+  let points = draw_uniforms.points;
+  var paint = draw_uniforms.color;
+  var uv = in.uv;
+
+  switch draw_uniforms.paint_kind {
+    case 1u: {
+      let axis = points.zw - points.xy;
+      let t = dot(in.local - points.xy, axis) / max(dot(axis, axis), 1e-12);
+      paint = gradient_color(apply_spread(t));
+    }
+    case 2u: {
+      let t = length(in.local - points.xy) / max(draw_uniforms.params.x, 1e-6);
+      paint = gradient_color(apply_spread(t));
+    }
+    case 3u: {
+      let d = in.local - points.xy;
+      // one turn from the start angle covers 0 to 1, whichever side of it atan2 lands on
+      let t = fract((atan2(d.y, d.x) - draw_uniforms.params.x) / TAU);
+      paint = gradient_color(apply_spread(t));
+    }
+    case 4u: {
+      let tile = (in.local - points.xy) / max(points.zw, vec2<f32>(1e-6));
+      uv = vec2<f32>(apply_spread(tile.x), apply_spread(tile.y));
+    }
+    default: {}
+  }
+
+  // no mip maps, so sampling level 0 keeps this valid outside uniform control flow
+  return paint * in.color * textureSampleLevel(t_diffuse, s_diffuse, uv, 0.0);
+}
@@ -0,0 +1,639 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use glam::Vec2;
+use log::warn;
+use serde::Deserialize;
+use crate::graphics::draw::{CoordinateSpace, Image, Rect, Renderer, Transform2D};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SpriteFrame {
+    // in image pixels from the top left corner
+    pub region: Rect,
+    // seconds
+    pub duration: f32,
+    // where the trimmed region sits relative to the center of the untrimmed frame, in image pixels with y down
+    pub offset: Vec2
+}
+
+impl SpriteFrame {
+    pub fn new(region: Rect, duration: f32) -> Self {
+        Self { region, duration, offset: Vec2::ZERO }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum PlayMode {
+    // stops on the last frame
+    Once,
+    #[default]
+    Loop,
+    // runs forward then backward without repeating the end frames
+    PingPong
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimationClip {
+    pub name: String,
+    pub frames: Vec<SpriteFrame>,
+    pub mode: PlayMode
+}
+
+impl AnimationClip {
+
+    pub fn new(name: &str, frames: Vec<SpriteFrame>, mode: PlayMode) -> Self {
+        Self { name: name.to_string(), frames, mode }
+    }
+
+    // frames laid out left to right then top to bottom in equally sized cells
+    pub fn from_grid(name: &str, frame_size: Vec2, columns: u32, count: u32, duration: f32, mode: PlayMode) -> Self {
+        let columns = columns.max(1);
+        let frames = (0..count)
+            .map(|i| {
+                let min = Vec2::new((i % columns) as f32, (i / columns) as f32) * frame_size;
+                SpriteFrame::new(Rect::new(min, min + frame_size), duration)
+            })
+            .collect();
+        Self::new(name, frames, mode)
+    }
+
+    pub fn with_mode(mut self, mode: PlayMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    // one pass through the frames, a ping pong cycle takes close to twice as long
+    pub fn duration(&self) -> f32 {
+        self.frames.iter().map(|frame| frame.duration).sum()
+    }
+
+}
+
+// an image with named clips, usually loaded from an exported sheet description
+#[derive(Clone, Debug)]
+pub struct SpriteSheet {
+    pub image: Arc<Image>,
+    pub clips: HashMap<String, AnimationClip>
+}
+
+// frame data shared by aseprite and texture packer json exports
+#[derive(Deserialize)]
+struct JsonRect {
+    x: f32,
+    y: f32,
+    w: f32,
+    h: f32
+}
+
+#[derive(Deserialize)]
+struct JsonSize {
+    w: f32,
+    h: f32
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonFrame {
+    #[serde(default)]
+    filename: String,
+    frame: JsonRect,
+    #[serde(default)]
+    rotated: bool,
+    #[serde(default)]
+    trimmed: bool,
+    sprite_source_size: Option<JsonRect>,
+    source_size: Option<JsonSize>,
+    // milliseconds, only aseprite writes it
+    duration: Option<f32>
+}
+
+// hash exports key frames by file name, array exports carry it inside
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonFrames {
+    Hash(serde_json::Map<String, serde_json::Value>),
+    Array(Vec<JsonFrame>)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonTag {
+    name: String,
+    from: usize,
+    to: usize,
+    #[serde(default)]
+    direction: String,
+    repeat: Option<String>
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct JsonMeta {
+    image: Option<String>,
+    #[serde(default)]
+    frame_tags: Vec<JsonTag>
+}
+
+#[derive(Deserialize)]
+struct JsonSheet {
+    frames: JsonFrames,
+    #[serde(default)]
+    meta: JsonMeta
+}
+
+impl SpriteSheet {
+
+    // frames without a duration in the file, such as texture packer's, last this long
+    pub const DEFAULT_FRAME_DURATION: f32 = 0.1;
+
+    pub fn new(image: Arc<Image>) -> Self {
+        Self { image, clips: HashMap::new() }
+    }
+
+    pub fn add_clip(&mut self, clip: AnimationClip) -> &mut Self {
+        self.clips.insert(clip.name.clone(), clip);
+        self
+    }
+
+    pub fn clip(&self, name: &str) -> Option<&AnimationClip> {
+        self.clips.get(name)
+    }
+
+    pub fn animate(&self, name: &str) -> Option<AnimatedSprite> {
+        let Some(clip) = self.clip(name) else {
+            warn!("Sprite sheet {} has no clip named {}", self.image.path, name);
+            return None;
+        };
+        Some(AnimatedSprite::new(self.image.clone(), clip.clone()))
+    }
+
+    // loads an aseprite or texture packer json export, the image is loaded from the path in its meta
+    // section relative to the json file
+    pub fn from_file(path: &str) -> Option<Self> {
+        let json = match std::fs::read_to_string(path) {
+            Ok(json) => json,
+            Err(error) => {
+                warn!("Failed to read sprite sheet {}: {}", path, error);
+                return None;
+            }
+        };
+        let image = match Self::parse(&json)?.meta.image {
+            Some(image) => std::path::Path::new(path).with_file_name(image),
+            None => {
+                warn!("Sprite sheet {} does not name its image", path);
+                return None;
+            }
+        };
+        let image = Arc::new(Image::load(&image.to_string_lossy())?);
+        Self::from_json(&json, image)
+    }
+
+    // aseprite tags become clips, without tags frames are grouped into clips by name with the trailing
+    // frame number and extension removed, so walk_01.png and walk_02.png make the clip walk
+    pub fn from_json(json: &str, image: Arc<Image>) -> Option<Self> {
+        let sheet = Self::parse(json)?;
+        let frames: Vec<JsonFrame> = match sheet.frames {
+            JsonFrames::Array(frames) => frames,
+            JsonFrames::Hash(map) => map.into_iter()
+                .filter_map(|(filename, value)| match serde_json::from_value::<JsonFrame>(value) {
+                    Ok(frame) => Some(JsonFrame { filename, ..frame }),
+                    Err(error) => {
+                        warn!("Skipping sprite sheet frame {}: {}", filename, error);
+                        None
+                    }
+                })
+                .collect()
+        };
+
+        let names: Vec<String> = frames.iter().map(|frame| frame.filename.clone()).collect();
+        let frames: Vec<SpriteFrame> = frames.into_iter().map(Self::convert_frame).collect();
+        let mut result = Self::new(image);
+
+        if sheet.meta.frame_tags.is_empty() {
+            let mut order: Vec<String> = Vec::new();
+            let mut groups: HashMap<String, Vec<SpriteFrame>> = HashMap::new();
+            for (name, frame) in names.iter().zip(frames.iter()) {
+                let name = Self::clip_name(name);
+                if !groups.contains_key(&name) {
+                    order.push(name.clone());
+                }
+                groups.entry(name).or_default().push(*frame);
+            }
+            for name in order {
+                let frames = groups.remove(&name).unwrap_or_default();
+                result.add_clip(AnimationClip::new(&name, frames, PlayMode::Loop));
+            }
+        }
+
+        for tag in sheet.meta.frame_tags {
+            if tag.from > tag.to || tag.to >= frames.len() {
+                warn!("Sprite sheet tag {} covers frames {} to {} out of {}", tag.name, tag.from, tag.to, frames.len());
+                continue;
+            }
+            let mut clip_frames = frames[tag.from..=tag.to].to_vec();
+            let mode = match tag.direction.as_str() {
+                "pingpong" => PlayMode::PingPong,
+                "reverse" => {
+                    clip_frames.reverse();
+                    PlayMode::Loop
+                }
+                "pingpong_reverse" => {
+                    clip_frames.reverse();
+                    PlayMode::PingPong
+                }
+                _ => PlayMode::Loop
+            };
+            // aseprite only bounds the repeat count for playback, one repeat is the only count kept here
+            let mode = if tag.repeat.as_deref() == Some("1") { PlayMode::Once } else { mode };
+            result.add_clip(AnimationClip::new(&tag.name, clip_frames, mode));
+        }
+
+        Some(result)
+    }
+
+    fn parse(json: &str) -> Option<JsonSheet> {
+        match serde_json::from_str(json) {
+            Ok(sheet) => Some(sheet),
+            Err(error) => {
+                warn!("Failed to parse sprite sheet: {}", error);
+                None
+            }
+        }
+    }
+
+    fn convert_frame(frame: JsonFrame) -> SpriteFrame {
+        if frame.rotated {
+            warn!("Sprite sheet frame {} is rotated, rotated packing is not supported", frame.filename);
+        }
+        let region = Rect::new(Vec2::new(frame.frame.x, frame.frame.y), Vec2::new(frame.frame.x + frame.frame.w, frame.frame.y + frame.frame.h));
+        let duration = frame.duration.map(|ms| ms / 1000.0).unwrap_or(Self::DEFAULT_FRAME_DURATION);
+
+        // trimmed frames are drawn where they sat in the untrimmed one
+        let offset = match (frame.trimmed, frame.sprite_source_size, frame.source_size) {
+            (true, Some(trim), Some(source)) => {
+                Vec2::new(trim.x + trim.w * 0.5, trim.y + trim.h * 0.5) - Vec2::new(source.w, source.h) * 0.5
+            }
+            _ => Vec2::ZERO
+        };
+        SpriteFrame { region, duration, offset }
+    }
+
+    fn clip_name(filename: &str) -> String {
+        let stem = filename.rsplit_once('.').map(|(stem, _)| stem).unwrap_or(filename);
+        let name = stem.trim_end_matches(|c: char| c.is_ascii_digit()).trim_end_matches(['_', '-', ' ']);
+        if name.is_empty() { stem.to_string() } else { name.to_string() }
+    }
+
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AnimationEvent {
+    FrameChanged { clip: String, frame: usize },
+    // a looping or ping pong clip started over
+    Looped { clip: String },
+    Finished { clip: String }
+}
+
+// plays a clip from an image, advance it from on_update and draw it from on_draw
+#[derive(Clone, Debug)]
+pub struct AnimatedSprite {
+    image: Arc<Image>,
+    clip: AnimationClip,
+    frame: usize,
+    elapsed: f32,
+    speed: f32,
+    playing: bool,
+    finished: bool,
+    // ping pong direction
+    forward: bool,
+    events: Vec<AnimationEvent>
+}
+
+impl AnimatedSprite {
+
+    pub fn new(image: Arc<Image>, clip: AnimationClip) -> Self {
+        Self {
+            image,
+            clip,
+            frame: 0,
+            elapsed: 0.0,
+            speed: 1.0,
+            playing: true,
+            finished: false,
+            forward: true,
+            events: Vec::new()
+        }
+    }
+
+    // switches to another clip from the start, playing the current clip again restarts it
+    pub fn play(&mut self, clip: AnimationClip) -> &mut Self {
+        self.clip = clip;
+        self.restart()
+    }
+
+    pub fn restart(&mut self) -> &mut Self {
+        self.frame = 0;
+        self.elapsed = 0.0;
+        self.playing = true;
+        self.finished = false;
+        self.forward = true;
+        self.events.push(AnimationEvent::FrameChanged { clip: self.clip.name.clone(), frame: 0 });
+        self
+    }
+
+    pub fn pause(&mut self) -> &mut Self {
+        self.playing = false;
+        self
+    }
+
+    pub fn resume(&mut self) -> &mut Self {
+        self.playing = !self.finished;
+        self
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    // multiplies the frame durations' playback rate, 2.0 plays twice as fast
+    pub fn set_speed(&mut self, speed: f32) -> &mut Self {
+        if speed < 0.0 {
+            warn!("Animation speed {} is negative, use a reversed clip to play backwards", speed);
+        }
+        self.speed = speed.max(0.0);
+        self
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn clip(&self) -> &AnimationClip {
+        &self.clip
+    }
+
+    pub fn frame_index(&self) -> usize {
+        self.frame
+    }
+
+    pub fn frame(&self) -> Option<&SpriteFrame> {
+        self.clip.frames.get(self.frame)
+    }
+
+    pub fn image(&self) -> &Arc<Image> {
+        &self.image
+    }
+
+    // advances by delta seconds, as passed to on_update
+    pub fn update(&mut self, delta: f32) {
+        // a clip without any time in it would never leave the loop below
+        if !self.playing || self.clip.duration() <= 0.0 {
+            return;
+        }
+
+        let step = delta * self.speed;
+        if !step.is_finite() {
+            warn!("Ignoring an animation update of {} seconds", step);
+            return;
+        }
+
+        self.elapsed += step;
+        // whole cycles end where they started, so a long delta skips them instead of stepping through every frame
+        let cycle = self.cycle_duration();
+        if self.clip.mode != PlayMode::Once && self.elapsed >= cycle {
+            self.elapsed %= cycle;
+            self.events.push(AnimationEvent::Looped { clip: self.clip.name.clone() });
+        }
+        while self.playing {
+            let duration = self.clip.frames[self.frame].duration;
+            if self.elapsed < duration {
+                break;
+            }
+            self.elapsed -= duration;
+            self.advance();
+        }
+    }
+
+    // time to come back to the same frame heading the same way
+    fn cycle_duration(&self) -> f32 {
+        let frames = &self.clip.frames;
+        match (self.clip.mode, frames.first(), frames.last()) {
+            (PlayMode::PingPong, Some(first), Some(last)) if frames.len() > 1 => self.clip.duration() * 2.0 - first.duration - last.duration,
+            _ => self.clip.duration()
+        }
+    }
+
+    fn advance(&mut self) {
+        let count = self.clip.frames.len();
+        let previous = self.frame;
+        match self.clip.mode {
+            PlayMode::Once => {
+                if self.frame + 1 < count {
+                    self.frame += 1;
+                } else {
+                    self.playing = false;
+                    self.finished = true;
+                    self.elapsed = 0.0;
+                    self.events.push(AnimationEvent::Finished { clip: self.clip.name.clone() });
+                }
+            }
+            PlayMode::Loop => {
+                self.frame = (self.frame + 1) % count;
+                if self.frame == 0 {
+                    self.events.push(AnimationEvent::Looped { clip: self.clip.name.clone() });
+                }
+            }
+            PlayMode::PingPong => {
+                if count > 1 {
+                    if self.forward && self.frame + 1 == count || !self.forward && self.frame == 0 {
+                        self.forward = !self.forward;
+                    }
+                    self.frame = if self.forward { self.frame + 1 } else { self.frame - 1 };
+                    if self.frame == 0 {
+                        self.events.push(AnimationEvent::Looped { clip: self.clip.name.clone() });
+                    }
+                }
+            }
+        }
+        if self.frame != previous {
+            self.events.push(AnimationEvent::FrameChanged { clip: self.clip.name.clone(), frame: self.frame });
+        }
+    }
+
+    // events since the last call, in the order they happened
+    pub fn drain_events(&mut self) -> impl Iterator<Item = AnimationEvent> + '_ {
+        self.events.drain(..)
+    }
+
+    pub fn draw(&self, renderer: &mut Renderer, transform: Transform2D) {
+        let Some(frame) = self.frame() else {
+            return;
+        };
+
+        let mut offset = frame.offset * renderer.logical_pixel();
+        if renderer.coordinate_space() == CoordinateSpace::Normalized {
+            offset.y = -offset.y;
+        }
+        renderer.with_transform(transform, |renderer| {
+            renderer.draw_image_region(Transform2D::at(offset.x, offset.y), self.image.clone(), frame.region);
+        });
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::draw::Color;
+
+    fn sprite(count: u32, mode: PlayMode) -> AnimatedSprite {
+        let clip = AnimationClip::from_grid("walk", Vec2::splat(16.0), 4, count, 0.1, mode);
+        let mut sprite = AnimatedSprite::new(Arc::new(Image::single_pixel(Color::WHITE)), clip);
+        sprite.drain_events().for_each(drop);
+        sprite
+    }
+
+    // the frame after each of a number of 0.1 second updates, nudged past the boundary against rounding
+    fn frames(sprite: &mut AnimatedSprite, updates: usize) -> Vec<usize> {
+        (0..updates).map(|_| {
+            sprite.update(0.1 + 1e-4);
+            sprite.frame_index()
+        }).collect()
+    }
+
+    #[test]
+    fn once_stops_on_the_last_frame() {
+        let mut sprite = sprite(3, PlayMode::Once);
+        sprite.update(0.05);
+        assert_eq!(sprite.frame_index(), 0);
+        assert_eq!(frames(&mut sprite, 4), vec![1, 2, 2, 2]);
+        assert!(sprite.is_finished() && !sprite.is_playing());
+        let events: Vec<_> = sprite.drain_events().collect();
+        assert_eq!(events, vec![
+            AnimationEvent::FrameChanged { clip: "walk".to_string(), frame: 1 },
+            AnimationEvent::FrameChanged { clip: "walk".to_string(), frame: 2 },
+            AnimationEvent::Finished { clip: "walk".to_string() }
+        ]);
+
+        sprite.resume();
+        assert!(!sprite.is_playing());
+        sprite.restart();
+        assert!(sprite.is_playing() && sprite.frame_index() == 0);
+    }
+
+    #[test]
+    fn loop_starts_over() {
+        let mut sprite = sprite(3, PlayMode::Loop);
+        assert_eq!(frames(&mut sprite, 5), vec![1, 2, 0, 1, 2]);
+        let looped = sprite.drain_events().filter(|event| matches!(event, AnimationEvent::Looped { .. })).count();
+        assert_eq!(looped, 1);
+    }
+
+    #[test]
+    fn ping_pong_turns_around_without_repeating_the_ends() {
+        let mut sprite = sprite(3, PlayMode::PingPong);
+        assert_eq!(frames(&mut sprite, 6), vec![1, 2, 1, 0, 1, 2]);
+        let mut single = self::sprite(1, PlayMode::PingPong);
+        assert_eq!(frames(&mut single, 2), vec![0, 0]);
+    }
+
+    #[test]
+    fn speed_scales_and_pausing_stops_the_clock() {
+        let mut sprite = sprite(4, PlayMode::Loop);
+        sprite.set_speed(2.0);
+        sprite.update(0.1 + 1e-4);
+        assert_eq!(sprite.frame_index(), 2);
+        sprite.set_speed(-1.0);
+        assert_eq!(sprite.speed(), 0.0);
+        sprite.set_speed(1.0);
+        sprite.pause();
+        sprite.update(1.0);
+        assert_eq!(sprite.frame_index(), 2);
+        sprite.resume();
+        sprite.update(0.1 + 1e-4);
+        assert_eq!(sprite.frame_index(), 3);
+    }
+
+    #[test]
+    fn bad_and_huge_deltas_do_not_spin() {
+        for mode in [PlayMode::Loop, PlayMode::PingPong] {
+            let mut sprite = sprite(3, mode);
+            sprite.update(f32::NAN);
+            sprite.update(f32::INFINITY);
+            assert_eq!(sprite.frame_index(), 0);
+
+            // a million whole cycles and a bit lands a frame on, as if only the bit had passed
+            let cycle = if mode == PlayMode::Loop { 0.3 } else { 0.4 };
+            sprite.update(cycle * 1e6 + 0.15);
+            assert_eq!(sprite.frame_index(), 1, "{mode:?}");
+            assert!(sprite.drain_events().count() < 4);
+        }
+    }
+
+    const ARRAY: &str = r#"{
+        "frames": [
+            { "filename": "walk_01.png", "frame": { "x": 0, "y": 0, "w": 16, "h": 16 } },
+            { "filename": "walk_02.png", "frame": { "x": 16, "y": 0, "w": 16, "h": 16 } },
+            { "filename": "jump.png", "frame": { "x": 32, "y": 0, "w": 8, "h": 12 }, "trimmed": true,
+              "spriteSourceSize": { "x": 4, "y": 4, "w": 8, "h": 12 }, "sourceSize": { "w": 16, "h": 16 } }
+        ],
+        "meta": { "image": "sheet.png" }
+    }"#;
+
+    const HASH: &str = r#"{
+        "frames": {
+            "hero 0.aseprite": { "frame": { "x": 0, "y": 0, "w": 16, "h": 16 }, "duration": 100 },
+            "hero 1.aseprite": { "frame": { "x": 16, "y": 0, "w": 16, "h": 16 }, "duration": 200 },
+            "hero 2.aseprite": { "frame": { "x": 32, "y": 0, "w": 16, "h": 16 }, "duration": 300 }
+        },
+        "meta": { "frameTags": [
+            { "name": "idle", "from": 0, "to": 1, "direction": "forward" },
+            { "name": "back", "from": 0, "to": 2, "direction": "reverse" },
+            { "name": "bob", "from": 1, "to": 2, "direction": "pingpong" },
+            { "name": "once", "from": 2, "to": 2, "direction": "forward", "repeat": "1" },
+            { "name": "broken", "from": 2, "to": 5 }
+        ] }
+    }"#;
+
+    fn image() -> Arc<Image> {
+        Arc::new(Image::single_pixel(Color::WHITE))
+    }
+
+    #[test]
+    fn array_exports_group_frames_by_name() {
+        let sheet = SpriteSheet::from_json(ARRAY, image()).unwrap();
+        let walk = sheet.clip("walk").unwrap();
+        assert_eq!(walk.frames.len(), 2);
+        assert_eq!(walk.frames[1].region, Rect::new(Vec2::new(16.0, 0.0), Vec2::new(32.0, 16.0)));
+        assert_eq!(walk.frames[0].duration, SpriteSheet::DEFAULT_FRAME_DURATION);
+        // the trimmed 8x12 region sat at 4,4 in its 16x16 frame, so its center is right and down of the middle
+        assert_eq!(sheet.clip("jump").unwrap().frames[0].offset, Vec2::new(0.0, 2.0));
+    }
+
+    #[test]
+    fn hash_exports_turn_tags_into_clips() {
+        let sheet = SpriteSheet::from_json(HASH, image()).unwrap();
+        assert_eq!(sheet.clips.len(), 4);
+        let idle = sheet.clip("idle").unwrap();
+        assert_eq!((idle.mode, idle.frames.len()), (PlayMode::Loop, 2));
+        assert!((idle.duration() - 0.3).abs() < 1e-6);
+        let back = sheet.clip("back").unwrap();
+        assert_eq!(back.frames.iter().map(|frame| frame.region.min.x).collect::<Vec<_>>(), vec![32.0, 16.0, 0.0]);
+        assert_eq!(sheet.clip("bob").unwrap().mode, PlayMode::PingPong);
+        assert_eq!(sheet.clip("once").unwrap().mode, PlayMode::Once);
+        assert!(sheet.clip("broken").is_none());
+        assert!(sheet.animate("missing").is_none());
+        assert!(SpriteSheet::from_json("{}", image()).is_none());
+    }
+
+    #[test]
+    fn sheets_with_missing_images_fail_to_load() {
+        let path = std::env::temp_dir().join(format!("rgraphics_sheet_{}.json", std::process::id()));
+        std::fs::write(&path, ARRAY).unwrap();
+        assert!(SpriteSheet::from_file(&path.to_string_lossy()).is_none());
+        assert!(SpriteSheet::from_file("does/not/exist.json").is_none());
+    }
+}
@@ -0,0 +1,286 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use ab_glyph::{Font as _, FontArc, PxScale, ScaleFont};
+use glam::Vec2;
+use image::{DynamicImage, Rgba, RgbaImage};
+use log::warn;
+use crate::graphics::draw::{Image, Rect};
+use crate::graphics::gpu::Vertex;
+use crate::graphics::mesh::{Indices, Mesh};
+
+static NEXT_FONT: AtomicU64 = AtomicU64::new(0);
+
+const ATLAS_WIDTH: u32 = 512;
+// wgpu's default max_texture_dimension_2d, which the renderer asks for
+const MAX_ATLAS_SIDE: u32 = 8192;
+// larger sizes stretch glyphs rasterized at this size, which keeps every atlas well inside MAX_ATLAS_SIDE
+const MAX_PIXEL_SIZE: u32 = 128;
+// drawn for characters outside the atlas
+const REPLACEMENT: char = '?';
+
+// printable ascii and latin-1, rasterized together the first time a size is drawn
+fn atlas_chars() -> impl Iterator<Item = char> {
+    (' '..='~').chain('\u{a0}'..='\u{ff}')
+}
+
+#[derive(Copy, Clone, Debug)]
+struct AtlasGlyph {
+    // in atlas pixels
+    region: Rect,
+    // from the pen position on the baseline to the bitmap's top left corner
+    offset: Vec2
+}
+
+// the glyphs of one pixel size packed into one image, never changed once built so it uploads once
+#[derive(Debug)]
+struct GlyphAtlas {
+    image: Arc<Image>,
+    glyphs: HashMap<char, AtlasGlyph>
+}
+
+// a truetype or opentype font, text is laid out in logical pixels with y down
+pub struct Font {
+    font: FontArc,
+    id: u64,
+    atlases: Mutex<HashMap<u32, Arc<GlyphAtlas>>>
+}
+
+impl std::fmt::Debug for Font {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Font").field("id", &self.id).finish()
+    }
+}
+
+impl Default for Font {
+    // Ubuntu Light, bundled so there is always something to draw text with
+    fn default() -> Self {
+        Self::from_bytes(epaint_default_fonts::UBUNTU_LIGHT.to_vec()).expect("the bundled font is valid")
+    }
+}
+
+impl Font {
+
+    pub fn from_bytes(data: Vec<u8>) -> Option<Self> {
+        match FontArc::try_from_vec(data) {
+            Ok(font) => Some(Self { font, id: NEXT_FONT.fetch_add(1, Ordering::Relaxed), atlases: Mutex::new(HashMap::new()) }),
+            Err(error) => {
+                warn!("Failed to parse font: {}", error);
+                None
+            }
+        }
+    }
+
+    pub fn from_file(path: &str) -> Option<Self> {
+        match std::fs::read(path) {
+            Ok(data) => Self::from_bytes(data),
+            Err(error) => {
+                warn!("Failed to read font {}: {}", path, error);
+                None
+            }
+        }
+    }
+
+    // distance between the baselines of two lines
+    pub fn line_height(&self, size: f32) -> f32 {
+        let font = self.font.as_scaled(PxScale::from(size));
+        font.ascent() - font.descent() + font.line_gap()
+    }
+
+    // width of the widest line and height of all of them
+    pub fn measure(&self, text: &str, size: f32) -> Vec2 {
+        let lines = text.split('\n').collect::<Vec<_>>();
+        let width = lines.iter().map(|line| self.offsets(line, size).last().copied().unwrap_or_default()).fold(0.0, f32::max);
+        Vec2::new(width, lines.len() as f32 * self.line_height(size))
+    }
+
+    // x of the pen before each character of a single line, with one more entry for the end of the line
+    pub fn offsets(&self, line: &str, size: f32) -> Vec<f32> {
+        let font = self.font.as_scaled(PxScale::from(size));
+        let mut offsets = Vec::with_capacity(line.len() + 1);
+        let mut x = 0.0;
+        let mut previous = None;
+        for c in line.chars() {
+            let id = font.glyph_id(c);
+            if let Some(previous) = previous {
+                x += font.kern(previous, id);
+            }
+            offsets.push(x);
+            x += font.h_advance(id);
+            previous = Some(id);
+        }
+        offsets.push(x);
+        offsets
+    }
+
+    fn atlas(&self, pixel_size: u32) -> Arc<GlyphAtlas> {
+        let mut atlases = self.atlases.lock().unwrap();
+        atlases.entry(pixel_size).or_insert_with(|| Arc::new(self.build_atlas(pixel_size))).clone()
+    }
+
+    fn build_atlas(&self, pixel_size: u32) -> GlyphAtlas {
+        let font = self.font.as_scaled(PxScale::from(pixel_size as f32));
+        let outlines: Vec<_> = atlas_chars()
+            .filter_map(|c| font.outline_glyph(font.scaled_glyph(c)).map(|outline| (c, outline)))
+            .collect();
+
+        let sizes: Vec<(u32, u32)> = outlines.iter()
+            .map(|(_, outline)| (outline.px_bounds().width().ceil() as u32, outline.px_bounds().height().ceil() as u32))
+            .collect();
+        // widens the atlas until it is about square, so large sizes do not make it too tall to upload
+        let widest = sizes.iter().map(|(width, _)| width + 2).max().unwrap_or_default();
+        let mut width = ATLAS_WIDTH.max(widest.next_power_of_two());
+        let (mut positions, mut height) = Self::pack(&sizes, width);
+        while height > width && width < MAX_ATLAS_SIDE {
+            width *= 2;
+            (positions, height) = Self::pack(&sizes, width);
+        }
+        if height > MAX_ATLAS_SIDE {
+            warn!("Glyph atlas for size {} is {}x{}, larger than most gpus allow", pixel_size, width, height);
+        }
+
+        let mut atlas = RgbaImage::from_pixel(width, height, Rgba([255, 255, 255, 0]));
+        let mut glyphs = HashMap::with_capacity(outlines.len());
+        for ((c, outline), (x, y)) in outlines.iter().zip(positions) {
+            outline.draw(|gx, gy, coverage| {
+                if x + gx < width && y + gy < height {
+                    atlas.put_pixel(x + gx, y + gy, Rgba([255, 255, 255, (coverage * 255.0).round() as u8]));
+                }
+            });
+            let bounds = outline.px_bounds();
+            let min = Vec2::new(x as f32, y as f32);
+            glyphs.insert(*c, AtlasGlyph {
+                region: Rect::new(min, min + Vec2::new(bounds.width(), bounds.height())),
+                // the outline was placed with its pen at the origin on the baseline
+                offset: Vec2::new(bounds.min.x, bounds.min.y)
+            });
+        }
+
+        GlyphAtlas {
+            image: Arc::new(Image { path: format!("font_{}_{}", self.id, pixel_size), image: DynamicImage::ImageRgba8(atlas) }),
+            glyphs
+        }
+    }
+
+    // shelves of glyphs left to right, a pixel apart so filtering never bleeds between them, returns where each
+    // one goes and the height of the atlas
+    fn pack(sizes: &[(u32, u32)], atlas_width: u32) -> (Vec<(u32, u32)>, u32) {
+        let mut positions = Vec::with_capacity(sizes.len());
+        let (mut x, mut y, mut shelf) = (1, 1, 0);
+        for (width, height) in sizes {
+            if x + width + 1 > atlas_width {
+                x = 1;
+                y += shelf + 1;
+                shelf = 0;
+            }
+            positions.push((x, y));
+            x += width + 1;
+            shelf = shelf.max(*height);
+        }
+        (positions, y + shelf + 1)
+    }
+
+    // quads for every glyph with the first line's top left corner at the origin, y down, and the atlas
+    // image they sample, spaces and missing glyphs produce no quads
+    pub fn mesh(&self, text: &str, size: f32) -> (Mesh, Arc<Image>) {
+        // glyphs are rasterized at whole pixel sizes up to MAX_PIXEL_SIZE and stretched the rest of the way
+        let pixel_size = (size.round().max(1.0) as u32).min(MAX_PIXEL_SIZE);
+        let stretch = size / pixel_size as f32;
+        let atlas = self.atlas(pixel_size);
+        let atlas_size = Vec2::new(atlas.image.image.width() as f32, atlas.image.image.height() as f32);
+        let ascent = self.font.as_scaled(PxScale::from(size)).ascent();
+        let line_height = self.line_height(size);
+
+        let mut vertices = Vec::new();
+        let mut indices: Vec<u32> = Vec::new();
+        for (row, line) in text.split('\n').enumerate() {
+            let baseline = row as f32 * line_height + ascent;
+            for (c, x) in line.chars().zip(self.offsets(line, size)) {
+                let Some(glyph) = atlas.glyphs.get(&c).or(atlas.glyphs.get(&REPLACEMENT)) else {
+                    continue;
+                };
+                if c.is_whitespace() {
+                    continue;
+                }
+                let min = Vec2::new(x, baseline) + glyph.offset * stretch;
+                let max = min + glyph.region.size() * stretch;
+                let base = vertices.len() as u32;
+                for corner in [Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(1.0, 1.0), Vec2::new(0.0, 1.0)] {
+                    let position = min + corner * (max - min);
+                    let uv = (glyph.region.min + corner * glyph.region.size()) / atlas_size;
+                    vertices.push(Vertex::new([position.x, position.y, 0.0], uv.into()));
+                }
+                indices.extend([base, base + 1, base + 2, base, base + 2, base + 3]);
+            }
+        }
+
+        (Mesh::new(vertices, Indices::compact(indices)), atlas.image.clone())
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quads(mesh: &Mesh) -> usize {
+        mesh.indices.len() / 6
+    }
+
+    fn mesh_bounds(mesh: &Mesh) -> Rect {
+        Rect::bounding(&mesh.vertices.iter().map(|vertex| Vec2::new(vertex.position[0], vertex.position[1])).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn offsets_hold_the_pen_before_each_character_and_at_the_end() {
+        let font = Font::default();
+        let offsets = font.offsets("Hello", 16.0);
+        assert_eq!(offsets.len(), 6);
+        assert_eq!(offsets[0], 0.0);
+        assert!(offsets.windows(2).all(|pair| pair[1] > pair[0]));
+        assert_eq!(font.offsets("", 16.0), vec![0.0]);
+        // advances scale with the size
+        let doubled = font.offsets("Hello", 32.0);
+        assert!((doubled[5] - offsets[5] * 2.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn measure_takes_the_widest_line_and_every_line_height() {
+        let font = Font::default();
+        let line_height = font.line_height(20.0);
+        let short = font.measure("ab", 20.0);
+        let long = font.measure("abcdef", 20.0);
+        assert_eq!(short.y, line_height);
+        let both = font.measure("ab\nabcdef\nab", 20.0);
+        assert_eq!(both, Vec2::new(long.x, line_height * 3.0));
+    }
+
+    #[test]
+    fn mesh_has_a_quad_per_visible_character() {
+        let font = Font::default();
+        let (mesh, atlas) = font.mesh("a b\nc", 16.0);
+        assert_eq!(quads(&mesh), 3);
+        assert!(mesh.vertices.iter().all(|vertex| (0.0..=1.0).contains(&vertex.uv[0]) && (0.0..=1.0).contains(&vertex.uv[1])));
+        // the second line sits a line lower
+        assert!(mesh_bounds(&mesh).max.y > font.line_height(16.0));
+        // characters missing from the atlas fall back to the replacement
+        assert_eq!(quads(&font.mesh("\u{4e2d}", 16.0).0), 1);
+        // the same size shares its atlas
+        assert!(Arc::ptr_eq(&atlas, &font.mesh("xyz", 16.0).1));
+    }
+
+    #[test]
+    fn large_sizes_stretch_a_capped_atlas() {
+        let font = Font::default();
+        let (small, small_atlas) = font.mesh("W", MAX_PIXEL_SIZE as f32);
+        for size in [400.0, 900.0] {
+            let (large, atlas) = font.mesh("W", size);
+            assert!(Arc::ptr_eq(&atlas, &small_atlas));
+            let ratio = mesh_bounds(&large).size() / mesh_bounds(&small).size();
+            assert!((ratio - Vec2::splat(size / MAX_PIXEL_SIZE as f32)).abs().max_element() < 0.01, "{ratio}");
+        }
+        let image = &small_atlas.image;
+        assert!(image.width() <= MAX_ATLAS_SIDE && image.height() <= MAX_ATLAS_SIDE);
+        assert!(image.height() <= image.width());
+    }
+}
@@ -0,0 +1,616 @@
+// imports maps made with the Tiled editor, in its tmx (xml) or json formats
+use std::path::Path;
+use std::sync::Arc;
+use glam::Vec2;
+use log::warn;
+use serde_json::Value;
+use crate::graphics::draw::{Color, Image};
+use crate::graphics::tilemap::{MapLayer, MapObject, ObjectLayer, ObjectShape, Properties, Property, Tile, TileLayer, Tilemap, Tileset};
+
+const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+const FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+// the hexagonal rotation bit is dropped along with the others
+const GID_MASK: u32 = 0x0FFF_FFFF;
+
+// loads a .tmx, .tmj or .json map, tilesets and images are found relative to the map file
+pub fn load(path: &str) -> Option<Tilemap> {
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(error) => {
+            warn!("Failed to read map {}: {}", path, error);
+            return None;
+        }
+    };
+    let directory = Path::new(path).parent().unwrap_or(Path::new(""));
+    if path.ends_with(".tmx") {
+        parse_tmx(&source, directory)
+    } else {
+        parse_json(&source, directory)
+    }
+}
+
+// a Tilemap draws from one tileset, so maps with more than one are rejected
+struct TilesetRef {
+    first_gid: u32,
+    tileset: Tileset
+}
+
+impl TilesetRef {
+    fn tile(&self, gid: u32) -> Option<Tile> {
+        let id = gid & GID_MASK;
+        if id == 0 {
+            return None;
+        }
+        if id < self.first_gid || id - self.first_gid >= self.tileset.tile_count {
+            return None;
+        }
+        Some(Tile {
+            id: id - self.first_gid,
+            flip_x: gid & FLIPPED_HORIZONTALLY != 0,
+            flip_y: gid & FLIPPED_VERTICALLY != 0,
+            flip_diagonal: gid & FLIPPED_DIAGONALLY != 0
+        })
+    }
+}
+
+fn check_map(orientation: &str, infinite: bool, tileset_count: usize) -> bool {
+    if infinite {
+        warn!("Infinite Tiled maps are not supported, resize the map to a fixed size");
+        return false;
+    }
+    if orientation != "orthogonal" {
+        warn!("Tiled map orientation {} is not supported, it will be drawn as orthogonal", orientation);
+    }
+    if tileset_count > 1 {
+        warn!("Tiled map uses {} tilesets, only maps with a single tileset are supported", tileset_count);
+        return false;
+    }
+    true
+}
+
+fn fill_layer(layer: &mut TileLayer, gids: &[u32], tileset: &TilesetRef) {
+    let mut dropped = 0;
+    for (index, gid) in gids.iter().enumerate().take((layer.width() * layer.height()) as usize) {
+        let tile = tileset.tile(*gid);
+        if tile.is_none() && gid & GID_MASK != 0 {
+            dropped += 1;
+        }
+        layer.set_tile(index as u32 % layer.width(), index as u32 / layer.width(), tile);
+    }
+    if dropped > 0 {
+        warn!("Layer {} has {} tiles outside the tileset, they are left empty", layer.name, dropped);
+    }
+}
+
+// "#AARRGGBB" or "#RRGGBB"
+fn parse_color(text: &str) -> Option<Color> {
+    let hex = text.trim_start_matches('#');
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    let channel = |shift: u32| ((value >> shift) & 0xff) as f32 / 255.0;
+    match hex.len() {
+        6 => Some(Color::new(channel(16), channel(8), channel(0), 1.0)),
+        8 => Some(Color::new(channel(16), channel(8), channel(0), channel(24))),
+        _ => None
+    }
+}
+
+fn parse_property(kind: &str, text: &str) -> Property {
+    match kind {
+        "int" => Property::Int(text.parse().unwrap_or_default()),
+        "float" => Property::Float(text.parse().unwrap_or_default()),
+        "bool" => Property::Bool(text == "true"),
+        "color" => Property::Color(parse_color(text).unwrap_or(Color::new(0.0, 0.0, 0.0, 0.0))),
+        "file" => Property::File(text.to_string()),
+        "object" => Property::Object(text.parse().unwrap_or_default()),
+        _ => Property::String(text.to_string())
+    }
+}
+
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in text.bytes().filter(|c| !c.is_ascii_whitespace() && *c != b'=') {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None
+        };
+        buffer = buffer << 6 | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+// strips the gzip header, the deflate stream inside is the same as zlib's without its wrapper
+fn inflate_gzip(data: &[u8]) -> Option<Vec<u8>> {
+    if data.len() < 10 || data[0] != 0x1f || data[1] != 0x8b {
+        return None;
+    }
+    let flags = data[3];
+    let mut position = 10;
+    if flags & 0x04 != 0 {
+        let extra = u16::from_le_bytes([*data.get(position)?, *data.get(position + 1)?]) as usize;
+        position += 2 + extra;
+    }
+    for flag in [0x08, 0x10] {
+        if flags & flag != 0 {
+            position += data.get(position..)?.iter().position(|b| *b == 0)? + 1;
+        }
+    }
+    if flags & 0x02 != 0 {
+        position += 2;
+    }
+    miniz_oxide::inflate::decompress_to_vec(data.get(position..)?).ok()
+}
+
+// tile data stored as base64, optionally compressed, holds little endian gids
+fn decode_gids(text: &str, compression: &str) -> Option<Vec<u32>> {
+    let Some(data) = decode_base64(text) else {
+        warn!("Tile layer data is not valid base64");
+        return None;
+    };
+    let data = match compression {
+        "" => Some(data),
+        "zlib" => miniz_oxide::inflate::decompress_to_vec_zlib(&data).ok(),
+        "gzip" => inflate_gzip(&data),
+        other => {
+            warn!("Tile layer compression {} is not supported, save the map with zlib, gzip or none", other);
+            return None;
+        }
+    };
+    let Some(data) = data else {
+        warn!("Tile layer data could not be decompressed");
+        return None;
+    };
+    Some(data.chunks_exact(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect())
+}
+
+fn load_image(directory: &Path, source: &str) -> Option<Arc<Image>> {
+    Image::load(&directory.join(source).to_string_lossy()).map(Arc::new)
+}
+
+// json
+
+fn get_f32(value: &Value, key: &str) -> f32 {
+    value.get(key).and_then(Value::as_f64).unwrap_or_default() as f32
+}
+
+fn get_u32(value: &Value, key: &str) -> u32 {
+    value.get(key).and_then(Value::as_u64).unwrap_or_default() as u32
+}
+
+fn get_str<'a>(value: &'a Value, key: &str) -> &'a str {
+    value.get(key).and_then(Value::as_str).unwrap_or_default()
+}
+
+fn get_bool(value: &Value, key: &str, default: bool) -> bool {
+    value.get(key).and_then(Value::as_bool).unwrap_or(default)
+}
+
+fn json_properties(value: &Value) -> Properties {
+    let mut properties = Properties::new();
+    for property in value.get("properties").and_then(Value::as_array).into_iter().flatten() {
+        let name = get_str(property, "name").to_string();
+        let kind = get_str(property, "type");
+        let value = &property["value"];
+        let property = match (kind, value) {
+            ("class", Value::Object(members)) => Property::Class(members.iter()
+                .map(|(name, value)| (name.clone(), json_value(value)))
+                .collect()),
+            (_, Value::String(text)) => parse_property(kind, text),
+            _ => parse_property(kind, &value.to_string())
+        };
+        properties.insert(name, property);
+    }
+    properties
+}
+
+// class members carry no type, so it is guessed from the json value
+fn json_value(value: &Value) -> Property {
+    match value {
+        Value::Bool(value) => Property::Bool(*value),
+        Value::Number(number) if number.is_i64() => Property::Int(number.as_i64().unwrap_or_default()),
+        Value::Number(number) => Property::Float(number.as_f64().unwrap_or_default()),
+        Value::String(text) => Property::String(text.clone()),
+        Value::Object(members) => Property::Class(members.iter().map(|(name, value)| (name.clone(), json_value(value))).collect()),
+        _ => Property::String(value.to_string())
+    }
+}
+
+fn json_tileset(value: &Value, directory: &Path) -> Option<TilesetRef> {
+    let first_gid = get_u32(value, "firstgid");
+
+    // external tilesets keep everything but the first gid in their own file
+    let (external, directory) = match value.get("source").and_then(Value::as_str) {
+        Some(source) => {
+            let path = directory.join(source);
+            if source.ends_with(".tsx") {
+                let xml = std::fs::read_to_string(&path).ok()?;
+                let document = roxmltree::Document::parse(&xml).ok()?;
+                let mut tileset = tmx_tileset(document.root_element(), path.parent().unwrap_or(directory))?;
+                tileset.first_gid = first_gid;
+                return Some(tileset);
+            }
+            let json = std::fs::read_to_string(&path).ok()?;
+            (serde_json::from_str::<Value>(&json).ok()?, path.parent().unwrap_or(directory).to_path_buf())
+        }
+        None => (value.clone(), directory.to_path_buf())
+    };
+    let value = &external;
+
+    let Some(image) = value.get("image").and_then(Value::as_str) else {
+        warn!("Tileset {} is a collection of images, only tilesets cut from one image are supported", get_str(value, "name"));
+        return None;
+    };
+    let mut tileset = Tileset::new(load_image(&directory, image)?, Vec2::new(get_f32(value, "tilewidth"), get_f32(value, "tileheight")))
+        .with_margin_and_spacing(get_f32(value, "margin"), get_f32(value, "spacing"));
+    tileset.name = get_str(value, "name").to_string();
+    for tile in value.get("tiles").and_then(Value::as_array).into_iter().flatten() {
+        let properties = json_properties(tile);
+        if !properties.is_empty() {
+            tileset.tile_properties.insert(get_u32(tile, "id"), properties);
+        }
+    }
+    Some(TilesetRef { first_gid, tileset })
+}
+
+fn json_points(value: Option<&Value>) -> Vec<Vec2> {
+    value.and_then(Value::as_array).into_iter().flatten()
+        .map(|point| Vec2::new(get_f32(point, "x"), get_f32(point, "y")))
+        .collect()
+}
+
+fn json_object(value: &Value, tileset: &TilesetRef) -> MapObject {
+    let shape = if get_bool(value, "ellipse", false) {
+        ObjectShape::Ellipse
+    } else if get_bool(value, "point", false) {
+        ObjectShape::Point
+    } else if value.get("polygon").is_some() {
+        ObjectShape::Polygon(json_points(value.get("polygon")))
+    } else if value.get("polyline").is_some() {
+        ObjectShape::Polyline(json_points(value.get("polyline")))
+    } else if let Some(text) = value.get("text") {
+        ObjectShape::Text(get_str(text, "text").to_string())
+    } else {
+        ObjectShape::Rectangle
+    };
+
+    let class = value.get("class").or(value.get("type")).and_then(Value::as_str).unwrap_or_default();
+    MapObject {
+        id: get_u32(value, "id"),
+        name: get_str(value, "name").to_string(),
+        class: class.to_string(),
+        position: Vec2::new(get_f32(value, "x"), get_f32(value, "y")),
+        size: Vec2::new(get_f32(value, "width"), get_f32(value, "height")),
+        rotation: get_f32(value, "rotation").to_radians(),
+        visible: get_bool(value, "visible", true),
+        shape,
+        tile: value.get("gid").and_then(Value::as_u64).and_then(|gid| tileset.tile(gid as u32)),
+        properties: json_properties(value)
+    }
+}
+
+// groups are flattened into the layer list, their offsets and visibility carried down to their children
+fn json_layers(values: &[Value], tileset: &TilesetRef, map: &mut Tilemap, offset: Vec2, visible: bool) {
+    for value in values {
+        let name = get_str(value, "name");
+        let layer_offset = offset + Vec2::new(get_f32(value, "offsetx"), get_f32(value, "offsety"));
+        let layer_visible = visible && get_bool(value, "visible", true);
+
+        match get_str(value, "type") {
+            "tilelayer" => {
+                let gids = match value.get("data") {
+                    Some(Value::Array(data)) => data.iter().map(|gid| gid.as_u64().unwrap_or_default() as u32).collect(),
+                    Some(Value::String(data)) => decode_gids(data, get_str(value, "compression")).unwrap_or_default(),
+                    _ => Vec::new()
+                };
+                let mut layer = TileLayer::new(name, map.width(), map.height());
+                fill_layer(&mut layer, &gids, tileset);
+                layer.visible = layer_visible;
+                layer.opacity = value.get("opacity").and_then(Value::as_f64).unwrap_or(1.0) as f32;
+                layer.offset = layer_offset;
+                layer.properties = json_properties(value);
+                map.layers.push(MapLayer::Tiles(layer));
+            }
+            "objectgroup" => {
+                let mut layer = ObjectLayer::new(name);
+                layer.visible = layer_visible;
+                layer.offset = layer_offset;
+                layer.properties = json_properties(value);
+                layer.objects = value.get("objects").and_then(Value::as_array).into_iter().flatten()
+                    .map(|object| json_object(object, tileset))
+                    .collect();
+                map.layers.push(MapLayer::Objects(layer));
+            }
+            "group" => {
+                let children = value.get("layers").and_then(Value::as_array).map(Vec::as_slice).unwrap_or_default();
+                json_layers(children, tileset, map, layer_offset, layer_visible);
+            }
+            other => warn!("Skipping Tiled layer {} of type {}, it is not supported", name, other)
+        }
+    }
+}
+
+// tilesets and images named in the map are loaded relative to directory
+pub fn parse_json(json: &str, directory: &Path) -> Option<Tilemap> {
+    let map: Value = match serde_json::from_str(json) {
+        Ok(map) => map,
+        Err(error) => {
+            warn!("Failed to parse Tiled map: {}", error);
+            return None;
+        }
+    };
+
+    let tilesets = map.get("tilesets").and_then(Value::as_array).map(Vec::as_slice).unwrap_or_default();
+    if !check_map(get_str(&map, "orientation"), get_bool(&map, "infinite", false), tilesets.len()) {
+        return None;
+    }
+    let Some(tileset) = tilesets.first().and_then(|tileset| json_tileset(tileset, directory)) else {
+        warn!("Tiled map has no usable tileset");
+        return None;
+    };
+
+    let mut tilemap = Tilemap::new(tileset.tileset.clone(), get_u32(&map, "width"), get_u32(&map, "height"))
+        .with_cell_size(Vec2::new(get_f32(&map, "tilewidth"), get_f32(&map, "tileheight")));
+    tilemap.properties = json_properties(&map);
+    let layers = map.get("layers").and_then(Value::as_array).map(Vec::as_slice).unwrap_or_default();
+    json_layers(layers, &tileset, &mut tilemap, Vec2::ZERO, true);
+    Some(tilemap)
+}
+
+// tmx
+
+fn attribute<'a>(node: roxmltree::Node<'a, '_>, name: &str) -> &'a str {
+    node.attribute(name).unwrap_or_default()
+}
+
+fn attribute_f32(node: roxmltree::Node, name: &str) -> f32 {
+    attribute(node, name).parse().unwrap_or_default()
+}
+
+fn attribute_u32(node: roxmltree::Node, name: &str) -> u32 {
+    attribute(node, name).parse().unwrap_or_default()
+}
+
+fn child<'a, 'input>(node: roxmltree::Node<'a, 'input>, name: &str) -> Option<roxmltree::Node<'a, 'input>> {
+    node.children().find(|child| child.has_tag_name(name))
+}
+
+fn tmx_properties(node: roxmltree::Node) -> Properties {
+    let mut properties = Properties::new();
+    let Some(list) = child(node, "properties") else {
+        return properties;
+    };
+    for property in list.children().filter(|child| child.has_tag_name("property")) {
+        let kind = attribute(property, "type");
+        let value = if kind == "class" {
+            Property::Class(tmx_properties(property))
+        } else {
+            // multi line strings are stored as text rather than in the value attribute
+            let text = property.attribute("value").or(property.text()).unwrap_or_default();
+            parse_property(kind, text)
+        };
+        properties.insert(attribute(property, "name").to_string(), value);
+    }
+    properties
+}
+
+fn tmx_tileset(node: roxmltree::Node, directory: &Path) -> Option<TilesetRef> {
+    let first_gid = attribute_u32(node, "firstgid");
+
+    if let Some(source) = node.attribute("source") {
+        let path = directory.join(source);
+        let Ok(xml) = std::fs::read_to_string(&path) else {
+            warn!("Failed to read tileset {}", path.display());
+            return None;
+        };
+        let document = match roxmltree::Document::parse(&xml) {
+            Ok(document) => document,
+            Err(error) => {
+                warn!("Failed to parse tileset {}: {}", path.display(), error);
+                return None;
+            }
+        };
+        let mut tileset = tmx_tileset(document.root_element(), path.parent().unwrap_or(directory))?;
+        tileset.first_gid = first_gid;
+        return Some(tileset);
+    }
+
+    let Some(image) = child(node, "image").and_then(|image| image.attribute("source")) else {
+        warn!("Tileset {} is a collection of images, only tilesets cut from one image are supported", attribute(node, "name"));
+        return None;
+    };
+    let mut tileset = Tileset::new(load_image(directory, image)?, Vec2::new(attribute_f32(node, "tilewidth"), attribute_f32(node, "tileheight")))
+        .with_margin_and_spacing(attribute_f32(node, "margin"), attribute_f32(node, "spacing"));
+    tileset.name = attribute(node, "name").to_string();
+    for tile in node.children().filter(|child| child.has_tag_name("tile")) {
+        let properties = tmx_properties(tile);
+        if !properties.is_empty() {
+            tileset.tile_properties.insert(attribute_u32(tile, "id"), properties);
+        }
+    }
+    Some(TilesetRef { first_gid, tileset })
+}
+
+fn tmx_gids(data: roxmltree::Node) -> Vec<u32> {
+    let text = data.text().unwrap_or_default();
+    match attribute(data, "encoding") {
+        "csv" => text.split(',').map(|gid| gid.trim().parse().unwrap_or_default()).collect(),
+        "base64" => decode_gids(text, attribute(data, "compression")).unwrap_or_default(),
+        // the oldest format has one element per tile
+        _ => data.children().filter(|child| child.has_tag_name("tile")).map(|tile| attribute_u32(tile, "gid")).collect()
+    }
+}
+
+fn tmx_points(node: roxmltree::Node) -> Vec<Vec2> {
+    attribute(node, "points")
+        .split_whitespace()
+        .filter_map(|point| point.split_once(','))
+        .map(|(x, y)| Vec2::new(x.parse().unwrap_or_default(), y.parse().unwrap_or_default()))
+        .collect()
+}
+
+fn tmx_object(node: roxmltree::Node, tileset: &TilesetRef) -> MapObject {
+    let shape = if child(node, "ellipse").is_some() {
+        ObjectShape::Ellipse
+    } else if child(node, "point").is_some() {
+        ObjectShape::Point
+    } else if let Some(polygon) = child(node, "polygon") {
+        ObjectShape::Polygon(tmx_points(polygon))
+    } else if let Some(polyline) = child(node, "polyline") {
+        ObjectShape::Polyline(tmx_points(polyline))
+    } else if let Some(text) = child(node, "text") {
+        ObjectShape::Text(text.text().unwrap_or_default().to_string())
+    } else {
+        ObjectShape::Rectangle
+    };
+
+    MapObject {
+        id: attribute_u32(node, "id"),
+        name: attribute(node, "name").to_string(),
+        class: node.attribute("class").or(node.attribute("type")).unwrap_or_default().to_string(),
+        position: Vec2::new(attribute_f32(node, "x"), attribute_f32(node, "y")),
+        size: Vec2::new(attribute_f32(node, "width"), attribute_f32(node, "height")),
+        rotation: attribute_f32(node, "rotation").to_radians(),
+        visible: attribute(node, "visible") != "0",
+        shape,
+        tile: node.attribute("gid").and_then(|gid| gid.parse().ok()).and_then(|gid| tileset.tile(gid)),
+        properties: tmx_properties(node)
+    }
+}
+
+fn tmx_layers(node: roxmltree::Node, tileset: &TilesetRef, map: &mut Tilemap, offset: Vec2, visible: bool) {
+    for node in node.children().filter(|child| child.is_element()) {
+        let name = attribute(node, "name");
+        let layer_offset = offset + Vec2::new(attribute_f32(node, "offsetx"), attribute_f32(node, "offsety"));
+        let layer_visible = visible && attribute(node, "visible") != "0";
+
+        match node.tag_name().name() {
+            "layer" => {
+                let gids = child(node, "data").map(tmx_gids).unwrap_or_default();
+                let mut layer = TileLayer::new(name, map.width(), map.height());
+                fill_layer(&mut layer, &gids, tileset);
+                layer.visible = layer_visible;
+                layer.opacity = node.attribute("opacity").and_then(|opacity| opacity.parse().ok()).unwrap_or(1.0);
+                layer.offset = layer_offset;
+                layer.properties = tmx_properties(node);
+                map.layers.push(MapLayer::Tiles(layer));
+            }
+            "objectgroup" => {
+                let mut layer = ObjectLayer::new(name);
+                layer.visible = layer_visible;
+                layer.offset = layer_offset;
+                layer.properties = tmx_properties(node);
+                layer.objects = node.children()
+                    .filter(|child| child.has_tag_name("object"))
+                    .map(|object| tmx_object(object, tileset))
+                    .collect();
+                map.layers.push(MapLayer::Objects(layer));
+            }
+            "group" => tmx_layers(node, tileset, map, layer_offset, layer_visible),
+            "imagelayer" => warn!("Skipping Tiled image layer {}, image layers are not supported", name),
+            _ => {}
+        }
+    }
+}
+
+// tilesets and images named in the map are loaded relative to directory
+pub fn parse_tmx(xml: &str, directory: &Path) -> Option<Tilemap> {
+    let document = match roxmltree::Document::parse(xml) {
+        Ok(document) => document,
+        Err(error) => {
+            warn!("Failed to parse Tiled map: {}", error);
+            return None;
+        }
+    };
+    let map = document.root_element();
+
+    let tilesets: Vec<roxmltree::Node> = map.children().filter(|child| child.has_tag_name("tileset")).collect();
+    if !check_map(attribute(map, "orientation"), attribute(map, "infinite") == "1", tilesets.len()) {
+        return None;
+    }
+    let Some(tileset) = tilesets.first().and_then(|tileset| tmx_tileset(*tileset, directory)) else {
+        warn!("Tiled map has no usable tileset");
+        return None;
+    };
+
+    let mut tilemap = Tilemap::new(tileset.tileset.clone(), attribute_u32(map, "width"), attribute_u32(map, "height"))
+        .with_cell_size(Vec2::new(attribute_f32(map, "tilewidth"), attribute_f32(map, "tileheight")));
+    tilemap.properties = tmx_properties(map);
+    tmx_layers(map, &tileset, &mut tilemap, Vec2::ZERO, true);
+    Some(tilemap)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    // a directory holding a 32x16 tileset image, two 16x16 tiles wide
+    fn tileset_directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("rgraphics_tiled_{}_{}", name, std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        image::RgbImage::new(32, 16).save(directory.join("tiles.jpg")).unwrap();
+        directory
+    }
+
+    fn json_map(tilesets: &str) -> String {
+        format!(r#"{{
+            "orientation": "orthogonal", "width": 2, "height": 1, "tilewidth": 16, "tileheight": 16,
+            "tilesets": [{tilesets}],
+            "layers": [{{ "type": "tilelayer", "name": "ground", "data": [1, 2147483650] }}]
+        }}"#)
+    }
+
+    const TILESET: &str = r#"{ "firstgid": 1, "name": "tiles", "image": "tiles.jpg", "tilewidth": 16, "tileheight": 16 }"#;
+
+    #[test]
+    fn json_maps_load_their_tiles() {
+        let directory = tileset_directory("json");
+        let map = parse_json(&json_map(TILESET), &directory).unwrap();
+        let layer = map.tile_layer("ground").unwrap();
+        assert_eq!(map.tileset().tile_count, 2);
+        assert_eq!(layer.tile(0, 0), Some(Tile::new(0)));
+        assert_eq!(layer.tile(1, 0), Some(Tile::new(1).with_flip(true, false)));
+    }
+
+    #[test]
+    fn tmx_maps_load_their_tiles() {
+        let directory = tileset_directory("tmx");
+        let xml = r#"<map orientation="orthogonal" width="2" height="1" tilewidth="16" tileheight="16">
+            <tileset firstgid="1" name="tiles" tilewidth="16" tileheight="16"><image source="tiles.jpg"/></tileset>
+            <layer name="ground"><data encoding="csv">2,0</data></layer>
+        </map>"#;
+        let map = parse_tmx(xml, &directory).unwrap();
+        let layer = map.tile_layer("ground").unwrap();
+        assert_eq!(layer.tile(0, 0), Some(Tile::new(1)));
+        assert_eq!(layer.tile(1, 0), None);
+    }
+
+    #[test]
+    fn missing_tileset_images_fail_to_load() {
+        let directory = std::env::temp_dir().join("rgraphics_tiled_missing");
+        assert!(parse_json(&json_map(TILESET), &directory).is_none());
+        let xml = r#"<map orientation="orthogonal" width="1" height="1" tilewidth="16" tileheight="16">
+            <tileset firstgid="1" name="tiles" tilewidth="16" tileheight="16"><image source="tiles.jpg"/></tileset>
+        </map>"#;
+        assert!(parse_tmx(xml, &directory).is_none());
+    }
+
+    #[test]
+    fn maps_with_several_tilesets_are_rejected() {
+        let directory = tileset_directory("several");
+        let second = r#"{ "firstgid": 3, "name": "more", "image": "tiles.jpg", "tilewidth": 16, "tileheight": 16 }"#;
+        assert!(parse_json(&json_map(&format!("{TILESET}, {second}")), &directory).is_none());
+    }
+}
@@ -0,0 +1,628 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use glam::Vec2;
+use crate::graphics::draw::{Color, CoordinateSpace, Image, Rect, Renderer, Transform2D};
+use crate::graphics::gpu::Vertex;
+use crate::graphics::mesh::{Indices, Mesh, MeshHandle};
+
+// tiles per side of the square chunks each layer is split into, a chunk is one retained mesh
+pub const CHUNK_SIZE: u32 = 16;
+
+// custom properties as set in an editor such as Tiled
+#[derive(Clone, Debug, PartialEq)]
+pub enum Property {
+    String(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Color(Color),
+    File(String),
+    // id of another object on the map
+    Object(u32),
+    Class(Properties)
+}
+
+pub type Properties = HashMap<String, Property>;
+
+// index into the tileset, flips are applied diagonal first, then horizontal, then vertical
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Tile {
+    pub id: u32,
+    pub flip_x: bool,
+    pub flip_y: bool,
+    // swaps the x and y axes, combined with the other flips it rotates by quarter turns
+    pub flip_diagonal: bool
+}
+
+impl Tile {
+
+    pub fn new(id: u32) -> Self {
+        Self { id, ..Default::default() }
+    }
+
+    pub fn with_flip(mut self, flip_x: bool, flip_y: bool) -> Self {
+        self.flip_x = flip_x;
+        self.flip_y = flip_y;
+        self
+    }
+
+    pub fn with_flip_diagonal(mut self, flip_diagonal: bool) -> Self {
+        self.flip_diagonal = flip_diagonal;
+        self
+    }
+
+}
+
+// an image cut into equally sized tiles, numbered left to right then top to bottom
+#[derive(Clone, Debug)]
+pub struct Tileset {
+    pub name: String,
+    pub image: Arc<Image>,
+    // in image pixels
+    pub tile_size: Vec2,
+    pub margin: f32,
+    pub spacing: f32,
+    pub columns: u32,
+    pub tile_count: u32,
+    pub tile_properties: HashMap<u32, Properties>
+}
+
+impl Tileset {
+
+    pub fn new(image: Arc<Image>, tile_size: Vec2) -> Self {
+        let mut tileset = Self {
+            name: image.path.clone(),
+            image,
+            tile_size,
+            margin: 0.0,
+            spacing: 0.0,
+            columns: 0,
+            tile_count: 0,
+            tile_properties: HashMap::new()
+        };
+        tileset.fit_to_image();
+        tileset
+    }
+
+    // border around the whole image and gap between tiles, in pixels
+    pub fn with_margin_and_spacing(mut self, margin: f32, spacing: f32) -> Self {
+        self.margin = margin;
+        self.spacing = spacing;
+        self.fit_to_image();
+        self
+    }
+
+    fn fit_to_image(&mut self) {
+        let image = Vec2::new(self.image.image.width() as f32, self.image.image.height() as f32);
+        let step = self.tile_size + self.spacing;
+        let fit = ((image - 2.0 * self.margin + self.spacing) / step.max(Vec2::ONE)).floor().max(Vec2::ZERO);
+        self.columns = fit.x as u32;
+        self.tile_count = fit.x as u32 * fit.y as u32;
+    }
+
+    // where the tile sits in the image, in pixels
+    pub fn region(&self, id: u32) -> Option<Rect> {
+        if id >= self.tile_count || self.columns == 0 {
+            return None;
+        }
+        let cell = Vec2::new((id % self.columns) as f32, (id / self.columns) as f32);
+        let min = Vec2::splat(self.margin) + cell * (self.tile_size + self.spacing);
+        Some(Rect::new(min, min + self.tile_size))
+    }
+
+}
+
+#[derive(Debug, Default)]
+struct Chunk {
+    mesh: Option<MeshHandle>,
+    dirty: bool
+}
+
+// a copy builds its own mesh, sharing the handle would let releasing one copy free the other's
+impl Clone for Chunk {
+    fn clone(&self) -> Self {
+        Self { mesh: None, dirty: true }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TileLayer {
+    pub name: String,
+    pub visible: bool,
+    pub opacity: f32,
+    // in map pixels
+    pub offset: Vec2,
+    pub properties: Properties,
+    width: u32,
+    height: u32,
+    tiles: Vec<Option<Tile>>,
+    chunks: Vec<Chunk>,
+    chunk_columns: u32
+}
+
+impl TileLayer {
+
+    pub fn new(name: &str, width: u32, height: u32) -> Self {
+        let chunk_columns = width.div_ceil(CHUNK_SIZE);
+        let chunk_rows = height.div_ceil(CHUNK_SIZE);
+        Self {
+            name: name.to_string(),
+            visible: true,
+            opacity: 1.0,
+            offset: Vec2::ZERO,
+            properties: Properties::new(),
+            width,
+            height,
+            tiles: vec![None; (width * height) as usize],
+            chunks: vec![Chunk { mesh: None, dirty: true }; (chunk_columns * chunk_rows) as usize],
+            chunk_columns
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn tile(&self, x: u32, y: u32) -> Option<Tile> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.tiles[(y * self.width + x) as usize]
+    }
+
+    // only the chunk holding the tile is rebuilt, and only if the tile actually changed
+    pub fn set_tile(&mut self, x: u32, y: u32, tile: Option<Tile>) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+        let slot = &mut self.tiles[(y * self.width + x) as usize];
+        if *slot != tile {
+            *slot = tile;
+            self.chunks[((y / CHUNK_SIZE) * self.chunk_columns + x / CHUNK_SIZE) as usize].dirty = true;
+        }
+    }
+
+    // rebuilds every chunk on the next draw, e.g. after the tileset changed
+    pub fn invalidate(&mut self) {
+        for chunk in self.chunks.iter_mut() {
+            chunk.dirty = true;
+        }
+    }
+
+    fn chunk_bounds(&self, index: usize, cell_size: Vec2) -> Rect {
+        let min = Vec2::new((index as u32 % self.chunk_columns) as f32, (index as u32 / self.chunk_columns) as f32) * CHUNK_SIZE as f32;
+        Rect::new(min * cell_size, (min + CHUNK_SIZE as f32) * cell_size)
+    }
+
+    fn rebuild(&mut self, renderer: &mut Renderer, tileset: &Tileset, cell_size: Vec2) {
+        for index in 0..self.chunks.len() {
+            if !self.chunks[index].dirty {
+                continue;
+            }
+            let mesh = self.build_chunk(index, tileset, cell_size);
+            let chunk = &mut self.chunks[index];
+            chunk.dirty = false;
+            match (mesh, chunk.mesh.as_ref()) {
+                (Some(mesh), Some(handle)) => renderer.update_mesh(handle, mesh),
+                (Some(mesh), None) => chunk.mesh = Some(renderer.create_mesh(mesh)),
+                (None, _) => {
+                    if let Some(handle) = chunk.mesh.take() {
+                        renderer.release_mesh(handle);
+                    }
+                }
+            }
+        }
+    }
+
+    fn build_chunk(&self, index: usize, tileset: &Tileset, cell_size: Vec2) -> Option<Mesh> {
+        let first = Vec2::new((index as u32 % self.chunk_columns) as f32, (index as u32 / self.chunk_columns) as f32) * CHUNK_SIZE as f32;
+        let (first_x, first_y) = (first.x as u32, first.y as u32);
+        let image_size = Vec2::new(tileset.image.image.width() as f32, tileset.image.image.height() as f32).max(Vec2::ONE);
+
+        let mut vertices = Vec::new();
+        let mut indices: Vec<u32> = Vec::new();
+        for y in first_y..(first_y + CHUNK_SIZE).min(self.height) {
+            for x in first_x..(first_x + CHUNK_SIZE).min(self.width) {
+                let Some(tile) = self.tiles[(y * self.width + x) as usize] else {
+                    continue;
+                };
+                let Some(region) = tileset.region(tile.id) else {
+                    continue;
+                };
+
+                // tiles taller or wider than the grid hang off its bottom left corner, as in Tiled
+                let min = Vec2::new(x as f32 * cell_size.x, (y + 1) as f32 * cell_size.y - tileset.tile_size.y);
+                // half a texel in from each edge so filtering never picks up the neighbouring tile
+                let uv_min = (region.min + 0.5) / image_size;
+                let uv_max = (region.max - 0.5) / image_size;
+
+                let base = vertices.len() as u32;
+                for corner in [Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(1.0, 1.0), Vec2::new(0.0, 1.0)] {
+                    let position = min + corner * tileset.tile_size;
+                    let mut uv = corner;
+                    if tile.flip_y {
+                        uv.y = 1.0 - uv.y;
+                    }
+                    if tile.flip_x {
+                        uv.x = 1.0 - uv.x;
+                    }
+                    if tile.flip_diagonal {
+                        uv = Vec2::new(uv.y, uv.x);
+                    }
+                    let uv = uv_min + uv * (uv_max - uv_min);
+                    vertices.push(Vertex::new([position.x, position.y, 0.0], uv.into()));
+                }
+                indices.extend([base, base + 1, base + 2, base, base + 2, base + 3]);
+            }
+        }
+
+        if vertices.is_empty() {
+            return None;
+        }
+        Some(Mesh::new(vertices, Indices::compact(indices)))
+    }
+
+    fn release(&mut self, renderer: &mut Renderer) {
+        for chunk in self.chunks.iter_mut() {
+            if let Some(handle) = chunk.mesh.take() {
+                renderer.release_mesh(handle);
+            }
+            chunk.dirty = true;
+        }
+    }
+
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ObjectShape {
+    Rectangle,
+    Ellipse,
+    Point,
+    // points relative to the object's position
+    Polygon(Vec<Vec2>),
+    Polyline(Vec<Vec2>),
+    Text(String)
+}
+
+// placed shapes such as spawn points and triggers, read by the game rather than drawn
+#[derive(Clone, Debug, PartialEq)]
+pub struct MapObject {
+    pub id: u32,
+    pub name: String,
+    // called type in older Tiled versions
+    pub class: String,
+    // in map pixels
+    pub position: Vec2,
+    pub size: Vec2,
+    // radians, clockwise on a y down map
+    pub rotation: f32,
+    pub visible: bool,
+    pub shape: ObjectShape,
+    // tile objects show a tile from the tileset
+    pub tile: Option<Tile>,
+    pub properties: Properties
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjectLayer {
+    pub name: String,
+    pub visible: bool,
+    pub offset: Vec2,
+    pub objects: Vec<MapObject>,
+    pub properties: Properties
+}
+
+impl ObjectLayer {
+
+    pub fn new(name: &str) -> Self {
+        Self { name: name.to_string(), visible: true, offset: Vec2::ZERO, objects: Vec::new(), properties: Properties::new() }
+    }
+
+    pub fn find(&self, name: &str) -> Option<&MapObject> {
+        self.objects.iter().find(|object| object.name == name)
+    }
+
+}
+
+#[derive(Clone, Debug)]
+pub enum MapLayer {
+    Tiles(TileLayer),
+    Objects(ObjectLayer)
+}
+
+impl MapLayer {
+    pub fn name(&self) -> &str {
+        match self {
+            MapLayer::Tiles(layer) => &layer.name,
+            MapLayer::Objects(layer) => &layer.name
+        }
+    }
+}
+
+// a grid of tile layers drawn bottom to top, laid out in map pixels with y down and one map pixel
+// drawn as one logical pixel, call release before dropping it to free the chunk meshes
+#[derive(Clone, Debug)]
+pub struct Tilemap {
+    tileset: Tileset,
+    pub layers: Vec<MapLayer>,
+    pub properties: Properties,
+    width: u32,
+    height: u32,
+    // size of a grid cell in map pixels, usually the tileset's tile size
+    cell_size: Vec2
+}
+
+impl Tilemap {
+
+    pub fn new(tileset: Tileset, width: u32, height: u32) -> Self {
+        Self {
+            cell_size: tileset.tile_size,
+            tileset,
+            layers: Vec::new(),
+            properties: Properties::new(),
+            width,
+            height
+        }
+    }
+
+    pub fn with_cell_size(mut self, cell_size: Vec2) -> Self {
+        self.cell_size = cell_size;
+        self.invalidate();
+        self
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn cell_size(&self) -> Vec2 {
+        self.cell_size
+    }
+
+    // in map pixels
+    pub fn pixel_size(&self) -> Vec2 {
+        Vec2::new(self.width as f32, self.height as f32) * self.cell_size
+    }
+
+    pub fn tileset(&self) -> &Tileset {
+        &self.tileset
+    }
+
+    pub fn set_tileset(&mut self, tileset: Tileset) {
+        self.tileset = tileset;
+        self.invalidate();
+    }
+
+    // returns the index of the new layer in layers
+    pub fn add_tile_layer(&mut self, name: &str) -> usize {
+        self.layers.push(MapLayer::Tiles(TileLayer::new(name, self.width, self.height)));
+        self.layers.len() - 1
+    }
+
+    pub fn add_object_layer(&mut self, layer: ObjectLayer) -> usize {
+        self.layers.push(MapLayer::Objects(layer));
+        self.layers.len() - 1
+    }
+
+    pub fn tile_layer(&self, name: &str) -> Option<&TileLayer> {
+        self.layers.iter().find_map(|layer| match layer {
+            MapLayer::Tiles(layer) if layer.name == name => Some(layer),
+            _ => None
+        })
+    }
+
+    pub fn tile_layer_mut(&mut self, name: &str) -> Option<&mut TileLayer> {
+        self.layers.iter_mut().find_map(|layer| match layer {
+            MapLayer::Tiles(layer) if layer.name == name => Some(layer),
+            _ => None
+        })
+    }
+
+    pub fn object_layer(&self, name: &str) -> Option<&ObjectLayer> {
+        self.layers.iter().find_map(|layer| match layer {
+            MapLayer::Objects(layer) if layer.name == name => Some(layer),
+            _ => None
+        })
+    }
+
+    // the grid cell under a point in map pixels
+    pub fn cell_at(&self, point: Vec2) -> Option<(u32, u32)> {
+        let cell = (point / self.cell_size).floor();
+        if cell.x < 0.0 || cell.y < 0.0 || cell.x >= self.width as f32 || cell.y >= self.height as f32 {
+            return None;
+        }
+        Some((cell.x as u32, cell.y as u32))
+    }
+
+    fn invalidate(&mut self) {
+        for layer in self.layers.iter_mut() {
+            if let MapLayer::Tiles(layer) = layer {
+                layer.invalidate();
+            }
+        }
+    }
+
+    // from map pixels to the renderer's coordinate space, transform places the map's top left corner
+    pub fn map_transform(&self, renderer: &Renderer, transform: Transform2D) -> Transform2D {
+        let mut scale = renderer.logical_pixel();
+        if renderer.coordinate_space() == CoordinateSpace::Normalized {
+            scale.y = -scale.y;
+        }
+        transform.compose(&Transform2D::IDENTITY.with_scale(scale))
+    }
+
+    // rebuilds changed chunks, then draws the chunks of visible layers that are on screen
+    pub fn draw(&mut self, renderer: &mut Renderer, transform: Transform2D) {
+        let map_transform = self.map_transform(renderer, transform);
+        let view = renderer.visible_rect(renderer.current_layer());
+        let outer = renderer.current_transform();
+
+        for layer in self.layers.iter_mut() {
+            let MapLayer::Tiles(layer) = layer else {
+                continue;
+            };
+            if !layer.visible {
+                continue;
+            }
+            layer.rebuild(renderer, &self.tileset, self.cell_size);
+
+            let layer_transform = map_transform.compose(&Transform2D::at(layer.offset.x, layer.offset.y));
+            let screen_transform = outer.compose(&layer_transform);
+            let paint = Color::new(1.0, 1.0, 1.0, layer.opacity);
+            for (index, chunk) in layer.chunks.iter().enumerate() {
+                let Some(ref handle) = chunk.mesh else {
+                    continue;
+                };
+                // oversized tiles reach up out of their chunk by the difference in height
+                let mut bounds = layer.chunk_bounds(index, self.cell_size);
+                bounds.min.y -= (self.tileset.tile_size.y - self.cell_size.y).max(0.0);
+                bounds.max.x += (self.tileset.tile_size.x - self.cell_size.x).max(0.0);
+                if !bounds.transformed(&screen_transform).intersects(&view) {
+                    continue;
+                }
+                renderer.draw_mesh(layer_transform, handle, paint, Some(self.tileset.image.clone()));
+            }
+        }
+    }
+
+    // frees every chunk mesh, drawing again rebuilds them
+    pub fn release(&mut self, renderer: &mut Renderer) {
+        for layer in self.layers.iter_mut() {
+            if let MapLayer::Tiles(layer) = layer {
+                layer.release(renderer);
+            }
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, RgbaImage};
+
+    // two 16x16 tiles side by side with a 1 pixel margin and 2 pixels between them
+    fn tileset() -> Tileset {
+        let image = Image { path: "tiles".to_string(), image: DynamicImage::ImageRgba8(RgbaImage::new(38, 20)) };
+        Tileset::new(Arc::new(image), Vec2::splat(16.0)).with_margin_and_spacing(1.0, 2.0)
+    }
+
+    fn dirty_chunks(layer: &TileLayer) -> Vec<usize> {
+        layer.chunks.iter().enumerate().filter(|(_, chunk)| chunk.dirty).map(|(index, _)| index).collect()
+    }
+
+    fn meshes(map: &Tilemap) -> Vec<Option<MeshHandle>> {
+        map.tile_layer("ground").unwrap().chunks.iter().map(|chunk| chunk.mesh).collect()
+    }
+
+    #[test]
+    fn regions_skip_the_margin_and_spacing() {
+        let tileset = tileset();
+        assert_eq!((tileset.columns, tileset.tile_count), (2, 2));
+        assert_eq!(tileset.region(0), Some(Rect::new(Vec2::splat(1.0), Vec2::splat(17.0))));
+        assert_eq!(tileset.region(1), Some(Rect::new(Vec2::new(19.0, 1.0), Vec2::new(35.0, 17.0))));
+        assert_eq!(tileset.region(2), None);
+    }
+
+    #[test]
+    fn only_changed_tiles_dirty_their_chunk() {
+        // 3 chunks across and 2 down
+        let mut layer = TileLayer::new("ground", 40, 20);
+        assert_eq!(dirty_chunks(&layer).len(), 6);
+        layer.chunks.iter_mut().for_each(|chunk| chunk.dirty = false);
+
+        layer.set_tile(5, 5, None);
+        layer.set_tile(100, 5, Some(Tile::new(0)));
+        assert!(dirty_chunks(&layer).is_empty());
+
+        layer.set_tile(20, 17, Some(Tile::new(1)));
+        assert_eq!(dirty_chunks(&layer), vec![4]);
+        assert_eq!(layer.tile(20, 17), Some(Tile::new(1)));
+
+        layer.chunks[4].dirty = false;
+        layer.set_tile(20, 17, Some(Tile::new(1)));
+        assert!(dirty_chunks(&layer).is_empty());
+        layer.set_tile(20, 17, Some(Tile::new(1).with_flip(true, false)));
+        assert_eq!(dirty_chunks(&layer), vec![4]);
+    }
+
+    #[test]
+    fn drawing_builds_chunks_once_and_drops_empty_ones() {
+        let mut renderer = Renderer::new();
+        let mut map = Tilemap::new(tileset(), 40, 20);
+        map.add_tile_layer("ground");
+        map.tile_layer_mut("ground").unwrap().set_tile(0, 0, Some(Tile::new(0)));
+        map.tile_layer_mut("ground").unwrap().set_tile(39, 19, Some(Tile::new(1)));
+
+        map.draw(&mut renderer, Transform2D::default());
+        let built = meshes(&map);
+        assert_eq!(built.iter().filter(|mesh| mesh.is_some()).count(), 2);
+        assert!(dirty_chunks(map.tile_layer("ground").unwrap()).is_empty());
+        map.draw(&mut renderer, Transform2D::default());
+        assert_eq!(meshes(&map), built);
+
+        map.tile_layer_mut("ground").unwrap().set_tile(0, 0, None);
+        map.draw(&mut renderer, Transform2D::default());
+        assert_eq!(meshes(&map)[0], None);
+        assert_eq!(meshes(&map)[5], built[5]);
+    }
+
+    #[test]
+    fn clones_build_their_own_chunk_meshes() {
+        let mut renderer = Renderer::new();
+        let mut map = Tilemap::new(tileset(), 16, 16);
+        map.add_tile_layer("ground");
+        map.tile_layer_mut("ground").unwrap().set_tile(0, 0, Some(Tile::new(0)));
+        map.draw(&mut renderer, Transform2D::default());
+
+        let mut copy = map.clone();
+        assert_eq!(meshes(&copy), vec![None]);
+        map.release(&mut renderer);
+        copy.draw(&mut renderer, Transform2D::default());
+        assert!(meshes(&copy)[0].is_some());
+        assert_ne!(meshes(&copy)[0], meshes(&map)[0]);
+    }
+
+    #[test]
+    fn flips_map_the_tile_corners() {
+        let tileset = tileset();
+        let image_size = Vec2::new(38.0, 20.0);
+        let region = tileset.region(1).unwrap();
+        let (uv_min, uv_max) = ((region.min + 0.5) / image_size, (region.max - 0.5) / image_size);
+        // which corner of the tile image each corner of the cell shows, top left, top right, bottom right, bottom left
+        let corners = |tile: Tile| -> Vec<Vec2> {
+            let mut layer = TileLayer::new("ground", 1, 1);
+            layer.set_tile(0, 0, Some(tile));
+            let mesh = layer.build_chunk(0, &tileset, Vec2::splat(16.0)).unwrap();
+            mesh.vertices.iter().map(|vertex| ((Vec2::from(vertex.uv) - uv_min) / (uv_max - uv_min)).round()).collect()
+        };
+        let (top_left, top_right, bottom_right, bottom_left) = (Vec2::ZERO, Vec2::X, Vec2::ONE, Vec2::Y);
+
+        let tile = Tile::new(1);
+        assert_eq!(corners(tile), vec![top_left, top_right, bottom_right, bottom_left]);
+        assert_eq!(corners(tile.with_flip(true, false)), vec![top_right, top_left, bottom_left, bottom_right]);
+        assert_eq!(corners(tile.with_flip(false, true)), vec![bottom_left, bottom_right, top_right, top_left]);
+        assert_eq!(corners(tile.with_flip(true, true)), vec![bottom_right, bottom_left, top_left, top_right]);
+        assert_eq!(corners(tile.with_flip_diagonal(true)), vec![top_left, bottom_left, bottom_right, top_right]);
+        // Tiled stores a quarter turn clockwise as diagonal and horizontal, and anticlockwise as diagonal and vertical
+        assert_eq!(corners(tile.with_flip_diagonal(true).with_flip(true, false)), vec![bottom_left, top_left, top_right, bottom_right]);
+        assert_eq!(corners(tile.with_flip_diagonal(true).with_flip(false, true)), vec![top_right, bottom_right, bottom_left, top_left]);
+    }
+
+    #[test]
+    fn tall_tiles_hang_off_the_bottom_of_their_cell() {
+        let mut layer = TileLayer::new("ground", 2, 2);
+        layer.set_tile(1, 1, Some(Tile::new(0)));
+        let mesh = layer.build_chunk(0, &tileset(), Vec2::splat(8.0)).unwrap();
+        let positions: Vec<Vec2> = mesh.vertices.iter().map(|vertex| Vec2::new(vertex.position[0], vertex.position[1])).collect();
+        assert_eq!(Rect::bounding(&positions), Rect::new(Vec2::new(8.0, 0.0), Vec2::new(24.0, 16.0)));
+    }
+}
@@ -0,0 +1,201 @@
+use glam::{Mat3, Mat4, Vec2, Vec3, Vec4};
+
+// applied as translate * rotate * skew * scale, all around the origin
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Transform2D {
+    pub position: Vec2,
+    pub scale: Vec2,
+    // radians, counter clockwise with y up
+    pub rotation: f32,
+    // pivot in the mesh's own units that lands on position and that rotation and scale happen around
+    pub origin: Vec2,
+    // shear angles in radians along x and y
+    pub skew: Vec2
+}
+
+impl Transform2D {
+
+    pub const IDENTITY: Transform2D = Transform2D {
+        position: Vec2::ZERO,
+        scale: Vec2::ONE,
+        rotation: 0.0,
+        origin: Vec2::ZERO,
+        skew: Vec2::ZERO
+    };
+
+    pub fn at(x: f32, y: f32) -> Self {
+        Self {
+            position: Vec2::new(x, y),
+            ..Self::IDENTITY
+        }
+    }
+
+    pub fn with_scale(mut self, scale: Vec2) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_rotation(mut self, rotation: f32) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn with_origin(mut self, origin: Vec2) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    pub fn with_skew(mut self, skew: Vec2) -> Self {
+        self.skew = skew;
+        self
+    }
+
+    pub fn to_mat3(&self) -> Mat3 {
+        let skew = Mat3::from_cols(
+            Vec3::new(1.0, self.skew.y.tan(), 0.0),
+            Vec3::new(self.skew.x.tan(), 1.0, 0.0),
+            Vec3::Z
+        );
+        Mat3::from_translation(self.position)
+            * Mat3::from_angle(self.rotation)
+            * skew
+            * Mat3::from_scale(self.scale)
+            * Mat3::from_translation(-self.origin)
+    }
+
+    pub fn to_mat4(&self) -> Mat4 {
+        let m = self.to_mat3();
+        Mat4::from_cols(
+            m.x_axis.truncate().extend(0.0).extend(0.0),
+            m.y_axis.truncate().extend(0.0).extend(0.0),
+            Vec4::Z,
+            m.z_axis.truncate().extend(0.0).extend(1.0)
+        )
+    }
+
+    // splits an affine matrix back into parts, the result has no origin and only skews along x
+    pub fn from_mat3(m: Mat3) -> Self {
+        let (a, b) = (m.x_axis.x, m.x_axis.y);
+        let (c, d) = (m.y_axis.x, m.y_axis.y);
+
+        let rotation = b.atan2(a);
+        let (sin, cos) = rotation.sin_cos();
+        let scale_x = (a * a + b * b).sqrt();
+        let scale_y = d * cos - c * sin;
+        let shear = if scale_y != 0.0 { (c * cos + d * sin) / scale_y } else { 0.0 };
+
+        Self {
+            position: m.z_axis.truncate(),
+            scale: Vec2::new(scale_x, scale_y),
+            rotation,
+            origin: Vec2::ZERO,
+            skew: Vec2::new(shear.atan(), 0.0)
+        }
+    }
+
+    // the transform that applies child first and then self, as when drawing child inside self
+    pub fn compose(&self, child: &Transform2D) -> Transform2D {
+        Self::from_mat3(self.to_mat3() * child.to_mat3())
+    }
+
+    // None when the transform squashes everything onto a line or a point
+    pub fn inverse(&self) -> Option<Transform2D> {
+        let m = self.to_mat3();
+        if m.determinant().abs() <= f32::EPSILON {
+            return None;
+        }
+        Some(Self::from_mat3(m.inverse()))
+    }
+
+    pub fn transform_point(&self, point: Vec2) -> Vec2 {
+        self.to_mat3().transform_point2(point)
+    }
+
+    pub fn transform_vector(&self, vector: Vec2) -> Vec2 {
+        self.to_mat3().transform_vector2(vector)
+    }
+
+}
+
+impl Default for Transform2D {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Transform2D {
+        Transform2D::at(3.0, -2.0)
+            .with_rotation(0.7)
+            .with_scale(Vec2::new(2.0, -0.5))
+            .with_skew(Vec2::new(0.3, -0.2))
+            .with_origin(Vec2::new(1.0, 4.0))
+    }
+
+    #[test]
+    fn compose_matches_the_matrix_product() {
+        let parent = sample();
+        let child = Transform2D::at(-1.0, 5.0).with_rotation(-1.2).with_scale(Vec2::new(0.5, 3.0)).with_skew(Vec2::new(0.0, 0.4));
+        assert!(parent.compose(&child).to_mat3().abs_diff_eq(parent.to_mat3() * child.to_mat3(), 1e-4));
+    }
+
+    #[test]
+    fn inverse_composed_with_the_transform_is_the_identity() {
+        let transform = sample();
+        let inverse = transform.inverse().unwrap();
+        assert!(inverse.compose(&transform).to_mat3().abs_diff_eq(Mat3::IDENTITY, 1e-4));
+        assert!(transform.compose(&inverse).to_mat3().abs_diff_eq(Mat3::IDENTITY, 1e-4));
+        let point = Vec2::new(7.0, -3.0);
+        assert!(inverse.transform_point(transform.transform_point(point)).abs_diff_eq(point, 1e-4));
+    }
+
+    #[test]
+    fn pivot_lands_on_the_position() {
+        let transform = sample();
+        assert!(transform.transform_point(transform.origin).abs_diff_eq(transform.position, 1e-5));
+    }
+
+    #[test]
+    fn rotation_turns_counter_clockwise() {
+        let transform = Transform2D::IDENTITY.with_rotation(std::f32::consts::FRAC_PI_2);
+        assert!(transform.transform_vector(Vec2::X).abs_diff_eq(Vec2::Y, 1e-6));
+    }
+
+    #[test]
+    fn skew_shears_along_each_axis() {
+        let angle = 0.5_f32;
+        let along_x = Transform2D::IDENTITY.with_skew(Vec2::new(angle, 0.0));
+        assert!(along_x.transform_point(Vec2::Y).abs_diff_eq(Vec2::new(angle.tan(), 1.0), 1e-6));
+        let along_y = Transform2D::IDENTITY.with_skew(Vec2::new(0.0, angle));
+        assert!(along_y.transform_point(Vec2::X).abs_diff_eq(Vec2::new(1.0, angle.tan()), 1e-6));
+    }
+
+    #[test]
+    fn matrices_round_trip_through_from_mat3() {
+        let matrix = sample().to_mat3();
+        assert!(Transform2D::from_mat3(matrix).to_mat3().abs_diff_eq(matrix, 1e-4));
+    }
+
+    #[test]
+    fn to_mat4_places_points_like_to_mat3() {
+        let transform = sample();
+        let point = Vec2::new(-2.0, 6.0);
+        let placed = transform.to_mat4().transform_point3(point.extend(0.0));
+        assert!(placed.truncate().abs_diff_eq(transform.transform_point(point), 1e-4));
+        assert_eq!(placed.z, 0.0);
+    }
+
+    #[test]
+    fn degenerate_scales_have_no_inverse() {
+        assert_eq!(Transform2D::IDENTITY.with_scale(Vec2::new(0.0, 1.0)).inverse(), None);
+        assert_eq!(Transform2D::IDENTITY.with_scale(Vec2::ZERO).inverse(), None);
+        // skewing both axes by 45 degrees folds the plane onto a line
+        let quarter = std::f32::consts::FRAC_PI_4;
+        assert_eq!(Transform2D::IDENTITY.with_skew(Vec2::splat(quarter)).inverse(), None);
+        assert!(Transform2D::IDENTITY.with_skew(Vec2::new(quarter, -quarter)).inverse().is_some());
+    }
+
+}
@@ -1,41 +1,154 @@
 pub mod graphics;
+pub mod context;
+pub mod timing;
+pub mod tween;
+pub mod ui;
+#[cfg(feature = "scene")]
+pub mod scene;
+#[cfg(feature = "egui")]
+pub mod egui_backend;
 
+use std::collections::HashMap;
 use std::sync::Arc;
-use std::thread;
 use std::time::Duration;
 use glam::Vec2;
-use log::{debug, error};
-use crate::graphics::gpu::Display;
+use log::{debug, error, warn};
+use crate::context::{Context, WindowCommand};
+#[cfg(feature = "egui")]
+use crate::egui_backend::EguiBackend;
+use crate::graphics::gpu::{Display, Gpu};
+use crate::timing::{Clock, FixedTimestep, FrameLimiter, FrameStats, LoopMode, SystemClock};
+use wgpu::PresentMode;
 use winit::application::ApplicationHandler;
-use winit::event::{DeviceId, WindowEvent};
-use winit::event_loop::{ActiveEventLoop, EventLoop};
+use winit::dpi::{LogicalSize, PhysicalSize};
+use winit::event::{DeviceId, ElementState, MouseButton, MouseScrollDelta, WindowEvent};
+use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
 use winit::keyboard::{KeyCode, PhysicalKey};
-use winit::window::{Window, WindowAttributes, WindowId};
+use winit::window::{Fullscreen, Window, WindowAttributes, WindowId};
 use crate::graphics::draw::Renderer;
 
+// scroll distance of one line of a mouse wheel, in logical pixels
+const SCROLL_LINE: f32 = 20.0;
+
 #[derive(Debug)]
 pub enum InputEvent {
+    // sent when the key goes down and again for each repeat while it is held
     KeyboardInput(DeviceId, KeyCode),
-    CursorMoved(DeviceId, Vec2)
+    KeyReleased(DeviceId, KeyCode),
+    // the characters a key press typed, with the keyboard layout and modifiers applied
+    ReceivedText(DeviceId, String),
+    // cursor position in logical pixels, use Context::to_physical for physical pixels
+    CursorMoved(DeviceId, Vec2),
+    MouseInput(DeviceId, MouseButton, ElementState),
+    // in logical pixels, positive y when the wheel turns away from the user
+    MouseWheel(DeviceId, Vec2),
+    ScaleFactorChanged(f64)
 }
 
 pub trait EventHandler {
-    fn on_init(&mut self) {}
-    fn on_input_event(&mut self, event: InputEvent) {}
-    fn on_update(&mut self, delta: f32) {}
-    fn on_draw(&mut self, renderer: &mut Renderer) {}
+    fn on_init(&mut self, _ctx: &mut Context) {}
+    fn on_input_event(&mut self, _ctx: &mut Context, _event: InputEvent) {}
+    fn on_update(&mut self, _ctx: &mut Context, _delta: f32) {}
+    fn on_fixed_update(&mut self, _ctx: &mut Context, _step: f32) {}
+    // called every frame after on_update, for debug inspectors and other egui windows
+    #[cfg(feature = "egui")]
+    fn on_egui(&mut self, _ctx: &mut Context, _egui: &egui::Context) {}
+    fn on_draw(&mut self, _renderer: &mut Renderer, _alpha: f32) {}
     fn on_close(&mut self) -> bool { true }
 }
 
-pub struct Raymond {
-    window: Option<Arc<Window>>,
-    window_attributes: WindowAttributes,
-    display: Option<Display>,
+// everything owned by one open window
+struct WindowState {
+    window: Arc<Window>,
+    display: Display,
     handler: Box<dyn EventHandler>,
+    context: Context,
     renderer: Renderer,
-    elapsed_since_last_frame: f32,
-    start: std::time::Instant,
-    target_frame_time: Option<f32>
+    fixed_timestep: Option<FixedTimestep>,
+    last_frame: Option<Duration>,
+    #[cfg(feature = "egui")]
+    egui: EguiBackend
+}
+
+impl WindowState {
+
+    fn redraw(&mut self, clock: &dyn Clock) {
+
+        // measure the time since the previous frame started, including time spent in the event loop
+        let frame_start = clock.now();
+        let delta = match self.last_frame {
+            Some(last_frame) => frame_start.saturating_sub(last_frame).as_secs_f32(),
+            None => 0.0
+        };
+        if self.last_frame.is_some() {
+            self.context.frame_stats_mut().record(delta);
+        }
+        self.last_frame = Some(frame_start);
+
+        // the egui pass spans the update and draw handlers so either can make widgets
+        #[cfg(feature = "egui")]
+        self.egui.begin_pass(&self.window);
+
+        // run the fixed updates owed for this frame
+        let alpha = match self.fixed_timestep {
+            Some(ref mut timestep) => {
+                let steps = timestep.advance(delta);
+                for _ in 0..steps {
+                    self.handler.on_fixed_update(&mut self.context, timestep.step());
+                }
+                timestep.alpha()
+            }
+            None => 1.0
+        };
+
+        // call the update handler
+        self.handler.on_update(&mut self.context, delta);
+
+        #[cfg(feature = "egui")]
+        {
+            let egui = self.egui.context().clone();
+            self.handler.on_egui(&mut self.context, &egui);
+        }
+
+        // call the draw handler
+        self.renderer.set_viewport(self.display.size(), self.display.scale_factor());
+        self.handler.on_draw(&mut self.renderer, alpha);
+
+        #[cfg(feature = "egui")]
+        {
+            let frame = self.egui.end_pass(&self.window);
+            self.display.set_egui_frame(frame);
+        }
+
+        // render the frame
+        self.display.render(&mut self.renderer);
+
+        // clear the renderer
+        self.renderer.end_frame();
+
+    }
+
+    fn resize(&mut self, size: PhysicalSize<u32>) {
+        self.display.resize(size);
+        let scale_factor = self.context.scale_factor();
+        self.context.set_window_metrics(size, scale_factor);
+    }
+
+}
+
+pub struct Raymond {
+    window_attributes: WindowAttributes,
+    handler: Option<Box<dyn EventHandler>>,
+    windows: HashMap<WindowId, WindowState>,
+    main_window: Option<WindowId>,
+    gpu: Option<Gpu>,
+    loop_mode: LoopMode,
+    clock: Arc<dyn Clock>,
+    // paces the whole loop rather than each window, so every window shares the target fps
+    frame_limiter: FrameLimiter,
+    redrawn: bool,
+    present_mode: PresentMode,
+    msaa_samples: u32
 }
 
 impl Raymond {
@@ -43,31 +156,27 @@ impl Raymond {
     pub fn new(handler: Box<dyn EventHandler>) -> Self {
         Self {
             window_attributes: Window::default_attributes(),
-            display: None,
-            window: None,
-            handler,
-            elapsed_since_last_frame: 0.0,
-            renderer: Renderer::new(),
-            start: std::time::Instant::now(),
-            target_frame_time: None
+            handler: Some(handler),
+            windows: HashMap::new(),
+            main_window: None,
+            gpu: None,
+            loop_mode: LoopMode::Variable,
+            clock: Arc::new(SystemClock::new()),
+            frame_limiter: FrameLimiter::new(None),
+            redrawn: false,
+            present_mode: PresentMode::AutoNoVsync,
+            msaa_samples: 1
         }
     }
 
     pub fn create_window(height : i32, width : i32, title : &str, handler: Box<dyn EventHandler>) -> Self {
         let window_attributes = Window::default_attributes()
             .with_title(title)
-            .with_inner_size(winit::dpi::PhysicalSize::new(width, height));
+            .with_inner_size(LogicalSize::new(width, height));
         
-        Self {
-            window_attributes,
-            display: None,
-            window: None,
-            handler,
-            elapsed_since_last_frame: 0.0,
-            renderer: Renderer::new(),
-            start: std::time::Instant::now(),
-            target_frame_time: None
-        }
+        let mut raymond = Self::new(handler);
+        raymond.window_attributes = window_attributes;
+        raymond
     }
 
     pub fn set_window_attributes(&mut self, attributes: WindowAttributes) -> &mut Self {
@@ -83,95 +192,290 @@ impl Raymond {
     }
 
     pub fn set_target_fps(&mut self, target: u32) -> &mut Self {
-        self.target_frame_time = Some(1.0 / target as f32);
+        self.frame_limiter.set_target_fps(Some(target));
         self
     }
 
-}
+    pub fn set_frame_limiter(&mut self, limiter: FrameLimiter) -> &mut Self {
+        self.frame_limiter = limiter;
+        self
+    }
 
-impl ApplicationHandler for Raymond {
+    pub fn set_clock(&mut self, clock: Box<dyn Clock>) -> &mut Self {
+        self.clock = Arc::from(clock);
+        for state in self.windows.values_mut() {
+            state.last_frame = None;
+            state.context.set_clock(self.clock.clone());
+        }
+        self
+    }
 
-    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+    pub fn set_vsync(&mut self, vsync: bool) -> &mut Self {
+        self.set_present_mode(if vsync { PresentMode::AutoVsync } else { PresentMode::AutoNoVsync })
+    }
+
+    pub fn set_present_mode(&mut self, mode: PresentMode) -> &mut Self {
+        self.present_mode = mode;
+        for state in self.windows.values_mut() {
+            state.display.set_present_mode(mode);
+        }
+        self
+    }
+
+    // 1, 2, 4 or 8, unsupported counts fall back to the largest one the adapter allows
+    pub fn set_msaa_samples(&mut self, samples: u32) -> &mut Self {
+        self.msaa_samples = samples;
+        for state in self.windows.values_mut() {
+            state.display.set_sample_count(samples);
+        }
+        self
+    }
+
+    // frame statistics for the main window
+    pub fn frame_stats(&self) -> Option<&FrameStats> {
+        self.main_window
+            .and_then(|id| self.windows.get(&id))
+            .map(|state| state.context.frame_stats())
+    }
+
+    pub fn set_loop_mode(&mut self, mode: LoopMode) -> &mut Self {
+        self.loop_mode = mode;
+        for state in self.windows.values_mut() {
+            state.fixed_timestep = Self::create_fixed_timestep(mode);
+        }
+        self
+    }
 
-        let window = match event_loop.create_window(self.window_attributes.clone()) {
+    pub fn window_ids(&self) -> impl Iterator<Item = WindowId> + '_ {
+        self.windows.keys().copied()
+    }
+
+    fn create_fixed_timestep(mode: LoopMode) -> Option<FixedTimestep> {
+        match mode {
+            LoopMode::Fixed { updates_per_second, max_updates_per_frame } => Some(FixedTimestep::new(updates_per_second, max_updates_per_frame)),
+            _ => None
+        }
+    }
+
+    fn open_window(&mut self, event_loop: &ActiveEventLoop, attributes: WindowAttributes, handler: Box<dyn EventHandler>) -> Option<WindowId> {
+
+        let window = match event_loop.create_window(attributes) {
             Ok(window) => Arc::new(window),
             Err(e) => {
                 error!("Error creating window: {}", e);
-                return;
+                return None;
             }
         };
 
+        // every window after the first shares the same device and caches
+        let display = match self.gpu {
+            Some(ref gpu) => Display::with_gpu(window.clone(), gpu, self.present_mode, self.msaa_samples),
+            None => Display::new(window.clone(), self.present_mode, self.msaa_samples)
+        };
+        if self.gpu.is_none() {
+            self.gpu = Some(display.gpu().clone());
+        }
+
+        let id = window.id();
+        let mut context = Context::for_window(id, self.clock.clone());
+        context.set_window_metrics(window.inner_size(), window.scale_factor());
+        #[cfg(feature = "egui")]
+        let egui = EguiBackend::new(context.egui().clone(), &window, display.max_texture_side());
 
-        let display = Display::new(window.clone());
-        self.display = Some(display);
-        self.window = Some(window.clone());
-        self.handler.on_init();
+        self.windows.insert(id, WindowState {
+            window,
+            display,
+            handler,
+            context,
+            renderer: Renderer::new(),
+            fixed_timestep: Self::create_fixed_timestep(self.loop_mode),
+            last_frame: None,
+            #[cfg(feature = "egui")]
+            egui
+        });
+
+        if let Some(state) = self.windows.get_mut(&id) {
+            state.handler.on_init(&mut state.context);
+        }
+        self.apply_context_commands(event_loop, id);
 
+        Some(id)
     }
 
-    fn window_event(&mut self, event_loop: &ActiveEventLoop, _window_id: WindowId, event: WindowEvent) {
+    fn close_window(&mut self, event_loop: &ActiveEventLoop, id: WindowId) {
+        self.windows.remove(&id);
 
-        let Some(ref mut display) = self.display else {
-            panic!("No display found");
+        // closing the main window or the last window ends the app
+        if self.main_window == Some(id) || self.windows.is_empty() {
+            event_loop.exit();
+        }
+    }
+
+    fn request_close(&mut self, event_loop: &ActiveEventLoop, id: WindowId) {
+        let Some(state) = self.windows.get_mut(&id) else {
+            return;
         };
+        if state.handler.on_close() {
+            self.close_window(event_loop, id);
+        }
+    }
 
-        match event {
-            WindowEvent::RedrawRequested => {
-                
-                // start the frame timer
-                self.start = std::time::Instant::now();
-                
-                // call the update handler
-                self.handler.on_update(self.elapsed_since_last_frame);
-                
-                // call the draw handler
-                self.handler.on_draw(&mut self.renderer);
-                
-                // render the frame
-                display.render(&mut self.renderer);
-                
-                // clear the renderer
-                self.renderer.end_frame();
-                
-                // sleep to reach target fps
-                if let Some(target_frame_time) = self.target_frame_time {
-                    let sleep_time = target_frame_time - self.start.elapsed().as_secs_f32();
-                    if sleep_time > 0.0 {
-                        thread::sleep(Duration::from_secs_f32(sleep_time));
+    fn apply_context_commands(&mut self, event_loop: &ActiveEventLoop, id: WindowId) {
+        let Some(state) = self.windows.get_mut(&id) else {
+            return;
+        };
+
+        let window = state.window.clone();
+        let new_windows = state.context.take_new_windows();
+        let mut close = false;
+
+        for command in state.context.take_commands() {
+            match command {
+                WindowCommand::SetTitle(title) => window.set_title(&title),
+                WindowCommand::SetFullscreen(true) => window.set_fullscreen(Some(Fullscreen::Borderless(None))),
+                WindowCommand::SetFullscreen(false) => window.set_fullscreen(None),
+                WindowCommand::SetSize(size) => {
+                    // some platforms resize immediately and will not send a resized event
+                    if let Some(new_size) = window.request_inner_size(size) {
+                        state.resize(new_size);
+                    }
+                }
+                WindowCommand::SetCursorVisible(visible) => window.set_cursor_visible(visible),
+                WindowCommand::SetCursorGrab(mode) => {
+                    if let Err(e) = window.set_cursor_grab(mode) {
+                        warn!("Unable to grab cursor: {}", e);
                     }
                 }
+                WindowCommand::SetCursorIcon(icon) => window.set_cursor(icon),
+                WindowCommand::SetPresentMode(mode) => state.display.set_present_mode(mode),
+                WindowCommand::SetMsaaSamples(samples) => state.display.set_sample_count(samples),
+                WindowCommand::Redraw => window.request_redraw(),
+                WindowCommand::Close => close = true,
+                WindowCommand::Quit => event_loop.exit()
+            }
+        }
+
+        for (attributes, handler) in new_windows {
+            self.open_window(event_loop, attributes, handler);
+        }
+
+        if close {
+            self.close_window(event_loop, id);
+        }
+    }
+
+    fn input_handled(&mut self, event_loop: &ActiveEventLoop, id: WindowId) {
+        // on demand loops only redraw in response to input
+        if self.loop_mode == LoopMode::OnDemand {
+            if let Some(state) = self.windows.get_mut(&id) {
+                state.context.request_redraw();
+            }
+        }
+        self.apply_context_commands(event_loop, id);
+    }
+
+}
+
+impl ApplicationHandler for Raymond {
+
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
 
-                self.elapsed_since_last_frame = self.start.elapsed().as_secs_f32();
-                
+        // the main window is only created the first time we are resumed
+        let Some(handler) = self.handler.take() else {
+            return;
+        };
+
+        self.main_window = self.open_window(event_loop, self.window_attributes.clone(), handler);
+
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, window_id: WindowId, event: WindowEvent) {
+
+        // events can still arrive for a window we have just closed
+        let Some(state) = self.windows.get_mut(&window_id) else {
+            return;
+        };
+
+        // egui sees every event first, and input it takes for its own widgets is not passed on
+        #[cfg(feature = "egui")]
+        let egui_consumed = {
+            let (consumed, repaint) = state.egui.on_window_event(&state.window, &event);
+            if repaint {
+                state.window.request_redraw();
+            }
+            consumed
+        };
+        #[cfg(not(feature = "egui"))]
+        let egui_consumed = false;
+
+        match event {
+            WindowEvent::RedrawRequested => {
+                state.redraw(self.clock.as_ref());
+                self.redrawn = true;
+
+                // apply any window changes requested during the frame
+                self.apply_context_commands(event_loop, window_id);
             }
             WindowEvent::CloseRequested => {
-                if self.handler.on_close() {
-                    event_loop.exit();
-                }
+                self.request_close(event_loop, window_id);
             }
             WindowEvent::Resized(physical_size) => {
-                display.resize(physical_size);
+                state.resize(physical_size);
             }
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                // the new physical size arrives in a resized event that follows
+                state.display.set_scale_factor(scale_factor);
+                let size = state.context.window_size();
+                state.context.set_window_metrics(size, scale_factor);
+                state.handler.on_input_event(&mut state.context, InputEvent::ScaleFactorChanged(scale_factor));
+                self.apply_context_commands(event_loop, window_id);
+            }
+            WindowEvent::KeyboardInput { .. } | WindowEvent::MouseInput { .. } | WindowEvent::MouseWheel { .. } if egui_consumed => {}
             WindowEvent::KeyboardInput { device_id, event, .. } => {
+                let pressed = event.state == ElementState::Pressed;
                 match event.physical_key {
+                    PhysicalKey::Code(code) if pressed => {
+                        state.handler.on_input_event(&mut state.context, InputEvent::KeyboardInput(device_id, code));
+                    }
                     PhysicalKey::Code(code) => {
-
-                        // exit on escape
-                        if code == KeyCode::Escape {
-                            if self.handler.on_close() {
-                                event_loop.exit();
-                            }
-                        }
-
-                        self.handler.on_input_event(InputEvent::KeyboardInput(device_id, code));
-                    },
+                        state.handler.on_input_event(&mut state.context, InputEvent::KeyReleased(device_id, code));
+                    }
                     _ => {
                         debug!("Unhandled physical key: {:?}", event.physical_key);
                     }
                 }
+
+                // control characters such as backspace arrive as text too, they are left to the key events
+                let text = event.text.filter(|_| pressed).map(|text| text.chars().filter(|c| !c.is_control()).collect::<String>());
+                if let Some(text) = text.filter(|text| !text.is_empty()) {
+                    state.handler.on_input_event(&mut state.context, InputEvent::ReceivedText(device_id, text));
+                }
+                self.input_handled(event_loop, window_id);
+
+                // close on escape
+                if pressed && event.physical_key == PhysicalKey::Code(KeyCode::Escape) {
+                    self.request_close(event_loop, window_id);
+                }
             }
             WindowEvent::CursorMoved { device_id, position } => {
-                self.handler.on_input_event(InputEvent::CursorMoved(device_id, Vec2::new(position.x as f32, position.y as f32)));
+                let position = position.to_logical::<f32>(state.context.scale_factor());
+                state.handler.on_input_event(&mut state.context, InputEvent::CursorMoved(device_id, Vec2::new(position.x, position.y)));
+                self.input_handled(event_loop, window_id);
+            }
+            WindowEvent::MouseInput { device_id, state: button_state, button } => {
+                state.handler.on_input_event(&mut state.context, InputEvent::MouseInput(device_id, button, button_state));
+                self.input_handled(event_loop, window_id);
+            }
+            WindowEvent::MouseWheel { device_id, delta, .. } => {
+                let delta = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => Vec2::new(x, y) * SCROLL_LINE,
+                    MouseScrollDelta::PixelDelta(position) => {
+                        let position = position.to_logical::<f32>(state.context.scale_factor());
+                        Vec2::new(position.x, position.y)
+                    }
+                };
+                state.handler.on_input_event(&mut state.context, InputEvent::MouseWheel(device_id, delta));
+                self.input_handled(event_loop, window_id);
             }
             _ => {
                 debug!("Unhandled window event: {:?}", event);
@@ -180,15 +484,20 @@ impl ApplicationHandler for Raymond {
 
     }
 
-    fn about_to_wait(&mut self, _event_loop: &ActiveEventLoop) {
-        match self.window {
-            Some(ref window) => {
-                window.request_redraw();
-            }
-            None => {
-                error!("No window found");
-            }
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        // wait to reach target fps once all the windows drawn this iteration are done
+        if std::mem::take(&mut self.redrawn) {
+            self.frame_limiter.wait(self.clock.as_ref());
+        }
+
+        if self.loop_mode == LoopMode::OnDemand {
+            event_loop.set_control_flow(ControlFlow::Wait);
+            return;
+        }
+
+        event_loop.set_control_flow(ControlFlow::Poll);
+        for state in self.windows.values() {
+            state.window.request_redraw();
         }
     }
 }
-
@@ -0,0 +1,625 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use glam::Vec2;
+use log::warn;
+use lyon::path::Path;
+use crate::graphics::draw::{CoordinateSpace, Image, Paint, Rect, Renderer, Transform2D};
+use crate::graphics::layer::LayerId;
+use crate::graphics::mesh::{CornerRadii, Mesh, MeshHandle, StrokeStyle};
+use crate::graphics::text::Font;
+
+// index into the graph plus a generation so ids of removed nodes never match a new node
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NodeId {
+    index: u32,
+    generation: u32
+}
+
+#[derive(Clone, Debug)]
+pub enum Shape {
+    Rectangle(Vec2),
+    Circle(f32),
+    Ellipse(Vec2),
+    RoundedRectangle(Vec2, CornerRadii),
+    RegularPolygon { radius: f32, sides: u16 },
+    Star { points: u16, outer_radius: f32, inner_radius: f32 },
+    // radius is the largest curve in the path, used for tessellation
+    Path { path: Path, radius: f32 }
+}
+
+impl Shape {
+
+    fn path(&self) -> Path {
+        match self {
+            Shape::Rectangle(size) => Mesh::rectangle_path(*size),
+            Shape::Circle(radius) => Mesh::ellipse_path(Vec2::splat(*radius)),
+            Shape::Ellipse(radii) => Mesh::ellipse_path(*radii),
+            Shape::RoundedRectangle(size, radii) => Mesh::rounded_rectangle_path(*size, *radii),
+            Shape::RegularPolygon { radius, sides } => Mesh::regular_polygon_path(*radius, *sides),
+            Shape::Star { points, outer_radius, inner_radius } => Mesh::star_path(*points, *outer_radius, *inner_radius),
+            Shape::Path { path, .. } => path.clone()
+        }
+    }
+
+    fn radius(&self) -> f32 {
+        match self {
+            Shape::Rectangle(size) => size.max_element(),
+            Shape::Circle(radius) => *radius,
+            Shape::Ellipse(radii) => radii.max_element(),
+            Shape::RoundedRectangle(_, radii) => radii.max(),
+            Shape::RegularPolygon { radius, .. } => *radius,
+            Shape::Star { outer_radius, .. } => *outer_radius,
+            Shape::Path { radius, .. } => *radius
+        }
+    }
+
+    fn bounds(&self) -> Rect {
+        match self {
+            Shape::Rectangle(size) | Shape::RoundedRectangle(size, _) => Rect::from_center(Vec2::ZERO, *size),
+            Shape::Ellipse(radii) => Rect::from_center(Vec2::ZERO, *radii * 2.0),
+            Shape::Path { path, .. } => {
+                let points: Vec<Vec2> = path.iter().flat_map(|event| [event.from(), event.to()]).map(|point| Vec2::new(point.x, point.y)).collect();
+                Rect::bounding(&points)
+            }
+            _ => Rect::from_center(Vec2::ZERO, Vec2::splat(self.radius() * 2.0))
+        }
+    }
+
+}
+
+// draws a node with its world transform, for anything the built in drawables do not cover
+pub type DrawFn = Box<dyn Fn(&mut Renderer, Transform2D)>;
+
+pub enum Drawable {
+    Sprite(Arc<Image>),
+    Shape { shape: Shape, paint: Paint, stroke: Option<StrokeStyle> },
+    // retained meshes keep no cpu copy, so culling needs the bounds passed in
+    Mesh { handle: MeshHandle, paint: Paint, image: Option<Arc<Image>>, bounds: Option<Rect> },
+    // the node's position is the top left corner of the text, size is in logical pixels
+    Text { text: String, font: Arc<Font>, size: f32, paint: Paint },
+    Custom { draw: DrawFn, bounds: Option<Rect> }
+}
+
+impl Drawable {
+
+    pub fn fill(shape: Shape, paint: impl Into<Paint>) -> Self {
+        Drawable::Shape { shape, paint: paint.into(), stroke: None }
+    }
+
+    pub fn stroke(shape: Shape, stroke: impl Into<StrokeStyle>, paint: impl Into<Paint>) -> Self {
+        Drawable::Shape { shape, paint: paint.into(), stroke: Some(stroke.into()) }
+    }
+
+    pub fn text(text: impl Into<String>, font: Arc<Font>, size: f32, paint: impl Into<Paint>) -> Self {
+        Drawable::Text { text: text.into(), font, size, paint: paint.into() }
+    }
+
+    // local bounds used for culling, None is never culled
+    fn bounds(&self, renderer: &Renderer) -> Option<Rect> {
+        match self {
+            Drawable::Sprite(image) => {
+                let size = Vec2::new(image.image.width() as f32, image.image.height() as f32) * renderer.logical_pixel();
+                Some(Rect::from_center(Vec2::ZERO, size))
+            }
+            Drawable::Shape { shape, stroke, .. } => {
+                let bounds = shape.bounds();
+                // strokes grow past the outline, miters can reach further than half the width
+                let grow = stroke.as_ref().map(|stroke| stroke.width * stroke.miter_limit.max(1.0) * 0.5).unwrap_or(0.0);
+                Some(Rect::new(bounds.min - grow, bounds.max + grow))
+            }
+            Drawable::Text { text, font, size, .. } => {
+                let mut size = font.measure(text, *size) * renderer.logical_pixel();
+                // the text runs down from its top left corner, which is towards negative y when y points up
+                if renderer.coordinate_space() == CoordinateSpace::Normalized {
+                    size.y = -size.y;
+                }
+                Some(Rect::new(Vec2::ZERO, size))
+            }
+            Drawable::Mesh { bounds, .. } | Drawable::Custom { bounds, .. } => *bounds
+        }
+    }
+
+    fn draw(&self, renderer: &mut Renderer, transform: Transform2D) {
+        match self {
+            Drawable::Sprite(image) => {
+                renderer.draw_image(transform, image.clone());
+            }
+            Drawable::Shape { shape, paint, stroke: None } => {
+                renderer.fill_path(transform, shape.path(), shape.radius(), paint.clone());
+            }
+            Drawable::Shape { shape, paint, stroke: Some(stroke) } => {
+                renderer.stroke_path(transform, shape.path(), shape.radius(), stroke.clone(), paint.clone());
+            }
+            Drawable::Mesh { handle, paint, image, .. } => {
+                renderer.draw_mesh(transform, handle, paint.clone(), image.clone());
+            }
+            Drawable::Text { text, font, size, paint } => {
+                renderer.draw_text(transform, text, font, *size, paint.clone());
+            }
+            Drawable::Custom { draw, .. } => draw(renderer, transform)
+        }
+    }
+
+}
+
+impl fmt::Debug for Drawable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Drawable::Sprite(image) => f.debug_tuple("Sprite").field(&image.path).finish(),
+            Drawable::Shape { shape, paint, stroke } => f.debug_struct("Shape").field("shape", shape).field("paint", paint).field("stroke", stroke).finish(),
+            Drawable::Mesh { handle, bounds, .. } => f.debug_struct("Mesh").field("handle", handle).field("bounds", bounds).finish(),
+            Drawable::Text { text, font, size, paint } => f.debug_struct("Text").field("text", text).field("font", font).field("size", size).field("paint", paint).finish(),
+            Drawable::Custom { bounds, .. } => f.debug_struct("Custom").field("bounds", bounds).finish()
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Node {
+    name: Option<String>,
+    pub transform: Transform2D,
+    // hiding a node hides everything below it
+    pub visible: bool,
+    pub z: f32,
+    // None draws on the parent's layer, or the renderer's current one at the root
+    pub layer: Option<LayerId>,
+    pub drawable: Option<Drawable>,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    world: Transform2D
+}
+
+impl Node {
+
+    pub fn new() -> Self {
+        Self {
+            name: None,
+            transform: Transform2D::default(),
+            visible: true,
+            z: 0.0,
+            layer: None,
+            drawable: None,
+            parent: None,
+            children: Vec::new(),
+            world: Transform2D::default()
+        }
+    }
+
+    pub fn named(name: &str) -> Self {
+        Self { name: Some(name.to_string()), ..Self::new() }
+    }
+
+    pub fn with_transform(mut self, transform: Transform2D) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    pub fn with_drawable(mut self, drawable: Drawable) -> Self {
+        self.drawable = Some(drawable);
+        self
+    }
+
+    pub fn with_z(mut self, z: f32) -> Self {
+        self.z = z;
+        self
+    }
+
+    pub fn with_layer(mut self, layer: LayerId) -> Self {
+        self.layer = Some(layer);
+        self
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    pub fn children(&self) -> &[NodeId] {
+        &self.children
+    }
+
+    // as of the last update_transforms or draw
+    pub fn world_transform(&self) -> Transform2D {
+        self.world
+    }
+
+}
+
+impl Default for Node {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct Slot {
+    generation: u32,
+    node: Option<Node>
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SceneStats {
+    pub drawn: usize,
+    pub culled: usize
+}
+
+#[derive(Default)]
+pub struct SceneGraph {
+    slots: Vec<Slot>,
+    free: Vec<u32>,
+    roots: Vec<NodeId>,
+    names: HashMap<String, NodeId>,
+    culling: bool
+}
+
+impl SceneGraph {
+
+    pub fn new() -> Self {
+        Self { culling: true, ..Default::default() }
+    }
+
+    // skip nodes whose bounds fall outside what the layer's camera shows, on by default
+    pub fn set_culling(&mut self, culling: bool) -> &mut Self {
+        self.culling = culling;
+        self
+    }
+
+    pub fn add(&mut self, node: Node) -> NodeId {
+        let id = self.insert(node);
+        self.roots.push(id);
+        id
+    }
+
+    pub fn add_child(&mut self, parent: NodeId, node: Node) -> Option<NodeId> {
+        if !self.contains(parent) {
+            warn!("Parent {:?} is not in the scene", parent);
+            return None;
+        }
+        let id = self.insert(node);
+        self.attach(id, parent);
+        Some(id)
+    }
+
+    fn insert(&mut self, node: Node) -> NodeId {
+        if let Some(ref name) = node.name {
+            if self.names.contains_key(name) {
+                warn!("Node name {} is already taken, find will return the newer node", name);
+            }
+        }
+
+        let id = match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                slot.node = Some(node);
+                NodeId { index, generation: slot.generation }
+            }
+            None => {
+                self.slots.push(Slot { generation: 0, node: Some(node) });
+                NodeId { index: (self.slots.len() - 1) as u32, generation: 0 }
+            }
+        };
+
+        if let Some(name) = self.slots[id.index as usize].node.as_ref().and_then(|node| node.name.clone()) {
+            self.names.insert(name, id);
+        }
+        id
+    }
+
+    // removes the node and everything below it
+    pub fn remove(&mut self, id: NodeId) -> bool {
+        if !self.contains(id) {
+            return false;
+        }
+        self.detach(id);
+
+        let mut pending = vec![id];
+        while let Some(id) = pending.pop() {
+            let slot = &mut self.slots[id.index as usize];
+            let Some(node) = slot.node.take() else {
+                continue;
+            };
+            slot.generation = slot.generation.wrapping_add(1);
+            self.free.push(id.index);
+            if let Some(name) = node.name {
+                if self.names.get(&name) == Some(&id) {
+                    self.names.remove(&name);
+                }
+            }
+            pending.extend(node.children);
+        }
+        true
+    }
+
+    // moves a node under a new parent, or to the top level with None, keeping its local transform
+    pub fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>) -> bool {
+        if !self.contains(id) || parent.is_some_and(|parent| !self.contains(parent)) {
+            return false;
+        }
+        // a node cannot end up below itself
+        if let Some(parent) = parent {
+            let mut ancestor = Some(parent);
+            while let Some(current) = ancestor {
+                if current == id {
+                    warn!("Cannot parent {:?} to its own descendant {:?}", id, parent);
+                    return false;
+                }
+                ancestor = self.get(current).and_then(|node| node.parent);
+            }
+        }
+
+        self.detach(id);
+        match parent {
+            Some(parent) => self.attach(id, parent),
+            None => self.roots.push(id)
+        }
+        true
+    }
+
+    fn attach(&mut self, id: NodeId, parent: NodeId) {
+        if let Some(node) = self.get_mut(id) {
+            node.parent = Some(parent);
+        }
+        if let Some(parent) = self.get_mut(parent) {
+            parent.children.push(id);
+        }
+    }
+
+    fn detach(&mut self, id: NodeId) {
+        match self.get_mut(id).and_then(|node| node.parent.take()) {
+            Some(parent) => {
+                if let Some(parent) = self.get_mut(parent) {
+                    parent.children.retain(|child| *child != id);
+                }
+            }
+            None => self.roots.retain(|root| *root != id)
+        }
+    }
+
+    pub fn contains(&self, id: NodeId) -> bool {
+        self.get(id).is_some()
+    }
+
+    pub fn get(&self, id: NodeId) -> Option<&Node> {
+        self.slots.get(id.index as usize)
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.node.as_ref())
+    }
+
+    pub fn get_mut(&mut self, id: NodeId) -> Option<&mut Node> {
+        self.slots.get_mut(id.index as usize)
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.node.as_mut())
+    }
+
+    pub fn find(&self, name: &str) -> Option<NodeId> {
+        self.names.get(name).copied()
+    }
+
+    pub fn rename(&mut self, id: NodeId, name: Option<&str>) -> bool {
+        if let Some(name) = name {
+            if self.names.get(name).is_some_and(|owner| *owner != id) {
+                warn!("Node name {} is already taken, find will return the renamed node", name);
+            }
+        }
+        let Some(node) = self.get_mut(id) else {
+            return false;
+        };
+        let old = std::mem::replace(&mut node.name, name.map(str::to_string));
+        if let Some(old) = old {
+            if self.names.get(&old) == Some(&id) {
+                self.names.remove(&old);
+            }
+        }
+        if let Some(name) = name {
+            self.names.insert(name.to_string(), id);
+        }
+        true
+    }
+
+    pub fn roots(&self) -> &[NodeId] {
+        &self.roots
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // recomputes every world transform from the local ones, parents before children
+    pub fn update_transforms(&mut self) {
+        let mut pending: Vec<(NodeId, Transform2D)> = self.roots.iter().rev().map(|root| (*root, Transform2D::default())).collect();
+        while let Some((id, parent)) = pending.pop() {
+            let Some(node) = self.get_mut(id) else {
+                continue;
+            };
+            node.world = parent.compose(&node.transform);
+            let world = node.world;
+            pending.extend(node.children.iter().rev().map(|child| (*child, world)));
+        }
+    }
+
+    // updates transforms and draws every visible node in depth first order, children above their parent
+    pub fn draw(&mut self, renderer: &mut Renderer) -> SceneStats {
+        self.update_transforms();
+
+        let (layer, z) = (renderer.current_layer(), renderer.z());
+        let outer = renderer.current_transform();
+        let mut visible_rects: HashMap<LayerId, Rect> = HashMap::new();
+        let mut stats = SceneStats::default();
+
+        let mut pending: Vec<(NodeId, LayerId)> = self.roots.iter().rev().map(|root| (*root, layer)).collect();
+        while let Some((id, parent_layer)) = pending.pop() {
+            let Some(node) = self.get(id) else {
+                continue;
+            };
+            if !node.visible {
+                continue;
+            }
+            let node_layer = node.layer.unwrap_or(parent_layer);
+            pending.extend(node.children.iter().rev().map(|child| (*child, node_layer)));
+
+            let Some(ref drawable) = node.drawable else {
+                continue;
+            };
+
+            if self.culling {
+                let view = *visible_rects.entry(node_layer).or_insert_with(|| renderer.visible_rect(node_layer));
+                let on_screen = drawable.bounds(renderer)
+                    .map(|bounds| bounds.transformed(&outer.compose(&node.world)).intersects(&view))
+                    .unwrap_or(true);
+                if !on_screen {
+                    stats.culled += 1;
+                    continue;
+                }
+            }
+
+            renderer.set_layer(node_layer);
+            renderer.set_z(node.z);
+            drawable.draw(renderer, node.world);
+            stats.drawn += 1;
+        }
+
+        renderer.set_layer(layer);
+        renderer.set_z(z);
+        stats
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::draw::Color;
+    use winit::dpi::PhysicalSize;
+
+    #[test]
+    fn text_is_culled_by_the_space_it_covers_below_its_position() {
+        let font = Arc::new(Font::default());
+        let mut renderer = Renderer::new();
+        renderer.set_viewport(PhysicalSize::new(600, 600), 2.0);
+
+        // y points up, so text above the top edge reaches down into view and text below the bottom edge does not
+        let mut stats = |y: f32| {
+            let mut scene = SceneGraph::new();
+            scene.add(Node::named("text").with_transform(Transform2D::at(0.0, y)).with_drawable(Drawable::text("Hi", font.clone(), 16.0, Color::WHITE)));
+            let stats = scene.draw(&mut renderer);
+            (stats.drawn, stats.culled)
+        };
+        assert_eq!(stats(1.02), (1, 0));
+        assert_eq!(stats(-1.02), (0, 1));
+    }
+
+    #[test]
+    fn children_follow_their_parent() {
+        let mut scene = SceneGraph::new();
+        let parent = scene.add(Node::new());
+        let child = scene.add_child(parent, Node::new()).unwrap();
+        let grandchild = scene.add_child(child, Node::new()).unwrap();
+
+        assert_eq!(scene.roots(), &[parent]);
+        assert_eq!(scene.get(parent).unwrap().children(), &[child]);
+        assert_eq!(scene.get(grandchild).unwrap().parent(), Some(child));
+        assert_eq!(scene.len(), 3);
+
+        // moving to the top level and back under another node
+        assert!(scene.set_parent(child, None));
+        assert_eq!(scene.roots(), &[parent, child]);
+        assert!(scene.get(parent).unwrap().children().is_empty());
+        assert!(scene.set_parent(parent, Some(grandchild)));
+        assert_eq!(scene.roots(), &[child]);
+        assert_eq!(scene.get(grandchild).unwrap().children(), &[parent]);
+    }
+
+    #[test]
+    fn nodes_cannot_be_parented_below_themselves() {
+        let mut scene = SceneGraph::new();
+        let root = scene.add(Node::new());
+        let child = scene.add_child(root, Node::new()).unwrap();
+        let grandchild = scene.add_child(child, Node::new()).unwrap();
+
+        assert!(!scene.set_parent(root, Some(grandchild)));
+        assert!(!scene.set_parent(child, Some(child)));
+        // nothing moved
+        assert_eq!(scene.roots(), &[root]);
+        assert_eq!(scene.get(root).unwrap().children(), &[child]);
+        assert_eq!(scene.get(child).unwrap().children(), &[grandchild]);
+    }
+
+    #[test]
+    fn removed_ids_stay_dead_when_their_slot_is_reused() {
+        let mut scene = SceneGraph::new();
+        let root = scene.add(Node::new());
+        let child = scene.add_child(root, Node::new()).unwrap();
+        let other = scene.add(Node::new());
+
+        // removing a node takes everything below it
+        assert!(scene.remove(root));
+        assert!(!scene.contains(root) && !scene.contains(child));
+        assert!(!scene.remove(root));
+        assert_eq!(scene.roots(), &[other]);
+        assert_eq!(scene.len(), 1);
+
+        let reused = scene.add(Node::new().with_z(1.0));
+        let second = scene.add(Node::new());
+        // both freed slots are handed out again under a new generation
+        assert!([root.index, child.index].contains(&reused.index) && [root.index, child.index].contains(&second.index));
+        assert!(!scene.contains(root) && !scene.contains(child));
+        assert!(scene.get(root).is_none() && scene.get_mut(child).is_none());
+        assert!(scene.add_child(root, Node::new()).is_none());
+        assert!(!scene.set_parent(reused, Some(child)));
+        assert!(!scene.rename(root, Some("root")));
+        assert_eq!(scene.get(reused).unwrap().z, 1.0);
+        assert!(scene.contains(second));
+        assert_eq!(scene.len(), 3);
+    }
+
+    #[test]
+    fn world_transforms_compose_down_the_tree() {
+        let mut scene = SceneGraph::new();
+        let parent = scene.add(Node::new().with_transform(Transform2D::at(10.0, 0.0).with_rotation(std::f32::consts::FRAC_PI_2).with_scale(Vec2::splat(2.0))));
+        let child = scene.add_child(parent, Node::new().with_transform(Transform2D::at(1.0, 0.0))).unwrap();
+        let grandchild = scene.add_child(child, Node::new().with_transform(Transform2D::at(0.0, 1.0))).unwrap();
+        scene.update_transforms();
+
+        let origin = |scene: &SceneGraph, id: NodeId| scene.get(id).unwrap().world_transform().transform_point(Vec2::ZERO);
+        assert!(origin(&scene, parent).distance(Vec2::new(10.0, 0.0)) < 1e-5);
+        // one unit along x, turned a quarter and doubled
+        assert!(origin(&scene, child).distance(Vec2::new(10.0, 2.0)) < 1e-5);
+        assert!(origin(&scene, grandchild).distance(Vec2::new(8.0, 2.0)) < 1e-5);
+
+        // reparenting keeps the local transform, so the world one follows the new parent
+        scene.set_parent(grandchild, None);
+        scene.update_transforms();
+        assert!(origin(&scene, grandchild).distance(Vec2::new(0.0, 1.0)) < 1e-5);
+    }
+
+    #[test]
+    fn names_find_their_node() {
+        let mut scene = SceneGraph::new();
+        let player = scene.add(Node::named("player"));
+        let enemy = scene.add(Node::named("enemy"));
+        assert_eq!(scene.find("player"), Some(player));
+        assert_eq!(scene.find("nobody"), None);
+
+        assert!(scene.rename(player, Some("hero")));
+        assert_eq!(scene.find("player"), None);
+        assert_eq!(scene.find("hero"), Some(player));
+        assert_eq!(scene.get(player).unwrap().name(), Some("hero"));
+
+        // taking a name moves it, and the old owner keeping it does not free it on rename
+        assert!(scene.rename(enemy, Some("hero")));
+        assert_eq!(scene.find("hero"), Some(enemy));
+        assert!(scene.rename(player, None));
+        assert_eq!(scene.find("hero"), Some(enemy));
+
+        assert!(scene.remove(enemy));
+        assert_eq!(scene.find("hero"), None);
+        assert_eq!(scene.find("enemy"), None);
+    }
+}
@@ -0,0 +1,394 @@
+use std::collections::VecDeque;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum LoopMode {
+    // update and draw once per frame with the measured frame time
+    #[default]
+    Variable,
+    // run on_fixed_update a fixed number of times per second, catching up with an accumulator
+    Fixed { updates_per_second: u32, max_updates_per_frame: u32 },
+    // only redraw after input or an explicit request from the context
+    OnDemand
+}
+
+impl LoopMode {
+    pub fn fixed(updates_per_second: u32) -> Self {
+        LoopMode::Fixed { updates_per_second, max_updates_per_frame: 8 }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FixedTimestep {
+    step: f32,
+    max_steps: u32,
+    accumulator: f32
+}
+
+impl FixedTimestep {
+
+    pub fn new(updates_per_second: u32, max_steps: u32) -> Self {
+        Self {
+            step: 1.0 / updates_per_second.max(1) as f32,
+            max_steps: max_steps.max(1),
+            accumulator: 0.0
+        }
+    }
+
+    pub fn step(&self) -> f32 {
+        self.step
+    }
+
+    // adds the frame time and returns how many fixed steps should run this frame
+    pub fn advance(&mut self, delta: f32) -> u32 {
+        self.accumulator += delta.max(0.0);
+
+        let mut steps = (self.accumulator / self.step) as u32;
+        self.accumulator -= steps as f32 * self.step;
+
+        // spiral of death: drop the time we can never catch up on
+        if steps > self.max_steps {
+            steps = self.max_steps;
+            self.accumulator = 0.0;
+        }
+
+        steps
+    }
+
+    // how far we are between the last fixed step and the next one
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.step).clamp(0.0, 1.0)
+    }
+
+    pub fn reset(&mut self) {
+        self.accumulator = 0.0;
+    }
+
+}
+
+pub trait Clock {
+    // monotonic time since an arbitrary starting point
+    fn now(&self) -> Duration;
+
+    fn sleep(&self, duration: Duration);
+
+    fn spin_until(&self, deadline: Duration) {
+        while self.now() < deadline {
+            std::hint::spin_loop();
+        }
+    }
+}
+
+pub struct SystemClock {
+    start: Instant
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self { start: Instant::now() }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FrameLimiter {
+    target: Option<Duration>,
+    spin_threshold: Duration,
+    next_deadline: Option<Duration>
+}
+
+impl FrameLimiter {
+
+    pub fn new(target_fps: Option<u32>) -> Self {
+        Self {
+            target: target_fps.map(|fps| Duration::from_secs_f64(1.0 / fps.max(1) as f64)),
+            // os sleeps routinely overshoot by a millisecond or two, so spin for the tail end
+            spin_threshold: Duration::from_millis(2),
+            next_deadline: None
+        }
+    }
+
+    pub fn set_target_fps(&mut self, target_fps: Option<u32>) -> &mut Self {
+        self.target = target_fps.map(|fps| Duration::from_secs_f64(1.0 / fps.max(1) as f64));
+        self.next_deadline = None;
+        self
+    }
+
+    pub fn set_spin_threshold(&mut self, threshold: Duration) -> &mut Self {
+        self.spin_threshold = threshold;
+        self
+    }
+
+    pub fn target_frame_time(&self) -> Option<Duration> {
+        self.target
+    }
+
+    // blocks until the next frame is due
+    pub fn wait(&mut self, clock: &dyn Clock) {
+        let Some(target) = self.target else {
+            return;
+        };
+
+        let now = clock.now();
+        let mut deadline = self.next_deadline.unwrap_or(now);
+
+        // fell behind by more than a frame, so start again from now rather than rushing to catch up
+        if now > deadline + target {
+            deadline = now;
+        }
+
+        if deadline > now {
+            let remaining = deadline - now;
+            if remaining > self.spin_threshold {
+                clock.sleep(remaining - self.spin_threshold);
+            }
+            clock.spin_until(deadline);
+        }
+
+        self.next_deadline = Some(deadline + target);
+    }
+
+}
+
+#[derive(Clone, Debug)]
+pub struct FrameStats {
+    samples: VecDeque<f32>,
+    capacity: usize
+}
+
+impl FrameStats {
+
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity.max(1)),
+            capacity: capacity.max(1)
+        }
+    }
+
+    pub fn record(&mut self, frame_time: f32) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(frame_time);
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn last(&self) -> Option<f32> {
+        self.samples.back().copied()
+    }
+
+    pub fn min(&self) -> Option<f32> {
+        self.samples.iter().copied().reduce(f32::min)
+    }
+
+    pub fn max(&self) -> Option<f32> {
+        self.samples.iter().copied().reduce(f32::max)
+    }
+
+    pub fn average(&self) -> Option<f32> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        Some(self.samples.iter().sum::<f32>() / self.samples.len() as f32)
+    }
+
+    pub fn fps(&self) -> Option<f32> {
+        self.average().filter(|avg| *avg > 0.0).map(|avg| 1.0 / avg)
+    }
+
+    // nearest rank percentile, e.g. 0.99 for the 99th percentile frame time
+    pub fn percentile(&self, p: f32) -> Option<f32> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<f32> = self.samples.iter().copied().collect();
+        sorted.sort_by(f32::total_cmp);
+        let rank = (p.clamp(0.0, 1.0) * sorted.len() as f32).ceil() as usize;
+        Some(sorted[rank.saturating_sub(1)])
+    }
+
+}
+
+impl Default for FrameStats {
+    fn default() -> Self {
+        Self::new(240)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use super::*;
+    use crate::context::Context;
+
+    // time only moves when the code under test sleeps or spins, or when a test advances it
+    #[derive(Default)]
+    struct FakeClock {
+        now: Mutex<Duration>,
+        sleeps: Mutex<Vec<Duration>>,
+        spins: Mutex<Vec<Duration>>
+    }
+
+    impl FakeClock {
+        fn advance(&self, duration: Duration) {
+            *self.now.lock().unwrap() += duration;
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> Duration {
+            *self.now.lock().unwrap()
+        }
+
+        fn sleep(&self, duration: Duration) {
+            self.sleeps.lock().unwrap().push(duration);
+            self.advance(duration);
+        }
+
+        fn spin_until(&self, deadline: Duration) {
+            self.spins.lock().unwrap().push(deadline);
+            let mut now = self.now.lock().unwrap();
+            *now = now.max(deadline);
+        }
+    }
+
+    fn millis(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn fixed_timestep_carries_partial_steps() {
+        let mut timestep = FixedTimestep::new(4, 8);
+        assert_eq!(timestep.advance(0.1), 0);
+        assert!((timestep.alpha() - 0.4).abs() < 1e-5);
+        assert_eq!(timestep.advance(0.2), 1);
+        assert!((timestep.alpha() - 0.2).abs() < 1e-5);
+        assert_eq!(timestep.advance(0.55), 2);
+        assert!((timestep.alpha() - 0.4).abs() < 1e-5);
+    }
+
+    #[test]
+    fn fixed_timestep_clamps_to_max_steps_and_drops_the_rest() {
+        let mut timestep = FixedTimestep::new(4, 3);
+        assert_eq!(timestep.advance(10.0), 3);
+        assert_eq!(timestep.alpha(), 0.0);
+        assert_eq!(timestep.advance(0.25), 1);
+    }
+
+    #[test]
+    fn fixed_timestep_ignores_negative_time() {
+        let mut timestep = FixedTimestep::new(4, 8);
+        assert_eq!(timestep.advance(-1.0), 0);
+        assert_eq!(timestep.alpha(), 0.0);
+    }
+
+    #[test]
+    fn frame_limiter_sleeps_then_spins_to_the_deadline() {
+        let clock = FakeClock::default();
+        let mut limiter = FrameLimiter::new(Some(10));
+
+        // the first frame only sets the deadline for the next one
+        limiter.wait(&clock);
+        assert!(clock.sleeps.lock().unwrap().is_empty());
+
+        clock.advance(millis(30));
+        limiter.wait(&clock);
+        assert_eq!(*clock.sleeps.lock().unwrap(), vec![millis(68)]);
+        assert_eq!(*clock.spins.lock().unwrap(), vec![millis(100)]);
+        assert_eq!(clock.now(), millis(100));
+
+        // the next deadline follows on from the last rather than from when the frame ended
+        clock.advance(millis(99));
+        limiter.wait(&clock);
+        assert_eq!(clock.sleeps.lock().unwrap().len(), 1);
+        assert_eq!(clock.spins.lock().unwrap().last(), Some(&millis(200)));
+    }
+
+    #[test]
+    fn frame_limiter_starts_over_after_falling_behind() {
+        let clock = FakeClock::default();
+        let mut limiter = FrameLimiter::new(Some(10));
+        limiter.wait(&clock);
+
+        clock.advance(millis(450));
+        limiter.wait(&clock);
+        assert!(clock.sleeps.lock().unwrap().is_empty());
+        assert!(clock.spins.lock().unwrap().is_empty());
+
+        clock.advance(millis(40));
+        limiter.wait(&clock);
+        assert_eq!(*clock.spins.lock().unwrap(), vec![millis(550)]);
+    }
+
+    #[test]
+    fn frame_limiter_without_a_target_never_waits() {
+        let clock = FakeClock::default();
+        let mut limiter = FrameLimiter::new(None);
+        limiter.wait(&clock);
+        limiter.wait(&clock);
+        assert_eq!(clock.now(), Duration::ZERO);
+        assert!(clock.spins.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn frame_stats_summarise_the_window_of_samples() {
+        let mut stats = FrameStats::new(10);
+        assert_eq!(stats.average(), None);
+        assert_eq!(stats.percentile(0.5), None);
+
+        for i in 1..=10 {
+            stats.record(i as f32);
+        }
+        assert_eq!(stats.min(), Some(1.0));
+        assert_eq!(stats.max(), Some(10.0));
+        assert_eq!(stats.average(), Some(5.5));
+        assert_eq!(stats.percentile(0.0), Some(1.0));
+        assert_eq!(stats.percentile(0.5), Some(5.0));
+        assert_eq!(stats.percentile(0.9), Some(9.0));
+        assert_eq!(stats.percentile(0.99), Some(10.0));
+
+        // the oldest sample falls out once the window is full
+        stats.record(20.0);
+        assert_eq!(stats.len(), 10);
+        assert_eq!(stats.min(), Some(2.0));
+        assert_eq!(stats.last(), Some(20.0));
+    }
+
+    #[test]
+    fn context_elapsed_follows_its_clock() {
+        let clock = Arc::new(FakeClock::default());
+        clock.advance(millis(500));
+        let context = Context::with_clock(clock.clone());
+        assert_eq!(context.elapsed(), Duration::ZERO);
+        clock.advance(millis(1500));
+        assert_eq!(context.elapsed(), millis(1500));
+    }
+
+}
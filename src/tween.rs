@@ -0,0 +1,716 @@
+use std::cell::Cell;
+use std::f32::consts::PI;
+use std::fmt;
+use std::rc::Rc;
+use glam::Vec2;
+use crate::graphics::draw::{Color, Transform2D};
+
+// the usual easing curves, see easings.net for what each one looks like
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Easing {
+    #[default]
+    Linear,
+    QuadIn, QuadOut, QuadInOut,
+    CubicIn, CubicOut, CubicInOut,
+    QuartIn, QuartOut, QuartInOut,
+    QuintIn, QuintOut, QuintInOut,
+    SineIn, SineOut, SineInOut,
+    ExpoIn, ExpoOut, ExpoInOut,
+    CircIn, CircOut, CircInOut,
+    BackIn, BackOut, BackInOut,
+    ElasticIn, ElasticOut, ElasticInOut,
+    BounceIn, BounceOut, BounceInOut
+}
+
+#[derive(Copy, Clone)]
+enum Curve { Quad, Cubic, Quart, Quint, Sine, Expo, Circ, Back, Elastic, Bounce }
+
+#[derive(Copy, Clone)]
+enum Ease { In, Out, InOut }
+
+impl Easing {
+
+    // maps progress between 0 and 1 to eased progress, back and elastic overshoot that range
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        let (curve, ease) = match self {
+            Easing::Linear => return t,
+            Easing::QuadIn => (Curve::Quad, Ease::In),
+            Easing::QuadOut => (Curve::Quad, Ease::Out),
+            Easing::QuadInOut => (Curve::Quad, Ease::InOut),
+            Easing::CubicIn => (Curve::Cubic, Ease::In),
+            Easing::CubicOut => (Curve::Cubic, Ease::Out),
+            Easing::CubicInOut => (Curve::Cubic, Ease::InOut),
+            Easing::QuartIn => (Curve::Quart, Ease::In),
+            Easing::QuartOut => (Curve::Quart, Ease::Out),
+            Easing::QuartInOut => (Curve::Quart, Ease::InOut),
+            Easing::QuintIn => (Curve::Quint, Ease::In),
+            Easing::QuintOut => (Curve::Quint, Ease::Out),
+            Easing::QuintInOut => (Curve::Quint, Ease::InOut),
+            Easing::SineIn => (Curve::Sine, Ease::In),
+            Easing::SineOut => (Curve::Sine, Ease::Out),
+            Easing::SineInOut => (Curve::Sine, Ease::InOut),
+            Easing::ExpoIn => (Curve::Expo, Ease::In),
+            Easing::ExpoOut => (Curve::Expo, Ease::Out),
+            Easing::ExpoInOut => (Curve::Expo, Ease::InOut),
+            Easing::CircIn => (Curve::Circ, Ease::In),
+            Easing::CircOut => (Curve::Circ, Ease::Out),
+            Easing::CircInOut => (Curve::Circ, Ease::InOut),
+            Easing::BackIn => (Curve::Back, Ease::In),
+            Easing::BackOut => (Curve::Back, Ease::Out),
+            Easing::BackInOut => (Curve::Back, Ease::InOut),
+            Easing::ElasticIn => (Curve::Elastic, Ease::In),
+            Easing::ElasticOut => (Curve::Elastic, Ease::Out),
+            Easing::ElasticInOut => (Curve::Elastic, Ease::InOut),
+            Easing::BounceIn => (Curve::Bounce, Ease::In),
+            Easing::BounceOut => (Curve::Bounce, Ease::Out),
+            Easing::BounceInOut => (Curve::Bounce, Ease::InOut)
+        };
+
+        // out is in played backwards and in out is in for the first half and out for the second
+        match ease {
+            Ease::In => curve.ease_in(t),
+            Ease::Out => 1.0 - curve.ease_in(1.0 - t),
+            Ease::InOut if t < 0.5 => curve.ease_in_half(2.0 * t) * 0.5,
+            Ease::InOut => 1.0 - curve.ease_in_half(2.0 - 2.0 * t) * 0.5
+        }
+    }
+
+}
+
+impl Curve {
+    fn ease_in(self, t: f32) -> f32 {
+        match self {
+            Curve::Quad => t * t,
+            Curve::Cubic => t * t * t,
+            Curve::Quart => t.powi(4),
+            Curve::Quint => t.powi(5),
+            Curve::Sine => 1.0 - (t * PI * 0.5).cos(),
+            Curve::Expo => if t <= 0.0 { 0.0 } else { 2f32.powf(10.0 * t - 10.0) },
+            Curve::Circ => 1.0 - (1.0 - t * t).max(0.0).sqrt(),
+            Curve::Back => {
+                const OVERSHOOT: f32 = 1.70158;
+                (OVERSHOOT + 1.0) * t * t * t - OVERSHOOT * t * t
+            }
+            Curve::Elastic => {
+                if t <= 0.0 || t >= 1.0 {
+                    return t;
+                }
+                -(2f32.powf(10.0 * t - 10.0)) * ((10.0 * t - 10.75) * (2.0 * PI / 3.0)).sin()
+            }
+            Curve::Bounce => 1.0 - Self::bounce_out(1.0 - t)
+        }
+    }
+
+    // the halves of in out, back and elastic use the larger overshoot and longer period of the usual curves
+    fn ease_in_half(self, t: f32) -> f32 {
+        match self {
+            Curve::Back => {
+                const OVERSHOOT: f32 = 1.70158 * 1.525;
+                (OVERSHOOT + 1.0) * t * t * t - OVERSHOOT * t * t
+            }
+            Curve::Elastic => {
+                if t <= 0.0 || t >= 1.0 {
+                    return t;
+                }
+                -(2f32.powf(10.0 * t - 10.0)) * ((10.0 * t - 11.125) * (2.0 * PI / 4.5)).sin()
+            }
+            _ => self.ease_in(t)
+        }
+    }
+
+    fn bounce_out(t: f32) -> f32 {
+        const N: f32 = 7.5625;
+        const D: f32 = 2.75;
+        if t < 1.0 / D {
+            N * t * t
+        } else if t < 2.0 / D {
+            let t = t - 1.5 / D;
+            N * t * t + 0.75
+        } else if t < 2.5 / D {
+            let t = t - 2.25 / D;
+            N * t * t + 0.9375
+        } else {
+            let t = t - 2.625 / D;
+            N * t * t + 0.984375
+        }
+    }
+}
+
+// values a tween can move between, t is eased progress and may leave 0 to 1 for back and elastic
+pub trait Lerp {
+    fn lerp(&self, to: &Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        self + (to - self) * t
+    }
+}
+
+impl Lerp for Vec2 {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        Vec2::lerp(*self, *to, t)
+    }
+}
+
+impl Lerp for Color {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        Color::new(self.r.lerp(&to.r, t), self.g.lerp(&to.g, t), self.b.lerp(&to.b, t), self.a.lerp(&to.a, t))
+    }
+}
+
+// every part is blended on its own, rotation takes the direct route between the two angles as given
+impl Lerp for Transform2D {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        Transform2D {
+            position: self.position.lerp(to.position, t),
+            scale: self.scale.lerp(to.scale, t),
+            rotation: self.rotation.lerp(&to.rotation, t),
+            origin: self.origin.lerp(to.origin, t),
+            skew: self.skew.lerp(to.skew, t)
+        }
+    }
+}
+
+// anything that can be placed on a timeline, tweens and timelines themselves included
+pub trait Animation {
+    // time from the start to the end of the last repeat, None when it repeats forever
+    fn total_duration(&self) -> Option<f32>;
+    // jumps to a time measured from the start and applies the state at that point
+    fn seek(&mut self, time: f32);
+}
+
+// delay, repeats and completion shared by tweens and timelines
+#[derive(Default)]
+struct Playback {
+    delay: f32,
+    // extra plays after the first, None repeats forever
+    repeat: Option<u32>,
+    // every other play runs backwards
+    yoyo: bool,
+    elapsed: f32,
+    finished: bool,
+    on_complete: Option<Box<dyn FnMut()>>
+}
+
+impl Playback {
+
+    fn new() -> Self {
+        Self { repeat: Some(0), ..Default::default() }
+    }
+
+    fn total(&self, duration: f32) -> Option<f32> {
+        self.repeat.map(|repeat| self.delay + duration * (repeat + 1) as f32)
+    }
+
+    // time within the current play for a time since the start, calls on_complete when the end is crossed
+    fn local(&mut self, time: f32, duration: f32) -> f32 {
+        self.elapsed = time;
+        let total = self.total(duration);
+        let finished = total.is_some_and(|total| time >= total);
+        if finished && !self.finished {
+            self.finished = true;
+            if let Some(ref mut on_complete) = self.on_complete {
+                on_complete();
+            }
+        }
+        self.finished = finished;
+
+        let time = time - self.delay;
+        if time <= 0.0 {
+            return 0.0;
+        }
+        if duration <= 0.0 {
+            return duration;
+        }
+
+        let mut play = (time / duration).floor();
+        let mut local = time - play * duration;
+        if let Some(repeat) = self.repeat {
+            if play > repeat as f32 {
+                play = repeat as f32;
+                local = duration;
+            }
+        }
+        // landing exactly on a boundary ends the previous play rather than starting the next
+        if local == 0.0 && play > 0.0 {
+            play -= 1.0;
+            local = duration;
+        }
+        if self.yoyo && play as u32 % 2 == 1 { duration - local } else { local }
+    }
+
+}
+
+impl fmt::Debug for Playback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Playback")
+            .field("delay", &self.delay)
+            .field("repeat", &self.repeat)
+            .field("yoyo", &self.yoyo)
+            .field("elapsed", &self.elapsed)
+            .field("finished", &self.finished)
+            .finish()
+    }
+}
+
+// moves a value from one end to the other over duration seconds, poll it with update and value or
+// give it a shared target when it is part of a timeline
+#[derive(Debug)]
+pub struct Tween<T: Lerp + Copy> {
+    from: T,
+    to: T,
+    duration: f32,
+    easing: Easing,
+    value: T,
+    target: Option<Rc<Cell<T>>>,
+    playback: Playback
+}
+
+impl<T: Lerp + Copy> Tween<T> {
+
+    pub fn new(from: T, to: T, duration: f32) -> Self {
+        Self {
+            from,
+            to,
+            duration: duration.max(0.0),
+            easing: Easing::Linear,
+            value: from,
+            target: None,
+            playback: Playback::new()
+        }
+    }
+
+    pub fn with_easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    // every update writes the value into target as well
+    pub fn with_target(mut self, target: Rc<Cell<T>>) -> Self {
+        target.set(self.value);
+        self.target = Some(target);
+        self
+    }
+
+    pub fn with_delay(mut self, delay: f32) -> Self {
+        self.playback.delay = delay.max(0.0);
+        self
+    }
+
+    // plays count more times after the first
+    pub fn with_repeat(mut self, count: u32) -> Self {
+        self.playback.repeat = Some(count);
+        self
+    }
+
+    pub fn repeat_forever(mut self) -> Self {
+        self.playback.repeat = None;
+        self
+    }
+
+    // repeats run backwards every other time instead of jumping back to the start
+    pub fn with_yoyo(mut self, yoyo: bool) -> Self {
+        self.playback.yoyo = yoyo;
+        self
+    }
+
+    pub fn on_complete(mut self, on_complete: impl FnMut() + 'static) -> Self {
+        self.playback.on_complete = Some(Box::new(on_complete));
+        self
+    }
+
+    // advances by delta seconds, as passed to on_update, and returns the new value
+    pub fn update(&mut self, delta: f32) -> T {
+        self.seek(self.playback.elapsed + delta);
+        self.value
+    }
+
+    pub fn value(&self) -> T {
+        self.value
+    }
+
+    pub fn elapsed(&self) -> f32 {
+        self.playback.elapsed
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.playback.finished
+    }
+
+    pub fn reset(&mut self) {
+        self.seek(0.0);
+    }
+
+}
+
+impl<T: Lerp + Copy> Animation for Tween<T> {
+
+    fn total_duration(&self) -> Option<f32> {
+        self.playback.total(self.duration)
+    }
+
+    fn seek(&mut self, time: f32) {
+        let local = self.playback.local(time, self.duration);
+        // without a duration the tween jumps to the end once its delay is over
+        let t = if self.duration > 0.0 {
+            local / self.duration
+        } else if time < self.playback.delay {
+            0.0
+        } else {
+            1.0
+        };
+        self.value = self.from.lerp(&self.to, self.easing.apply(t));
+        if let Some(ref target) = self.target {
+            target.set(self.value);
+        }
+    }
+
+}
+
+// waits on a timeline without changing anything
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Delay(pub f32);
+
+impl Animation for Delay {
+
+    fn total_duration(&self) -> Option<f32> {
+        Some(self.0.max(0.0))
+    }
+
+    fn seek(&mut self, _time: f32) {}
+
+}
+
+struct Track {
+    animation: Box<dyn Animation>,
+    // last time the animation was moved to, so ones that have not started yet are left alone
+    time: f32
+}
+
+// plays animations one after another or all at once, timelines nest inside each other
+pub struct Timeline {
+    tracks: Vec<Track>,
+    parallel: bool,
+    playback: Playback
+}
+
+impl Timeline {
+
+    pub fn sequence() -> Self {
+        Self { tracks: Vec::new(), parallel: false, playback: Playback::new() }
+    }
+
+    pub fn parallel() -> Self {
+        Self { parallel: true, ..Self::sequence() }
+    }
+
+    pub fn with(mut self, animation: impl Animation + 'static) -> Self {
+        self.add(animation);
+        self
+    }
+
+    pub fn add(&mut self, animation: impl Animation + 'static) -> &mut Self {
+        self.tracks.push(Track { animation: Box::new(animation), time: 0.0 });
+        self
+    }
+
+    pub fn with_delay(mut self, delay: f32) -> Self {
+        self.playback.delay = delay.max(0.0);
+        self
+    }
+
+    pub fn with_repeat(mut self, count: u32) -> Self {
+        self.playback.repeat = Some(count);
+        self
+    }
+
+    pub fn repeat_forever(mut self) -> Self {
+        self.playback.repeat = None;
+        self
+    }
+
+    pub fn with_yoyo(mut self, yoyo: bool) -> Self {
+        self.playback.yoyo = yoyo;
+        self
+    }
+
+    pub fn on_complete(mut self, on_complete: impl FnMut() + 'static) -> Self {
+        self.playback.on_complete = Some(Box::new(on_complete));
+        self
+    }
+
+    pub fn update(&mut self, delta: f32) {
+        self.seek(self.playback.elapsed + delta);
+    }
+
+    pub fn elapsed(&self) -> f32 {
+        self.playback.elapsed
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.playback.finished
+    }
+
+    pub fn reset(&mut self) {
+        self.seek(0.0);
+    }
+
+    // one play through the tracks, an animation that repeats forever makes it endless
+    fn duration(&self) -> f32 {
+        let durations = self.tracks.iter().map(|track| track.animation.total_duration().unwrap_or(f32::INFINITY));
+        if self.parallel {
+            durations.fold(0.0, f32::max)
+        } else {
+            durations.sum()
+        }
+    }
+
+}
+
+impl Animation for Timeline {
+
+    fn total_duration(&self) -> Option<f32> {
+        let duration = self.duration();
+        if duration.is_infinite() {
+            return None;
+        }
+        self.playback.total(duration)
+    }
+
+    fn seek(&mut self, time: f32) {
+        let duration = self.duration();
+        let local = self.playback.local(time, duration);
+
+        if self.parallel {
+            for track in self.tracks.iter_mut() {
+                track.animation.seek(local);
+                track.time = local;
+            }
+            return;
+        }
+
+        let mut starts = Vec::with_capacity(self.tracks.len());
+        let mut start = 0.0;
+        for track in self.tracks.iter() {
+            starts.push(start);
+            start += track.animation.total_duration().unwrap_or(f32::INFINITY);
+        }
+
+        // tracks that have not started are rewound last to first when playing backwards, then the
+        // rest are played up to now in order so later tracks win when they share a target
+        for (track, start) in self.tracks.iter_mut().zip(starts.iter()).rev() {
+            if local < *start && track.time > 0.0 {
+                track.animation.seek(0.0);
+                track.time = 0.0;
+            }
+        }
+        for (track, start) in self.tracks.iter_mut().zip(starts.iter()) {
+            if local >= *start {
+                track.time = local - start;
+                track.animation.seek(track.time);
+            }
+        }
+    }
+
+}
+
+impl fmt::Debug for Timeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Timeline")
+            .field("tracks", &self.tracks.len())
+            .field("parallel", &self.parallel)
+            .field("playback", &self.playback)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Easing; 31] = [
+        Easing::Linear,
+        Easing::QuadIn, Easing::QuadOut, Easing::QuadInOut,
+        Easing::CubicIn, Easing::CubicOut, Easing::CubicInOut,
+        Easing::QuartIn, Easing::QuartOut, Easing::QuartInOut,
+        Easing::QuintIn, Easing::QuintOut, Easing::QuintInOut,
+        Easing::SineIn, Easing::SineOut, Easing::SineInOut,
+        Easing::ExpoIn, Easing::ExpoOut, Easing::ExpoInOut,
+        Easing::CircIn, Easing::CircOut, Easing::CircInOut,
+        Easing::BackIn, Easing::BackOut, Easing::BackInOut,
+        Easing::ElasticIn, Easing::ElasticOut, Easing::ElasticInOut,
+        Easing::BounceIn, Easing::BounceOut, Easing::BounceInOut
+    ];
+
+    fn assert_close(easing: Easing, t: f32, expected: f32) {
+        let value = easing.apply(t);
+        assert!((value - expected).abs() < 1e-4, "{easing:?} at {t} gave {value}, expected {expected}");
+    }
+
+    fn playback(repeat: Option<u32>, yoyo: bool) -> Playback {
+        Playback { repeat, yoyo, ..Playback::new() }
+    }
+
+    #[test]
+    fn playback_repeats_and_yoyos() {
+        let mut repeat = playback(Some(2), false);
+        let locals: Vec<f32> = [0.5, 1.0, 1.25, 2.0, 2.5, 3.0, 10.0].map(|time| repeat.local(time, 1.0)).to_vec();
+        assert_eq!(locals, vec![0.5, 1.0, 0.25, 1.0, 0.5, 1.0, 1.0]);
+
+        let mut yoyo = playback(Some(2), true);
+        let locals: Vec<f32> = [0.5, 1.0, 1.25, 2.0, 2.5, 3.0, 10.0].map(|time| yoyo.local(time, 1.0)).to_vec();
+        assert_eq!(locals, vec![0.5, 1.0, 0.75, 0.0, 0.5, 1.0, 1.0]);
+
+        let mut forever = playback(None, true);
+        assert_eq!(forever.local(1001.25, 1.0), 0.75);
+        assert!(!forever.finished);
+    }
+
+    #[test]
+    fn playback_waits_out_its_delay() {
+        let mut delayed = Playback { delay: 0.5, ..Playback::new() };
+        assert_eq!(delayed.local(0.25, 1.0), 0.0);
+        assert_eq!(delayed.local(1.0, 1.0), 0.5);
+        assert_eq!(delayed.total(1.0), Some(1.5));
+        assert!(!delayed.finished);
+        delayed.local(1.5, 1.0);
+        assert!(delayed.finished);
+    }
+
+    #[test]
+    fn on_complete_fires_once_per_finish() {
+        let count = Rc::new(Cell::new(0));
+        let counter = count.clone();
+        let mut tween = Tween::new(0.0, 1.0, 1.0).with_repeat(1).on_complete(move || counter.set(counter.get() + 1));
+        tween.update(1.5);
+        assert_eq!(count.get(), 0);
+        tween.update(0.5);
+        tween.update(0.5);
+        tween.update(5.0);
+        assert_eq!(count.get(), 1);
+        assert!(tween.is_finished());
+
+        // going back before the end and finishing again counts again
+        tween.reset();
+        assert!(!tween.is_finished());
+        tween.update(2.0);
+        assert_eq!(count.get(), 2);
+    }
+
+    #[test]
+    fn tweens_hold_their_start_through_the_delay() {
+        let mut tween = Tween::new(1.0, 3.0, 2.0).with_delay(1.0).with_easing(Easing::QuadIn);
+        assert_eq!(tween.update(0.5), 1.0);
+        assert_eq!(tween.update(1.5), 1.5);
+        assert_eq!(tween.update(5.0), 3.0);
+
+        let mut instant = Tween::new(1.0, 3.0, 0.0).with_delay(1.0);
+        assert_eq!(instant.update(0.5), 1.0);
+        assert_eq!(instant.update(0.5), 3.0);
+        assert_eq!(Tween::new(1.0, 3.0, 0.0).update(0.0), 3.0);
+    }
+
+    #[test]
+    fn sequences_rewind_and_replay_shared_targets() {
+        let value = Rc::new(Cell::new(-1.0));
+        let mut timeline = Timeline::sequence()
+            .with(Tween::new(0.0, 1.0, 1.0).with_target(value.clone()))
+            .with(Delay(0.5))
+            .with(Tween::new(1.0, 2.0, 1.0).with_target(value.clone()))
+            .with_repeat(1);
+        // the tween that starts later does not touch the target before it starts
+        assert_eq!(value.get(), 1.0);
+        timeline.seek(0.5);
+        assert_eq!(value.get(), 0.5);
+        timeline.seek(1.25);
+        assert_eq!(value.get(), 1.0);
+        timeline.seek(2.0);
+        assert_eq!(value.get(), 1.5);
+        assert_eq!(timeline.total_duration(), Some(5.0));
+
+        // seeking back rewinds the later tween first so the earlier one has the last word
+        timeline.seek(0.25);
+        assert_eq!(value.get(), 0.25);
+
+        // the repeat plays the whole sequence again
+        timeline.seek(2.5);
+        assert_eq!(value.get(), 2.0);
+        timeline.seek(3.0);
+        assert_eq!(value.get(), 0.5);
+        timeline.seek(4.5);
+        assert_eq!(value.get(), 1.5);
+        assert!(!timeline.is_finished());
+        timeline.seek(5.0);
+        assert_eq!(value.get(), 2.0);
+        assert!(timeline.is_finished());
+    }
+
+    #[test]
+    fn yoyo_sequences_play_backwards() {
+        let value = Rc::new(Cell::new(0.0));
+        let mut timeline = Timeline::sequence()
+            .with(Tween::new(0.0, 1.0, 1.0).with_target(value.clone()))
+            .with(Tween::new(1.0, 2.0, 1.0).with_target(value.clone()))
+            .with_repeat(1)
+            .with_yoyo(true);
+        timeline.seek(2.0);
+        assert_eq!(value.get(), 2.0);
+        timeline.seek(2.5);
+        assert_eq!(value.get(), 1.5);
+        timeline.seek(3.5);
+        assert_eq!(value.get(), 0.5);
+        timeline.seek(4.0);
+        assert_eq!(value.get(), 0.0);
+    }
+
+    #[test]
+    fn parallel_timelines_last_as_long_as_their_longest_track() {
+        let (a, b) = (Rc::new(Cell::new(0.0)), Rc::new(Cell::new(0.0)));
+        let mut timeline = Timeline::parallel()
+            .with(Tween::new(0.0, 1.0, 1.0).with_target(a.clone()))
+            .with(Tween::new(0.0, 4.0, 2.0).with_target(b.clone()))
+            .with_delay(1.0);
+        assert_eq!(timeline.total_duration(), Some(3.0));
+        timeline.update(2.0);
+        assert_eq!((a.get(), b.get()), (1.0, 2.0));
+        timeline.update(1.0);
+        assert!(timeline.is_finished());
+        assert!(Timeline::sequence().with(Tween::new(0.0, 1.0, 1.0).repeat_forever()).total_duration().is_none());
+    }
+
+    #[test]
+    fn every_easing_starts_at_zero_and_ends_at_one() {
+        for easing in ALL {
+            assert_close(easing, 0.0, 0.0);
+            assert_close(easing, 1.0, 1.0);
+        }
+    }
+
+    #[test]
+    fn in_out_easings_pass_through_the_midpoint() {
+        for easing in ALL.into_iter().filter(|easing| format!("{easing:?}").ends_with("InOut")) {
+            assert_close(easing, 0.5, 0.5);
+        }
+        assert_close(Easing::Linear, 0.5, 0.5);
+    }
+
+    #[test]
+    fn back_and_elastic_in_out_match_easings_net() {
+        // easeInOutBack and easeInOutElastic from easings.net evaluated at 0.2 and 0.8
+        assert_close(Easing::BackInOut, 0.2, -0.0925557);
+        assert_close(Easing::BackInOut, 0.8, 1.0925557);
+        assert_close(Easing::ElasticInOut, 0.2, -0.0039063);
+        assert_close(Easing::ElasticInOut, 0.8, 1.0039063);
+    }
+
+    #[test]
+    fn out_easings_mirror_in_easings() {
+        for (ease_in, ease_out) in [(Easing::QuadIn, Easing::QuadOut), (Easing::BackIn, Easing::BackOut), (Easing::ElasticIn, Easing::ElasticOut), (Easing::BounceIn, Easing::BounceOut)] {
+            for t in [0.1, 0.3, 0.7] {
+                assert_close(ease_out, t, 1.0 - ease_in.apply(1.0 - t));
+            }
+        }
+    }
+}
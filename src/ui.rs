@@ -0,0 +1,970 @@
+// immediate mode widgets, rebuilt every frame between begin and end and laid out in logical pixels
+// with y down from the top left corner
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+use glam::Vec2;
+use winit::event::{ElementState, MouseButton};
+use winit::keyboard::KeyCode;
+use crate::InputEvent;
+use crate::graphics::draw::{Color, CoordinateSpace, Rect, Renderer, Transform2D};
+use crate::graphics::mesh::CornerRadii;
+use crate::graphics::text::Font;
+
+const SCROLLBAR_WIDTH: f32 = 8.0;
+const MIN_THUMB_HEIGHT: f32 = 16.0;
+const FOCUS_OUTLINE: f32 = 1.5;
+
+#[derive(Clone, Debug)]
+pub struct Theme {
+    pub font: Arc<Font>,
+    pub text_size: f32,
+    pub text: Color,
+    // hints in empty text fields
+    pub weak_text: Color,
+    pub panel: Color,
+    pub title_bar: Color,
+    // behind text fields, slider tracks and scroll areas
+    pub field: Color,
+    pub widget: Color,
+    pub hovered: Color,
+    // a widget while the mouse button is held on it
+    pub active: Color,
+    // checks, slider fills, scrollbars and the focus outline
+    pub accent: Color,
+    pub corner_radius: f32,
+    // between a widget's frame and its text
+    pub padding: Vec2,
+    // between widgets
+    pub spacing: f32,
+    // sliders and text fields, narrower when there is less room
+    pub field_width: f32
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+impl Theme {
+
+    pub fn dark() -> Self {
+        Self {
+            font: Arc::new(Font::default()),
+            text_size: 16.0,
+            text: Color::new(0.9, 0.9, 0.9, 1.0),
+            weak_text: Color::new(0.5, 0.5, 0.52, 1.0),
+            panel: Color::new(0.12, 0.12, 0.13, 0.95),
+            title_bar: Color::new(0.2, 0.2, 0.22, 1.0),
+            field: Color::new(0.07, 0.07, 0.08, 1.0),
+            widget: Color::new(0.24, 0.24, 0.26, 1.0),
+            hovered: Color::new(0.32, 0.32, 0.35, 1.0),
+            active: Color::new(0.4, 0.4, 0.44, 1.0),
+            accent: Color::new(0.26, 0.55, 0.95, 1.0),
+            corner_radius: 4.0,
+            padding: Vec2::new(8.0, 4.0),
+            spacing: 6.0,
+            field_width: 200.0
+        }
+    }
+
+    pub fn light() -> Self {
+        Self {
+            text: Color::new(0.1, 0.1, 0.1, 1.0),
+            weak_text: Color::new(0.5, 0.5, 0.5, 1.0),
+            panel: Color::new(0.94, 0.94, 0.95, 0.95),
+            title_bar: Color::new(0.82, 0.82, 0.85, 1.0),
+            field: Color::new(1.0, 1.0, 1.0, 1.0),
+            widget: Color::new(0.85, 0.85, 0.87, 1.0),
+            hovered: Color::new(0.78, 0.78, 0.81, 1.0),
+            active: Color::new(0.7, 0.7, 0.74, 1.0),
+            accent: Color::new(0.15, 0.45, 0.9, 1.0),
+            ..Self::dark()
+        }
+    }
+
+}
+
+// the same label in the same panel or scroll area gives the same id every frame, text after "##" is
+// left out of what is shown so that widgets with the same text can be told apart
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct WidgetId(u64);
+
+impl WidgetId {
+    fn new(scope: WidgetId, label: &str) -> Self {
+        let mut hasher = DefaultHasher::new();
+        scope.0.hash(&mut hasher);
+        label.hash(&mut hasher);
+        WidgetId(hasher.finish())
+    }
+}
+
+fn display_text(label: &str) -> &str {
+    label.split("##").next().unwrap_or_default()
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Response {
+    pub id: WidgetId,
+    pub rect: Rect,
+    pub hovered: bool,
+    // the mouse button went down on it this frame
+    pub pressed: bool,
+    // released over it after being pressed on it, or activated from the keyboard while focused
+    pub clicked: bool,
+    // its value was edited this frame
+    pub changed: bool,
+    pub focused: bool,
+    // enter was pressed in a text field
+    pub submitted: bool
+}
+
+// what a frame of widgets draws, in logical pixels, replayed through the renderer by Ui::draw
+#[derive(Clone, Debug, PartialEq)]
+pub enum Primitive {
+    Rect { rect: Rect, color: Color, radius: f32 },
+    Outline { rect: Rect, color: Color, radius: f32, width: f32 },
+    // position is the top left corner of the text
+    Text { position: Vec2, text: String, size: f32, color: Color },
+    PushClip(Rect),
+    PopClip
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Typed {
+    Key(KeyCode),
+    Text(String)
+}
+
+// input gathered between frames, only the primary mouse button is used
+#[derive(Clone, Debug, Default)]
+struct Input {
+    pointer: Option<Vec2>,
+    down: bool,
+    pressed: bool,
+    released: bool,
+    typed: Vec<Typed>,
+    scroll: Vec2
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Direction {
+    Row,
+    Column
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Region {
+    // ids of the widgets inside are made from this one
+    scope: WidgetId,
+    // the panel the region is in, widgets only respond when it is the panel under the pointer
+    panel: Option<WidgetId>,
+    // widgets are placed from cursor and shrink to fit before the right edge
+    rect: Rect,
+    cursor: Vec2,
+    direction: Direction,
+    used: Option<Rect>,
+    clip: Rect
+}
+
+// state that outlives a frame, keyed by the widget it belongs to
+#[derive(Clone, Debug, Default)]
+struct Memory {
+    scroll: HashMap<WidgetId, f32>,
+    content_height: HashMap<WidgetId, f32>,
+    // in characters
+    caret: HashMap<WidgetId, usize>
+}
+
+#[derive(Debug)]
+pub struct Ui {
+    pub theme: Theme,
+    pending: Input,
+    input: Input,
+    regions: Vec<Region>,
+    primitives: Vec<Primitive>,
+    memory: Memory,
+    // held down on
+    active: Option<WidgetId>,
+    focused: Option<WidgetId>,
+    hovered: Option<WidgetId>,
+    // focusable widgets in the order they were made this frame, for tab
+    focus_order: Vec<WidgetId>,
+    // the press this frame landed on a focusable widget
+    press_focused: bool,
+    panels: Vec<(WidgetId, Rect)>,
+    previous_panels: Vec<(WidgetId, Rect)>,
+    top_panel: Option<WidgetId>
+}
+
+impl Default for Ui {
+    fn default() -> Self {
+        Self::new(Theme::default())
+    }
+}
+
+impl Ui {
+
+    pub fn new(theme: Theme) -> Self {
+        Self {
+            theme,
+            pending: Input::default(),
+            input: Input::default(),
+            regions: Vec::new(),
+            primitives: Vec::new(),
+            memory: Memory::default(),
+            active: None,
+            focused: None,
+            hovered: None,
+            focus_order: Vec::new(),
+            press_focused: false,
+            panels: Vec::new(),
+            previous_panels: Vec::new(),
+            top_panel: None
+        }
+    }
+
+    // feeds a window event in, call from on_input_event
+    pub fn handle_event(&mut self, event: &InputEvent) {
+        match event {
+            InputEvent::CursorMoved(_, position) => self.pointer_moved(*position),
+            InputEvent::MouseInput(_, MouseButton::Left, state) => self.pointer_button(*state == ElementState::Pressed),
+            InputEvent::MouseWheel(_, delta) => self.scrolled(*delta),
+            InputEvent::KeyboardInput(_, code) => self.key_pressed(*code),
+            InputEvent::ReceivedText(_, text) => self.text_typed(text),
+            _ => {}
+        }
+    }
+
+    // the methods below feed input in directly, without a window
+
+    pub fn pointer_moved(&mut self, position: Vec2) {
+        self.pending.pointer = Some(position);
+    }
+
+    pub fn pointer_left(&mut self) {
+        self.pending.pointer = None;
+    }
+
+    pub fn pointer_button(&mut self, down: bool) {
+        if down && !self.pending.down {
+            self.pending.pressed = true;
+        }
+        if !down && self.pending.down {
+            self.pending.released = true;
+        }
+        self.pending.down = down;
+    }
+
+    pub fn key_pressed(&mut self, code: KeyCode) {
+        self.pending.typed.push(Typed::Key(code));
+    }
+
+    pub fn text_typed(&mut self, text: &str) {
+        self.pending.typed.push(Typed::Text(text.to_string()));
+    }
+
+    pub fn scrolled(&mut self, delta: Vec2) {
+        self.pending.scroll += delta;
+    }
+
+    // starts a frame of widgets laid out down the viewport, usually the renderer's logical size
+    pub fn begin(&mut self, viewport: Rect) {
+        self.input = self.pending.clone();
+        self.pending.pressed = false;
+        self.pending.released = false;
+        self.pending.typed.clear();
+        self.pending.scroll = Vec2::ZERO;
+
+        self.primitives.clear();
+        self.focus_order.clear();
+        self.press_focused = false;
+        self.hovered = None;
+        self.previous_panels = std::mem::take(&mut self.panels);
+        // panels made later are drawn on top, so the last one under the pointer gets it
+        self.top_panel = self.input.pointer.and_then(|pointer| {
+            self.previous_panels.iter().rev().find(|(_, rect)| rect.contains(pointer)).map(|(id, _)| *id)
+        });
+
+        self.regions.clear();
+        self.regions.push(Region {
+            scope: WidgetId::default(),
+            panel: None,
+            rect: viewport,
+            cursor: viewport.min,
+            direction: Direction::Column,
+            used: None,
+            clip: viewport
+        });
+    }
+
+    pub fn end(&mut self) {
+        self.regions.clear();
+
+        // a press anywhere but a focusable widget takes the focus away, as does its widget going away
+        if self.input.pressed && !self.press_focused {
+            self.focused = None;
+        }
+        if self.focused.is_some_and(|focused| !self.focus_order.contains(&focused)) {
+            self.focused = None;
+        }
+        if self.input.typed.contains(&Typed::Key(KeyCode::Tab)) && !self.focus_order.is_empty() {
+            let next = match self.focused.and_then(|focused| self.focus_order.iter().position(|id| *id == focused)) {
+                Some(index) => (index + 1) % self.focus_order.len(),
+                None => 0
+            };
+            self.focused = Some(self.focus_order[next]);
+        }
+        if self.input.released || !self.input.down {
+            self.active = None;
+        }
+    }
+
+    pub fn primitives(&self) -> &[Primitive] {
+        &self.primitives
+    }
+
+    pub fn focused(&self) -> Option<WidgetId> {
+        self.focused
+    }
+
+    pub fn set_focus(&mut self, id: Option<WidgetId>) {
+        self.focused = id;
+    }
+
+    // the widget under the pointer in the last frame
+    pub fn hovered(&self) -> Option<WidgetId> {
+        self.hovered
+    }
+
+    // the pointer is over a panel or dragging a widget, so the game should leave the mouse alone
+    pub fn wants_pointer(&self) -> bool {
+        self.active.is_some() || self.top_panel.is_some() || self.hovered.is_some()
+    }
+
+    // a widget has the keyboard focus
+    pub fn wants_keyboard(&self) -> bool {
+        self.focused.is_some()
+    }
+
+    // the id a widget with this label gets in the current panel or scroll area
+    pub fn id(&self, label: &str) -> WidgetId {
+        WidgetId::new(self.region().scope, label)
+    }
+
+    fn region(&self) -> &Region {
+        self.regions.last().expect("widgets are made between Ui::begin and Ui::end")
+    }
+
+    fn region_mut(&mut self) -> &mut Region {
+        self.regions.last_mut().expect("widgets are made between Ui::begin and Ui::end")
+    }
+
+    pub fn available_width(&self) -> f32 {
+        let region = self.region();
+        (region.rect.max.x - region.cursor.x).max(0.0)
+    }
+
+    fn line_height(&self) -> f32 {
+        self.theme.font.line_height(self.theme.text_size)
+    }
+
+    fn widget_height(&self) -> f32 {
+        self.line_height() + self.theme.padding.y * 2.0
+    }
+
+    fn text_width(&self, text: &str) -> f32 {
+        self.theme.font.measure(text, self.theme.text_size).x
+    }
+
+    // takes the next size worth of space in the current row or column
+    fn allocate(&mut self, size: Vec2) -> Rect {
+        let spacing = self.theme.spacing;
+        let region = self.region_mut();
+        let rect = Rect { min: region.cursor, max: region.cursor + size.max(Vec2::ZERO) };
+        match region.direction {
+            Direction::Column => region.cursor.y += size.y + spacing,
+            Direction::Row => region.cursor.x += size.x + spacing
+        }
+        region.used = Some(match region.used {
+            Some(used) => Rect { min: used.min.min(rect.min), max: used.max.max(rect.max) },
+            None => rect
+        });
+        rect
+    }
+
+    // empty space before the next widget
+    pub fn space(&mut self, amount: f32) {
+        let region = self.region_mut();
+        match region.direction {
+            Direction::Column => region.cursor.y += amount,
+            Direction::Row => region.cursor.x += amount
+        }
+    }
+
+    fn hit(&self, rect: Rect) -> bool {
+        let region = self.region();
+        self.input.pointer.is_some_and(|pointer| rect.contains(pointer) && region.clip.contains(pointer))
+            && self.top_panel == region.panel
+    }
+
+    fn interact(&mut self, id: WidgetId, rect: Rect, focusable: bool) -> Response {
+        let hovered = self.hit(rect);
+        if hovered {
+            self.hovered = Some(id);
+        }
+        if focusable {
+            self.focus_order.push(id);
+        }
+
+        let mut response = Response { id, rect, hovered, ..Default::default() };
+        if hovered && self.input.pressed {
+            self.active = Some(id);
+            response.pressed = true;
+            if focusable {
+                self.focused = Some(id);
+                self.press_focused = true;
+            }
+        }
+        if self.active == Some(id) && self.input.released {
+            response.clicked = hovered;
+        }
+        response.focused = self.focused == Some(id);
+        response
+    }
+
+    // keys typed while the widget has the focus
+    fn keys(&self, id: WidgetId) -> Vec<KeyCode> {
+        if self.focused != Some(id) {
+            return Vec::new();
+        }
+        self.input.typed.iter().filter_map(|typed| match typed {
+            Typed::Key(code) => Some(*code),
+            Typed::Text(_) => None
+        }).collect()
+    }
+
+    fn fill(&self, response: &Response) -> Color {
+        if self.active == Some(response.id) && response.hovered {
+            self.theme.active
+        } else if response.hovered {
+            self.theme.hovered
+        } else {
+            self.theme.widget
+        }
+    }
+
+    fn draw_rect(&mut self, rect: Rect, color: Color) {
+        self.primitives.push(Primitive::Rect { rect, color, radius: self.theme.corner_radius });
+    }
+
+    fn draw_text(&mut self, position: Vec2, text: &str, color: Color) {
+        if !text.is_empty() {
+            self.primitives.push(Primitive::Text { position, text: text.to_string(), size: self.theme.text_size, color });
+        }
+    }
+
+    // text at the left of rect after the padding, centered vertically
+    fn draw_label(&mut self, rect: Rect, text: &str, color: Color) {
+        let y = rect.min.y + (rect.size().y - self.line_height()) * 0.5;
+        self.draw_text(Vec2::new(rect.min.x + self.theme.padding.x, y), text, color);
+    }
+
+    fn draw_focus(&mut self, response: &Response) {
+        if response.focused {
+            self.primitives.push(Primitive::Outline { rect: response.rect, color: self.theme.accent, radius: self.theme.corner_radius, width: FOCUS_OUTLINE });
+        }
+    }
+
+    fn push_clip(&mut self, rect: Rect) -> Rect {
+        let clip = self.region().clip.intersect(&rect);
+        self.primitives.push(Primitive::PushClip(clip));
+        clip
+    }
+
+    pub fn label(&mut self, text: &str) -> Response {
+        let id = self.id(text);
+        let text = display_text(text);
+        let size = Vec2::new(self.text_width(text) + self.theme.padding.x * 2.0, self.widget_height());
+        let rect = self.allocate(size);
+        self.draw_label(rect, text, self.theme.text);
+        self.interact(id, rect, false)
+    }
+
+    pub fn button(&mut self, label: &str) -> Response {
+        let id = self.id(label);
+        let text = display_text(label);
+        let size = Vec2::new(self.text_width(text) + self.theme.padding.x * 2.0, self.widget_height());
+        let rect = self.allocate(size);
+        let mut response = self.interact(id, rect, true);
+        let keys = self.keys(id);
+        if keys.contains(&KeyCode::Enter) || keys.contains(&KeyCode::Space) {
+            response.clicked = true;
+        }
+
+        self.draw_rect(rect, self.fill(&response));
+        self.draw_label(rect, text, self.theme.text);
+        self.draw_focus(&response);
+        response
+    }
+
+    pub fn checkbox(&mut self, label: &str, checked: &mut bool) -> Response {
+        let id = self.id(label);
+        let text = display_text(label);
+        let height = self.widget_height();
+        let size = Vec2::new(height + self.text_width(text) + self.theme.padding.x, height);
+        let rect = self.allocate(size);
+        let mut response = self.interact(id, rect, true);
+        let keys = self.keys(id);
+        if keys.contains(&KeyCode::Enter) || keys.contains(&KeyCode::Space) {
+            response.clicked = true;
+        }
+        if response.clicked {
+            *checked = !*checked;
+            response.changed = true;
+        }
+
+        let inset = self.theme.padding.y;
+        let check_box = Rect { min: rect.min + inset, max: rect.min + height - inset };
+        self.draw_rect(check_box, self.fill(&response));
+        if *checked {
+            let mark = Rect { min: check_box.min + inset, max: check_box.max - inset };
+            self.draw_rect(mark, self.theme.accent);
+        }
+        let text_rect = Rect { min: Vec2::new(rect.min.x + height - self.theme.padding.x, rect.min.y), max: rect.max };
+        self.draw_label(text_rect, text, self.theme.text);
+        self.draw_focus(&response);
+        response
+    }
+
+    // drag along it or use the arrow keys while it is focused, which step by a hundredth of the range
+    pub fn slider(&mut self, label: &str, value: &mut f32, range: RangeInclusive<f32>) -> Response {
+        let id = self.id(label);
+        let text = display_text(label);
+        let height = self.widget_height();
+        let width = self.theme.field_width.min(self.available_width()).max(height);
+        let rect = self.allocate(Vec2::new(width, height));
+        let mut response = self.interact(id, rect, true);
+
+        let (min, max) = (*range.start(), *range.end());
+        let previous = *value;
+        if self.active == Some(id) {
+            if let Some(pointer) = self.input.pointer {
+                let t = ((pointer.x - rect.min.x) / rect.size().x).clamp(0.0, 1.0);
+                *value = min + (max - min) * t;
+            }
+        }
+        let step = (max - min) / 100.0;
+        for key in self.keys(id) {
+            match key {
+                KeyCode::ArrowLeft => *value -= step,
+                KeyCode::ArrowRight => *value += step,
+                KeyCode::Home => *value = min,
+                KeyCode::End => *value = max,
+                _ => {}
+            }
+        }
+        *value = value.clamp(min.min(max), min.max(max));
+        response.changed = *value != previous;
+
+        let t = if max != min { (*value - min) / (max - min) } else { 0.0 };
+        self.draw_rect(rect, self.theme.field);
+        let fill = Rect { min: rect.min, max: Vec2::new(rect.min.x + rect.size().x * t, rect.max.y) };
+        if !fill.is_empty() {
+            let color = if response.hovered || self.active == Some(id) { self.theme.accent } else { Color { a: 0.8, ..self.theme.accent } };
+            self.draw_rect(fill, color);
+        }
+        let text = if text.is_empty() { format!("{:.2}", value) } else { format!("{}: {:.2}", text, value) };
+        self.draw_label(rect, &text, self.theme.text);
+        self.draw_focus(&response);
+        response
+    }
+
+    // single line, the hint shows while it is empty and unfocused, enter submits and gives up the focus
+    pub fn text_field(&mut self, hint: &str, text: &mut String) -> Response {
+        let id = self.id(hint);
+        let hint = display_text(hint);
+        let height = self.widget_height();
+        let width = self.theme.field_width.min(self.available_width()).max(height);
+        let rect = self.allocate(Vec2::new(width, height));
+        let mut response = self.interact(id, rect, true);
+
+        let size = self.theme.text_size;
+        let inner_width = (width - self.theme.padding.x * 2.0).max(0.0);
+        let length = text.chars().count();
+        let mut caret = self.memory.caret.get(&id).copied().unwrap_or(length).min(length);
+        let offsets = self.theme.font.offsets(text, size);
+        let shift = (offsets[caret] - inner_width).max(0.0);
+
+        // a click puts the caret between the characters nearest the pointer
+        if response.pressed {
+            if let Some(pointer) = self.input.pointer {
+                let x = pointer.x - rect.min.x - self.theme.padding.x + shift;
+                caret = offsets.iter().enumerate()
+                    .min_by(|a, b| (a.1 - x).abs().total_cmp(&(b.1 - x).abs()))
+                    .map(|(index, _)| index)
+                    .unwrap_or(length);
+            }
+        }
+
+        if response.focused {
+            let byte = |text: &String, index: usize| text.char_indices().nth(index).map(|(byte, _)| byte).unwrap_or(text.len());
+            for typed in self.input.typed.clone() {
+                match typed {
+                    Typed::Text(typed) => {
+                        text.insert_str(byte(text, caret), &typed);
+                        caret += typed.chars().count();
+                        response.changed = true;
+                    }
+                    Typed::Key(KeyCode::Backspace) if caret > 0 => {
+                        caret -= 1;
+                        text.remove(byte(text, caret));
+                        response.changed = true;
+                    }
+                    Typed::Key(KeyCode::Delete) if caret < text.chars().count() => {
+                        text.remove(byte(text, caret));
+                        response.changed = true;
+                    }
+                    Typed::Key(KeyCode::ArrowLeft) => caret = caret.saturating_sub(1),
+                    Typed::Key(KeyCode::ArrowRight) => caret = (caret + 1).min(text.chars().count()),
+                    Typed::Key(KeyCode::Home) => caret = 0,
+                    Typed::Key(KeyCode::End) => caret = text.chars().count(),
+                    Typed::Key(KeyCode::Enter | KeyCode::NumpadEnter) => {
+                        response.submitted = true;
+                        self.focused = None;
+                        response.focused = false;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        self.memory.caret.insert(id, caret);
+
+        // keeps the caret in view when the text is wider than the field
+        let offsets = if response.changed { self.theme.font.offsets(text, size) } else { offsets };
+        let shift = (offsets[caret] - inner_width).max(0.0);
+        self.draw_rect(rect, self.theme.field);
+        self.push_clip(rect);
+        let text_rect = Rect { min: Vec2::new(rect.min.x - shift, rect.min.y), max: rect.max };
+        if text.is_empty() && !response.focused {
+            self.draw_label(text_rect, hint, self.theme.weak_text);
+        } else {
+            self.draw_label(text_rect, text, self.theme.text);
+        }
+        if response.focused {
+            let x = rect.min.x + self.theme.padding.x + offsets[caret] - shift;
+            let caret_rect = Rect { min: Vec2::new(x, rect.min.y + self.theme.padding.y), max: Vec2::new(x + 1.0, rect.max.y - self.theme.padding.y) };
+            self.primitives.push(Primitive::Rect { rect: caret_rect, color: self.theme.text, radius: 0.0 });
+        }
+        self.primitives.push(Primitive::PopClip);
+        self.draw_focus(&response);
+        response
+    }
+
+    fn layout(&mut self, direction: Direction, contents: impl FnOnce(&mut Ui)) -> Rect {
+        let parent = *self.region();
+        self.regions.push(Region { direction, used: None, ..parent });
+        contents(self);
+        let region = self.regions.pop().expect("the region pushed above");
+        let size = region.used.map(|used| used.max - parent.cursor).unwrap_or_default();
+        self.allocate(size)
+    }
+
+    // widgets side by side, returns the space they took
+    pub fn row(&mut self, contents: impl FnOnce(&mut Ui)) -> Rect {
+        self.layout(Direction::Row, contents)
+    }
+
+    // widgets one under the other, for stacking inside a row
+    pub fn column(&mut self, contents: impl FnOnce(&mut Ui)) -> Rect {
+        self.layout(Direction::Column, contents)
+    }
+
+    // a window with a title bar at a fixed place, widgets inside are laid out down it and clipped to it
+    pub fn panel(&mut self, title: &str, rect: Rect, contents: impl FnOnce(&mut Ui)) -> Response {
+        let id = self.id(title);
+        let title = display_text(title);
+        self.panels.push((id, rect));
+        let hovered = self.input.pointer.is_some_and(|pointer| rect.contains(pointer)) && self.top_panel == Some(id);
+
+        self.draw_rect(rect, self.theme.panel);
+        let mut top = rect.min.y;
+        if !title.is_empty() {
+            let bar = Rect { min: rect.min, max: Vec2::new(rect.max.x, rect.min.y + self.widget_height()) };
+            self.draw_rect(bar, self.theme.title_bar);
+            self.draw_label(bar, title, self.theme.text);
+            top = bar.max.y;
+        }
+
+        // panels float above the layout and are only clipped to the viewport
+        let viewport = self.regions[0].clip;
+        let clip = viewport.intersect(&rect);
+        self.primitives.push(Primitive::PushClip(clip));
+        let padding = self.theme.padding.x;
+        let start = Vec2::new(rect.min.x + padding, top + padding);
+        self.regions.push(Region {
+            scope: id,
+            panel: Some(id),
+            rect: Rect { min: start, max: rect.max - padding },
+            cursor: start,
+            direction: Direction::Column,
+            used: None,
+            clip
+        });
+        contents(self);
+        self.regions.pop();
+        self.primitives.push(Primitive::PopClip);
+
+        Response { id, rect, hovered, ..Default::default() }
+    }
+
+    // the full width that is left and height tall, scrolled by the mouse wheel or by dragging the bar
+    pub fn scroll_area(&mut self, label: &str, height: f32, contents: impl FnOnce(&mut Ui)) -> Response {
+        let id = self.id(label);
+        let width = self.available_width();
+        let rect = self.allocate(Vec2::new(width, height));
+        let hovered = self.hit(rect);
+
+        let content_height = self.memory.content_height.get(&id).copied().unwrap_or_default();
+        let max_offset = (content_height - height).max(0.0);
+        let mut offset = self.memory.scroll.get(&id).copied().unwrap_or_default().clamp(0.0, max_offset);
+
+        // dragging the bar
+        let track = Rect { min: Vec2::new(rect.max.x - SCROLLBAR_WIDTH, rect.min.y), max: rect.max };
+        let thumb_height = if content_height > 0.0 { (height * height / content_height).clamp(MIN_THUMB_HEIGHT.min(height), height) } else { height };
+        let bar = self.interact(WidgetId::new(id, "scrollbar"), track, false);
+        if self.active == Some(bar.id) && max_offset > 0.0 {
+            if let Some(pointer) = self.input.pointer {
+                let t = (pointer.y - track.min.y - thumb_height * 0.5) / (height - thumb_height).max(1.0);
+                offset = t.clamp(0.0, 1.0) * max_offset;
+            }
+        }
+
+        self.draw_rect(rect, self.theme.field);
+        let clip = self.push_clip(rect);
+        let padding = self.theme.padding;
+        let start = Vec2::new(rect.min.x + padding.x, rect.min.y + padding.y - offset);
+        let panel = self.region().panel;
+        self.regions.push(Region {
+            scope: id,
+            panel,
+            rect: Rect { min: start, max: Vec2::new(rect.max.x - SCROLLBAR_WIDTH - padding.x, f32::INFINITY) },
+            cursor: start,
+            direction: Direction::Column,
+            used: None,
+            clip
+        });
+        contents(self);
+        let region = self.regions.pop().expect("the region pushed above");
+        self.primitives.push(Primitive::PopClip);
+
+        let content_height = region.used.map(|used| used.max.y - start.y + padding.y * 2.0).unwrap_or_default();
+        self.memory.content_height.insert(id, content_height);
+        let max_offset = (content_height - height).max(0.0);
+
+        // areas inside this one finish first, so the innermost one under the pointer takes the wheel
+        if hovered && self.input.scroll.y != 0.0 && max_offset > 0.0 {
+            offset -= self.input.scroll.y;
+            self.input.scroll = Vec2::ZERO;
+        }
+        let offset = offset.clamp(0.0, max_offset);
+        self.memory.scroll.insert(id, offset);
+
+        if max_offset > 0.0 {
+            let y = track.min.y + (height - thumb_height) * offset / max_offset;
+            let thumb = Rect { min: Vec2::new(track.min.x, y), max: Vec2::new(track.max.x, y + thumb_height) };
+            let color = if bar.hovered || self.active == Some(bar.id) { self.theme.accent } else { self.theme.widget };
+            self.draw_rect(thumb, color);
+        }
+
+        Response { id, rect, hovered, ..Default::default() }
+    }
+
+    // draws the last frame's widgets through the renderer in whatever coordinate space it is using
+    pub fn draw(&self, renderer: &mut Renderer) {
+        let (origin, scale) = match renderer.coordinate_space() {
+            CoordinateSpace::Normalized => (Vec2::new(-1.0, 1.0), renderer.logical_pixel() * Vec2::new(1.0, -1.0)),
+            CoordinateSpace::Logical => (Vec2::ZERO, Vec2::ONE),
+            CoordinateSpace::Physical => (Vec2::ZERO, Vec2::splat(renderer.scale_factor()))
+        };
+        let to_space = |point: Vec2| origin + point * scale;
+        let centered = |rect: &Rect| {
+            let center = to_space((rect.min + rect.max) * 0.5);
+            Transform2D::at(center.x, center.y).with_scale(scale)
+        };
+
+        for primitive in &self.primitives {
+            match primitive {
+                Primitive::Rect { rect, color, radius } => {
+                    let radius = radius.min(rect.size().min_element() * 0.5);
+                    if radius > 0.0 {
+                        renderer.draw_rounded_rectangle(centered(rect), rect.size(), CornerRadii::uniform(radius), *color);
+                    } else {
+                        renderer.draw_rectangle(centered(rect), rect.size(), *color);
+                    }
+                }
+                Primitive::Outline { rect, color, radius, width } => {
+                    let radius = radius.min(rect.size().min_element() * 0.5);
+                    renderer.stroke_rounded_rectangle(centered(rect), rect.size(), CornerRadii::uniform(radius), *width, *color);
+                }
+                Primitive::Text { position, text, size, color } => {
+                    let position = to_space(*position);
+                    renderer.draw_text(Transform2D::at(position.x, position.y), text, &self.theme.font, *size, *color);
+                }
+                Primitive::PushClip(rect) => {
+                    renderer.push_clip_rect(Rect::new(to_space(rect.min), to_space(rect.max)));
+                }
+                Primitive::PopClip => {
+                    renderer.pop_clip_rect();
+                }
+            }
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame<R>(ui: &mut Ui, widgets: impl FnOnce(&mut Ui) -> R) -> R {
+        ui.begin(Rect::new(Vec2::ZERO, Vec2::new(800.0, 600.0)));
+        let result = widgets(ui);
+        ui.end();
+        result
+    }
+
+    fn center(rect: Rect) -> Vec2 {
+        (rect.min + rect.max) * 0.5
+    }
+
+    #[test]
+    fn buttons_click_when_released_over_them() {
+        let mut ui = Ui::default();
+        let rect = frame(&mut ui, |ui| ui.button("Go")).rect;
+
+        ui.pointer_moved(center(rect));
+        ui.pointer_button(true);
+        let pressed = frame(&mut ui, |ui| ui.button("Go"));
+        assert!(pressed.hovered && pressed.pressed && !pressed.clicked);
+        assert!(ui.wants_pointer());
+
+        ui.pointer_button(false);
+        assert!(frame(&mut ui, |ui| ui.button("Go")).clicked);
+        assert!(!frame(&mut ui, |ui| ui.button("Go")).clicked);
+    }
+
+    #[test]
+    fn buttons_do_not_click_when_the_press_started_elsewhere() {
+        let mut ui = Ui::default();
+        let rect = frame(&mut ui, |ui| ui.button("Go")).rect;
+
+        ui.pointer_moved(Vec2::new(700.0, 500.0));
+        ui.pointer_button(true);
+        assert!(!frame(&mut ui, |ui| ui.button("Go")).pressed);
+        ui.pointer_moved(center(rect));
+        ui.pointer_button(false);
+        assert!(!frame(&mut ui, |ui| ui.button("Go")).clicked);
+
+        // and not when the press leaves the button before the release
+        ui.pointer_button(true);
+        frame(&mut ui, |ui| ui.button("Go"));
+        ui.pointer_moved(Vec2::new(700.0, 500.0));
+        ui.pointer_button(false);
+        assert!(!frame(&mut ui, |ui| ui.button("Go")).clicked);
+    }
+
+    #[test]
+    fn checkboxes_toggle_from_the_mouse_and_keyboard() {
+        let mut ui = Ui::default();
+        let mut checked = false;
+        let rect = frame(&mut ui, |ui| ui.checkbox("Sound", &mut checked)).rect;
+
+        ui.pointer_moved(center(rect));
+        ui.pointer_button(true);
+        frame(&mut ui, |ui| ui.checkbox("Sound", &mut checked));
+        assert!(!checked);
+        ui.pointer_button(false);
+        let response = frame(&mut ui, |ui| ui.checkbox("Sound", &mut checked));
+        assert!(checked && response.changed && response.focused);
+
+        ui.key_pressed(KeyCode::Space);
+        frame(&mut ui, |ui| ui.checkbox("Sound", &mut checked));
+        assert!(!checked);
+    }
+
+    #[test]
+    fn slider_drags_are_clamped_to_the_range() {
+        let mut ui = Ui::default();
+        let mut value = 5.0;
+        let rect = frame(&mut ui, |ui| ui.slider("Volume", &mut value, 0.0..=10.0)).rect;
+
+        ui.pointer_moved(Vec2::new(rect.min.x + rect.size().x * 0.25, center(rect).y));
+        ui.pointer_button(true);
+        assert!(frame(&mut ui, |ui| ui.slider("Volume", &mut value, 0.0..=10.0)).changed);
+        assert!((value - 2.5).abs() < 1e-4);
+
+        // the drag keeps going outside the slider
+        ui.pointer_moved(Vec2::new(rect.max.x + 300.0, rect.max.y + 100.0));
+        frame(&mut ui, |ui| ui.slider("Volume", &mut value, 0.0..=10.0));
+        assert_eq!(value, 10.0);
+        ui.pointer_moved(Vec2::new(-300.0, 0.0));
+        frame(&mut ui, |ui| ui.slider("Volume", &mut value, 0.0..=10.0));
+        assert_eq!(value, 0.0);
+
+        ui.pointer_button(false);
+        frame(&mut ui, |ui| ui.slider("Volume", &mut value, 0.0..=10.0));
+        ui.pointer_moved(Vec2::new(rect.max.x, center(rect).y));
+        frame(&mut ui, |ui| ui.slider("Volume", &mut value, 0.0..=10.0));
+        assert_eq!(value, 0.0);
+    }
+
+    #[test]
+    fn text_fields_take_focus_cycle_with_tab_and_accept_typing() {
+        let mut ui = Ui::default();
+        let (mut name, mut city) = (String::new(), String::new());
+        let form = |ui: &mut Ui, name: &mut String, city: &mut String| {
+            frame(ui, |ui| (ui.text_field("Name", name), ui.text_field("City", city)))
+        };
+        let (name_field, city_field) = form(&mut ui, &mut name, &mut city);
+        assert_eq!(ui.focused(), None);
+
+        ui.pointer_moved(center(name_field.rect));
+        ui.pointer_button(true);
+        form(&mut ui, &mut name, &mut city);
+        ui.pointer_button(false);
+        ui.text_typed("Ada");
+        let (typed, _) = form(&mut ui, &mut name, &mut city);
+        assert!(typed.focused && typed.changed);
+        assert_eq!(name, "Ada");
+        assert!(ui.wants_keyboard());
+
+        ui.key_pressed(KeyCode::Tab);
+        form(&mut ui, &mut name, &mut city);
+        assert_eq!(ui.focused(), Some(city_field.id));
+        ui.text_typed("Paris");
+        ui.key_pressed(KeyCode::Backspace);
+        form(&mut ui, &mut name, &mut city);
+        assert_eq!((name.as_str(), city.as_str()), ("Ada", "Pari"));
+
+        // tab wraps around to the first field
+        ui.key_pressed(KeyCode::Tab);
+        form(&mut ui, &mut name, &mut city);
+        assert_eq!(ui.focused(), Some(name_field.id));
+
+        ui.key_pressed(KeyCode::Enter);
+        let (submitted, _) = form(&mut ui, &mut name, &mut city);
+        assert!(submitted.submitted);
+        assert_eq!(ui.focused(), None);
+
+        // pressing away from every field drops the focus
+        ui.set_focus(Some(city_field.id));
+        ui.pointer_moved(Vec2::new(700.0, 500.0));
+        ui.pointer_button(true);
+        form(&mut ui, &mut name, &mut city);
+        assert_eq!(ui.focused(), None);
+    }
+}